@@ -0,0 +1,36 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// With the maze_library feature, embed every file in maze_data so the crate
+// does not depend on relative paths at run time.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_MAZE_LIBRARY").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=maze_data");
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let data_dir = Path::new(&manifest_dir).join("maze_data");
+    let mut files: Vec<_> = fs::read_dir(&data_dir)
+        .expect("maze_data directory is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    files.sort();
+
+    let mut code = String::from("pub(crate) static MAZES: &[(&str, &str)] = &[\n");
+    for path in files {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        code += &format!(
+            "    ({:?}, include_str!({:?})),\n",
+            name,
+            path.to_str().unwrap()
+        );
+    }
+    code += "];\n";
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("maze_library.rs");
+    fs::write(out, code).unwrap();
+}
@@ -1,64 +1,957 @@
-use crate::maze::{Compass, Direction, Location, Maze, Position, Wall};
-use crate::path_finder::PathFinder;
+use crate::explain::{Candidate, Decision, Verdict};
+use crate::fusion::{FusionWeights, Sensor, WallFusion};
+use crate::graph::MazeGraph;
+use crate::maze::{Compass, Direction, Location, Maze, Position, Wall, NO_STEP};
+// Kept here as well, most callers know it as adachi::StepMapMode
+pub use crate::maze::StepMapMode;
+use crate::path_finder::{
+    MoveFailure, NavigationError, NavigationStep, Observation, PathFinder, RangeReading,
+};
+#[cfg(feature = "provenance")]
+use crate::provenance::Provenance;
 use log;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 
 // Adachi method
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum StepMapMode {
-    UnexploredAsAbsent,  // Search
-    UnexploredAsPresent, // Shortest path
+// Order in which neighbors with the same step value are considered
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TieBreak {
+    // North, East, South, West
+    Fixed,
+    // Current heading first, then left, right and backward
+    PreferStraight,
+    // Neighbor closest to unexplored walls (see frontier_distance_map)
+    // first, PreferStraight among equally close ones
+    PreferFrontier,
 }
 
+// What to do when an observation contradicts a wall observed earlier
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ConflictPolicy {
+    KeepFirst,
+    // Last observation wins (default)
+    Overwrite,
+    MarkUnexplored,
+    // Ask the callback given to set_conflict_callback
+    Callback,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct WallConflict {
+    pub pos: Position,
+    pub compass: Compass,
+    pub previous: Wall,
+    pub observed: Wall,
+}
+
+/*
+    Step map export formats.
+
+    Csv: one line per maze row, top row (largest y) first like
+    display_step_map, unreachable cells empty:
+    5,4,,2
+    6,,0,1
+
+    Json: {"width":2,"height":2,"goal":{"x":1,"y":0},"steps":[[..],[..]]}
+    with steps indexed [y][x] and null for unreachable cells.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepMapFormat {
+    Csv,
+    Json,
+}
+
+// See Adachi::reachability
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reachability {
+    pub cells: Vec<Position>,
+    pub goal_reachable: bool,
+    pub frontier: Vec<Position>,
+}
+
+// Where a Dijkstra flood fill stops
+#[derive(Clone, Copy)]
+enum FloodLimit {
+    // Robot's value plus a margin
+    Robot(u16),
+    Radius(u16),
+}
+
+#[derive(Serialize)]
+struct StepMapJson<'a> {
+    width: usize,
+    height: usize,
+    goal: Position,
+    steps: &'a [Vec<Option<u16>>],
+}
+
+pub type ConflictCallback = Box<dyn FnMut(&WallConflict) -> Wall + Send>;
+
+// The solver is considered stuck when the last `window` navigate calls
+// discovered no wall and visited at most `max_cells` different cells
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct StallDetection {
+    pub window: usize,
+    pub max_cells: usize,
+}
+
+impl Default for StallDetection {
+    fn default() -> Self {
+        StallDetection {
+            window: 32,
+            max_cells: 4,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct StallReport {
+    pub location: Location,
+    pub steps: usize,
+    // Cells visited during the stall, first visit order
+    pub cells: Vec<Position>,
+}
+
+// What to do about a stall, decided by the stall callback
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StallRecovery {
+    // Return NavigationError::Stuck (default without a callback)
+    Fail,
+    // Ignore it and start watching again
+    Continue,
+    // Set the revisit penalty (see set_revisit_penalty) and continue
+    RaisePenalty(u16),
+}
+
+pub type StallCallback = Box<dyn FnMut(&StallReport) -> StallRecovery + Send>;
+
+// The whole search state can be saved (e.g. to flash after each cell)
+// with to_bytes and restored with from_bytes. The step map is not saved;
+// it is rebuilt on the next navigate. Callbacks are not saved either.
+// The bytes are postcard (see compact) with the postcard feature and
+// JSON without it, so only a build with the same features can read them
+// back.
+#[derive(Serialize, Deserialize)]
 pub struct Adachi {
     location: Location,
     maze: Maze,
+    #[serde(skip)]
     step_map: Vec<Vec<u16>>,
     mode: StepMapMode,
+    tie_break: TieBreak,
+    target: Position,
+    conflict_policy: ConflictPolicy,
+    conflicts: Vec<WallConflict>,
+    #[serde(skip)]
+    conflict_callback: Option<ConflictCallback>,
+    // Heading of the move last returned by navigate
+    #[serde(default)]
+    last_move: Option<Compass>,
+    // Vote accumulator replacing the conflict policy when enabled
+    #[serde(default)]
+    fusion: Option<WallFusion>,
+    // Cells to map completely after first entering them (goal area sweep)
+    #[serde(default)]
+    goal_sweep: Option<Vec<Position>>,
+    #[serde(default)]
+    sweeping: bool,
+    #[serde(default)]
+    sweep_done: bool,
+    // Times navigate was called in each cell during this run
+    #[serde(default)]
+    visits: Vec<Vec<u16>>,
+    // Extra step map cost per earlier visit of a cell
+    #[serde(default)]
+    revisit_penalty: u16,
+    // User costs per cell [y][x], empty if none were set
+    #[serde(default)]
+    cell_penalties: Vec<Vec<u16>>,
+    // Keep-out cells, never entered by any plan
+    #[serde(default)]
+    banned: Vec<Position>,
+    // Margin for set_early_exit
+    #[serde(default)]
+    early_exit: Option<u16>,
+    #[serde(default)]
+    stall_detection: Option<StallDetection>,
+    #[serde(skip)]
+    stall_callback: Option<StallCallback>,
+    // Cells of the navigate calls since the last discovered wall
+    #[serde(default)]
+    stall_history: Vec<Position>,
+    #[serde(default)]
+    last_stall: Option<StallReport>,
+    // Build a Decision on every navigate
+    #[serde(default)]
+    explain: bool,
+    #[serde(skip)]
+    last_decision: Option<Decision>,
+    // Walls turned from unexplored to known, ever
+    #[serde(default)]
+    discoveries: usize,
+    // SafetyMargin cost layer of the mode it was built for, dropped
+    // whenever an unexplored wall changes
+    #[serde(skip)]
+    margin_costs: Option<(StepMapMode, Vec<Vec<u16>>)>,
+    // Run and step that discovered each wall
+    #[cfg(feature = "provenance")]
+    #[serde(default)]
+    provenance: Provenance,
 }
 
 impl Adachi {
-    const NONE: u16 = std::u16::MAX - 1;
+    pub const NONE: u16 = NO_STEP;
     pub fn new(maze: Maze) -> Self {
         Adachi {
-            location: Location {
-                pos: Position { x: 0, y: 0 },
-                dir: Compass::North,
-            },
-            maze: maze,
+            #[cfg(feature = "provenance")]
+            provenance: Provenance::new(maze.get_width(), maze.get_height()),
+            location: maze.get_start(),
+            target: maze.get_goal(),
+            maze,
             step_map: vec![],
             mode: StepMapMode::UnexploredAsAbsent,
+            tie_break: TieBreak::Fixed,
+            conflict_policy: ConflictPolicy::Overwrite,
+            conflicts: vec![],
+            conflict_callback: None,
+            last_move: None,
+            fusion: None,
+            goal_sweep: None,
+            sweeping: false,
+            sweep_done: false,
+            visits: vec![],
+            revisit_penalty: 0,
+            cell_penalties: vec![],
+            banned: vec![],
+            early_exit: None,
+            stall_detection: None,
+            stall_callback: None,
+            stall_history: vec![],
+            last_stall: None,
+            explain: false,
+            last_decision: None,
+            discoveries: 0,
+            margin_costs: None,
+        }
+    }
+
+    // Start a search from a map learned earlier (e.g. the first run)
+    pub fn with_known_maze(maze: Maze, location: Location) -> Self {
+        let mut solver = Adachi::new(maze);
+        solver.location = location;
+        solver
+    }
+
+    // Merge walls known in `known` into the solver's map.
+    // Walls the solver has already observed take precedence.
+    pub fn absorb(&mut self, known: &Maze) -> anyhow::Result<usize> {
+        self.margin_costs = None;
+        self.maze.merge(known).map_err(|e| anyhow::anyhow!(e))
+    }
+
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.conflict_policy = policy;
+    }
+
+    // Used with ConflictPolicy::Callback; returns the wall to store
+    pub fn set_conflict_callback(&mut self, callback: ConflictCallback) {
+        self.conflict_callback = Some(callback);
+    }
+
+    // On the first entry into `area` (usually Maze::center_goal_area), map
+    // every wall of its cells before reporting the goal as reached, so the
+    // fast run does not have to guess the inside of the goal.
+    pub fn set_goal_sweep(&mut self, area: Option<Vec<Position>>) {
+        self.goal_sweep = area;
+        self.sweeping = false;
+        self.sweep_done = false;
+    }
+
+    // Nearest cell of the sweep area that still has an unexplored wall
+    fn next_sweep_cell(&self) -> Option<Position> {
+        let area = self.goal_sweep.as_ref()?;
+        let here = self.location.pos;
+        let graph = self.maze.to_graph(StepMapMode::UnexploredAsAbsent);
+        let distances = graph.distances_from(graph.node(here));
+        area.iter()
+            .filter(|cell| **cell != here)
+            .filter(|cell| {
+                Compass::iter().any(|c| self.maze.get(cell.y, cell.x, c) == Wall::Unexplored)
+            })
+            .filter_map(|cell| distances[graph.node(*cell)].map(|d| (d, *cell)))
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, cell)| cell)
+    }
+
+    // Decide each wall by a (weighted) vote over all its observations
+    // instead of the conflict policy. None goes back to the policy.
+    pub fn set_fusion(&mut self, weights: Option<FusionWeights>) {
+        self.fusion =
+            weights.map(|w| WallFusion::new(self.maze.get_width(), self.maze.get_height(), w));
+    }
+
+    pub fn fusion(&self) -> Option<&WallFusion> {
+        self.fusion.as_ref()
+    }
+
+    // Every conflicting observation since the last clear_conflicts
+    pub fn conflicts(&self) -> &[WallConflict] {
+        &self.conflicts
+    }
+
+    pub fn clear_conflicts(&mut self) {
+        self.conflicts.clear();
+    }
+
+    #[cfg(feature = "provenance")]
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    // None disables stall detection (the default)
+    pub fn set_stall_detection(&mut self, detection: Option<StallDetection>) {
+        self.stall_detection = detection;
+        self.stall_history.clear();
+    }
+
+    // Decides the recovery when a stall is detected
+    pub fn set_stall_callback(&mut self, callback: StallCallback) {
+        self.stall_callback = Some(callback);
+    }
+
+    // The most recent stall, recovered from or not
+    pub fn last_stall(&self) -> Option<&StallReport> {
+        self.last_stall.as_ref()
+    }
+
+    // Keep the reason of every navigate decision (off by default, it costs
+    // an allocation per step). See last_decision.
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+        if !explain {
+            self.last_decision = None;
+        }
+    }
+
+    // Why the last navigate chose its direction, if set_explain is on.
+    // Cleared when a navigate gives no direction.
+    pub fn last_decision(&self) -> Option<&Decision> {
+        self.last_decision.as_ref()
+    }
+
+    fn explain_decision(&self, target: Position, chosen: Compass) -> Decision {
+        let cur = self.location;
+        let step = |y: usize, x: usize| Some(self.step_map[y][x]).filter(|s| *s != Adachi::NONE);
+        let chosen_step = self
+            .maze
+            .get_neighbor_cell(cur.pos.y, cur.pos.x, chosen)
+            .and_then(|(y, x)| step(y, x));
+        let candidates = Compass::iter()
+            .map(|compass| {
+                let wall = self.maze.get(cur.pos.y, cur.pos.x, compass);
+                let neighbor = self.maze.get_neighbor_cell(cur.pos.y, cur.pos.x, compass);
+                let neighbor_step = neighbor.and_then(|(y, x)| step(y, x));
+                let verdict = if compass == chosen {
+                    Verdict::Chosen
+                } else if neighbor.is_none() {
+                    Verdict::OuterWall
+                } else if !self.can_drive(cur.pos, compass) {
+                    Verdict::Wall(wall)
+                } else if neighbor_step.is_none() {
+                    Verdict::Unreachable
+                } else if neighbor_step == chosen_step {
+                    Verdict::TieBreak
+                } else {
+                    Verdict::HigherStep
+                };
+                Candidate {
+                    compass,
+                    wall,
+                    step: neighbor_step,
+                    verdict,
+                }
+            })
+            .collect();
+        Decision {
+            location: cur,
+            target,
+            step: step(cur.pos.y, cur.pos.x),
+            candidates,
+            chosen,
+            direction: cur.dir.get_direction_to(chosen),
+        }
+    }
+
+    // Called once per navigate after the observation is stored
+    fn check_stall(&mut self, discoveries_before: usize) -> Result<(), NavigationError> {
+        let Some(detection) = self.stall_detection else {
+            return Ok(());
+        };
+        if self.discoveries != discoveries_before {
+            self.stall_history.clear();
+        }
+        self.stall_history.push(self.location.pos);
+        if self.stall_history.len() > detection.window {
+            self.stall_history.remove(0);
+        }
+        let mut cells: Vec<Position> = vec![];
+        for pos in &self.stall_history {
+            if !cells.contains(pos) {
+                cells.push(*pos);
+            }
+        }
+        if self.stall_history.len() < detection.window || cells.len() > detection.max_cells {
+            return Ok(());
+        }
+        let report = StallReport {
+            location: self.location,
+            steps: self.stall_history.len(),
+            cells,
+        };
+        log::warn!("Stall detected: {:?}", report);
+        let recovery = match self.stall_callback.as_mut() {
+            Some(callback) => callback(&report),
+            None => StallRecovery::Fail,
+        };
+        let error = NavigationError::Stuck {
+            steps: report.steps as u32,
+            cells: report.cells.len() as u32,
+        };
+        self.last_stall = Some(report);
+        self.stall_history.clear();
+        match recovery {
+            StallRecovery::Fail => Err(error),
+            StallRecovery::Continue => Ok(()),
+            StallRecovery::RaisePenalty(penalty) => {
+                self.revisit_penalty = penalty;
+                Ok(())
+            }
+        }
+    }
+
+    fn store_wall(&mut self, pos: Position, compass: Compass, wall: Wall) {
+        if wall != Wall::Unexplored && self.maze.get(pos.y, pos.x, compass) == Wall::Unexplored {
+            self.discoveries += 1;
+        }
+        if (wall == Wall::Unexplored) != (self.maze.get(pos.y, pos.x, compass) == Wall::Unexplored)
+        {
+            self.margin_costs = None;
+        }
+        #[cfg(feature = "provenance")]
+        if wall != Wall::Unexplored && self.maze.get(pos.y, pos.x, compass) == Wall::Unexplored {
+            self.provenance.record(&self.maze, pos, compass);
+        }
+        self.maze.set(pos.y, pos.x, compass, wall);
+    }
+
+    // Store an observed wall, applying the conflict policy when it
+    // contradicts what was observed before
+    fn observe_wall(&mut self, pos: Position, compass: Compass, observed: Wall, sensor: Sensor) {
+        // Nothing was seen (e.g. a sensor dropout); keep what is known
+        if observed == Wall::Unexplored {
+            return;
+        }
+        let previous = self.maze.get(pos.y, pos.x, compass);
+        if let Some(fusion) = self.fusion.as_mut() {
+            let verdict = fusion.add(pos, compass, observed, sensor);
+            if verdict != Wall::Unexplored {
+                self.store_wall(pos, compass, verdict);
+            }
+            return;
+        }
+        let mut wall = observed;
+        if previous != Wall::Unexplored && observed != Wall::Unexplored && previous != observed {
+            let conflict = WallConflict {
+                pos,
+                compass,
+                previous,
+                observed,
+            };
+            log::warn!("Wall conflict: {:?}", conflict);
+            self.conflicts.push(conflict);
+            wall = match self.conflict_policy {
+                ConflictPolicy::KeepFirst => previous,
+                ConflictPolicy::Overwrite => observed,
+                ConflictPolicy::MarkUnexplored => Wall::Unexplored,
+                ConflictPolicy::Callback => match self.conflict_callback.as_mut() {
+                    Some(callback) => callback(&conflict),
+                    None => observed,
+                },
+            };
+        }
+        self.store_wall(pos, compass, wall);
+    }
+
+    // Record the walls seen from the current location without planning.
+    // navigate does this itself; use it to update the map before choosing
+    // a different target.
+    pub fn observe(&mut self, front: Wall, left: Wall, right: Wall) {
+        let cur_d = self.location.dir;
+        let pos = self.location.pos;
+        self.observe_wall(pos, cur_d.turn(Direction::Forward), front, Sensor::Front);
+        self.observe_wall(pos, cur_d.turn(Direction::Left), left, Sensor::Side);
+        self.observe_wall(pos, cur_d.turn(Direction::Right), right, Sensor::Side);
+    }
+
+    // Record the walls along the heading reported by a long-range sensor
+    pub fn observe_range(&mut self, reading: RangeReading) {
+        let compass = self.location.dir;
+        let mut pos = self.location.pos;
+        for _ in 0..reading.open_cells {
+            self.observe_wall(pos, compass, Wall::Absent, Sensor::Front);
+            match self.maze.get_neighbor_cell(pos.y, pos.x, compass) {
+                Some((y, x)) => pos = Position::new(x, y),
+                None => return,
+            }
         }
+        if reading.wall {
+            self.observe_wall(pos, compass, Wall::Present, Sensor::Front);
+        }
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        #[cfg(feature = "postcard")]
+        return crate::compact::to_postcard(self).map_err(|e| anyhow::anyhow!(e));
+        #[cfg(not(feature = "postcard"))]
+        return Ok(serde_json::to_vec(self)?);
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Adachi> {
+        #[cfg(feature = "postcard")]
+        return crate::compact::from_postcard(bytes).map_err(|e| anyhow::anyhow!(e));
+        #[cfg(not(feature = "postcard"))]
+        return Ok(serde_json::from_slice(bytes)?);
     }
 
     pub fn set_mode(&mut self, mode: StepMapMode) {
         self.mode = mode;
     }
 
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+    }
+
+    // Make each earlier visit of a cell cost `penalty` extra steps, which
+    // keeps the search from oscillating in symmetric regions. 0 disables.
+    // The fast run plan ignores it.
+    pub fn set_revisit_penalty(&mut self, penalty: u16) {
+        self.revisit_penalty = penalty;
+    }
+
+    // Extra cost of leaving `pos` (e.g. a cell where the robot tends to
+    // slip, or a damaged region). Applies to every step map, the fast run
+    // plan and to_graph included. 0 removes it.
+    pub fn set_cell_penalty(&mut self, pos: Position, cost: u16) {
+        let (width, height) = (self.maze.get_width(), self.maze.get_height());
+        if pos.x >= width || pos.y >= height {
+            log::warn!("Cell penalty outside the maze: {:?}", pos);
+            return;
+        }
+        if self.cell_penalties.is_empty() {
+            self.cell_penalties = vec![vec![0; width]; height];
+        }
+        self.cell_penalties[pos.y][pos.x] = cost;
+    }
+
+    pub fn cell_penalty(&self, pos: Position) -> u16 {
+        self.cell_penalties
+            .get(pos.y)
+            .and_then(|row| row.get(pos.x))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn clear_cell_penalties(&mut self) {
+        self.cell_penalties.clear();
+    }
+
+    // Graph of the map for Dijkstra planning (MazeGraph::distances_from),
+    // with the cell penalties on the moves out of each cell
+    pub fn to_graph(&self, mode: StepMapMode) -> MazeGraph {
+        self.maze
+            .to_graph_with_cell_costs(mode, &self.cell_penalties)
+    }
+
+    // Forbid planning through `pos` as if it were walled in, without
+    // touching the map ("what if this corridor were closed", keep-out
+    // zones). The robot can still leave a banned cell it is in. Graph
+    // helpers on the maze (Maze::distance, ...) do not know about bans.
+    pub fn ban_cell(&mut self, pos: Position) {
+        if !self.banned.contains(&pos) {
+            self.banned.push(pos);
+        }
+    }
+
+    pub fn unban_cell(&mut self, pos: Position) {
+        self.banned.retain(|p| *p != pos);
+    }
+
+    pub fn banned_cells(&self) -> &[Position] {
+        &self.banned
+    }
+
+    pub fn clear_banned_cells(&mut self) {
+        self.banned.clear();
+    }
+
+    pub fn visit_count(&self, pos: Position) -> u16 {
+        self.visits
+            .get(pos.y)
+            .and_then(|row| row.get(pos.x))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Visit counts indexed [y][x], empty before the first navigate
+    pub fn visits(&self) -> &[Vec<u16>] {
+        &self.visits
+    }
+
+    // Cells visited at least once, e.g. for render::Markers::visited
+    pub fn visited_cells(&self) -> Vec<Position> {
+        let mut cells = vec![];
+        for (y, row) in self.visits.iter().enumerate() {
+            for (x, count) in row.iter().enumerate() {
+                if *count > 0 {
+                    cells.push(Position::new(x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    fn record_visit(&mut self, pos: Position) {
+        let (width, height) = (self.maze.get_width(), self.maze.get_height());
+        if self.visits.len() != height || self.visits.first().map(|r| r.len()) != Some(width) {
+            self.visits = vec![vec![0; width]; height];
+        }
+        let count = &mut self.visits[pos.y][pos.x];
+        *count = count.saturating_add(1);
+    }
+
+    // Whether navigate may drive from `pos` through its `compass` wall.
+    // Whatever self.mode lets the step map through, the robot itself only
+    // crosses walls it has seen to be absent. choose_compass and
+    // explain_decision both go through here so they cannot disagree.
+    fn can_drive(&self, pos: Position, compass: Compass) -> bool {
+        let known_open = StepMapMode::UnexploredAsPresent;
+        self.maze.can_move(pos.y, pos.x, compass, known_open)
+    }
+
+    // Pick the open neighbor with the smallest step value.
+    // Ties are resolved by the order given by tie_break.
+    fn choose_compass(&self) -> Option<Compass> {
+        let cur = self.location;
+        let frontier = self.frontier_for_tie_break();
+        let candidates = self.tie_break_order(cur.pos, cur.dir, frontier.as_deref());
+
+        let mut min_step = Adachi::NONE;
+        let mut result = None;
+        for compass in candidates {
+            if !self.can_drive(cur.pos, compass) {
+                continue;
+            }
+            if let Some((y, x)) = self.maze.get_neighbor_cell(cur.pos.y, cur.pos.x, compass) {
+                if self.step_map[y][x] < min_step {
+                    min_step = self.step_map[y][x];
+                    result = Some(compass);
+                }
+            }
+        }
+        result
+    }
+
+    // Neighbor order for tie_break at `pos` heading `dir`
+    fn tie_break_order(
+        &self,
+        pos: Position,
+        dir: Compass,
+        frontier: Option<&[Vec<u16>]>,
+    ) -> Vec<Compass> {
+        match self.tie_break {
+            TieBreak::Fixed => Compass::iter().collect(),
+            TieBreak::PreferStraight => Direction::iter().map(|d| dir.turn(d)).collect(),
+            TieBreak::PreferFrontier => {
+                let mut candidates: Vec<Compass> = Direction::iter().map(|d| dir.turn(d)).collect();
+                if let Some(frontier) = frontier {
+                    // Stable sort keeps the PreferStraight order among ties
+                    candidates.sort_by_key(|compass| {
+                        match self.maze.get_neighbor_cell(pos.y, pos.x, *compass) {
+                            Some((y, x)) => frontier[y][x],
+                            None => Adachi::NONE,
+                        }
+                    });
+                }
+                candidates
+            }
+        }
+    }
+
+    fn frontier_for_tie_break(&self) -> Option<Vec<Vec<u16>>> {
+        (self.tie_break == TieBreak::PreferFrontier).then(|| self.frontier_distance_map())
+    }
+
     pub fn get_goal(&self) -> Position {
         self.maze.get_goal()
     }
 
+    // Cell used by navigate_to, e.g. the start for the return trip or
+    // a cell to inspect. Defaults to the goal of the maze.
+    pub fn set_target(&mut self, target: Position) {
+        self.target = target;
+    }
+
+    pub fn get_target(&self) -> Position {
+        self.target
+    }
+
+    // Same as navigate, heading for the target instead of a given goal
+    pub fn navigate_to(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+    ) -> Result<Direction, NavigationError> {
+        self.navigate(front, left, right, self.target)
+    }
+
+    // SafetyMargin cost of leaving each cell, penalty * (margin + 1 - d)
+    // with d the Manhattan distance to the nearest cell with an unexplored
+    // wall. One multi-source BFS over the grid, O(width * height).
+    fn safety_margin_costs(&self, margin: u8, penalty: u16) -> Vec<Vec<u16>> {
+        let (width, height) = (self.maze.get_width(), self.maze.get_height());
+        let mut distance = vec![vec![usize::MAX; width]; height];
+        let mut queue = VecDeque::new();
+        for (y, row) in distance.iter_mut().enumerate() {
+            for (x, d) in row.iter_mut().enumerate() {
+                if Compass::iter().any(|c| self.maze.get(y, x, c) == Wall::Unexplored) {
+                    *d = 0;
+                    queue.push_back((y, x));
+                }
+            }
+        }
+        while let Some((y, x)) = queue.pop_front() {
+            let next = distance[y][x] + 1;
+            if next > margin as usize {
+                continue;
+            }
+            let neighbors = [
+                (y + 1 < height).then(|| (y + 1, x)),
+                y.checked_sub(1).map(|y| (y, x)),
+                (x + 1 < width).then(|| (y, x + 1)),
+                x.checked_sub(1).map(|x| (y, x)),
+            ];
+            for (ny, nx) in neighbors.into_iter().flatten() {
+                if distance[ny][nx] == usize::MAX {
+                    distance[ny][nx] = next;
+                    queue.push_back((ny, nx));
+                }
+            }
+        }
+        distance
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| match *d {
+                        d if d <= margin as usize => {
+                            penalty.saturating_mul(margin as u16 + 1 - d as u16)
+                        }
+                        _ => 0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Build the SafetyMargin layer for the current mode unless the cached
+    // one still applies
+    fn refresh_margin_costs(&mut self) {
+        if let StepMapMode::SafetyMargin { margin, penalty } = self.mode {
+            if !matches!(&self.margin_costs, Some((mode, _)) if *mode == self.mode) {
+                self.margin_costs = Some((self.mode, self.safety_margin_costs(margin, penalty)));
+            }
+        }
+    }
+
+    // Extra cost of leaving each cell: revisit penalty, safety margin and
+    // user penalties
+    fn cell_costs(&self) -> Vec<Vec<u16>> {
+        let (width, height) = (self.maze.get_width(), self.maze.get_height());
+        let mut costs = match (self.mode, &self.margin_costs) {
+            (StepMapMode::SafetyMargin { .. }, Some((mode, cached))) if *mode == self.mode => {
+                cached.clone()
+            }
+            (StepMapMode::SafetyMargin { margin, penalty }, _) => {
+                self.safety_margin_costs(margin, penalty)
+            }
+            _ => vec![vec![0u16; width]; height],
+        };
+        for (row, penalties) in costs.iter_mut().zip(&self.cell_penalties) {
+            for (cost, penalty) in row.iter_mut().zip(penalties) {
+                *cost = cost.saturating_add(*penalty);
+            }
+        }
+        if !self.maze.zones().is_empty() {
+            for (y, row) in costs.iter_mut().enumerate() {
+                for (x, cost) in row.iter_mut().enumerate() {
+                    *cost = cost.saturating_add(self.maze.zone_cost(y, x));
+                }
+            }
+        }
+        if self.revisit_penalty > 0 {
+            for (y, row) in costs.iter_mut().enumerate() {
+                for (x, cost) in row.iter_mut().enumerate() {
+                    let visits = self.visit_count(Position::new(x, y));
+                    *cost = cost.saturating_add(self.revisit_penalty.saturating_mul(visits));
+                }
+            }
+        }
+        costs
+    }
+
+    // Stop navigate's flood fill once every cell up to the robot's value
+    // plus `margin` is final; cells further away stay NONE. navigate only
+    // looks at the robot's neighbors, so margin 0 is enough to pick the
+    // same move. calc_step_map and the fast run plan always compute the
+    // whole map. None (the default) disables it.
+    pub fn set_early_exit(&mut self, margin: Option<u16>) {
+        self.early_exit = margin;
+    }
+
+    // Dijkstra from the goal: values are final in the order they are popped
+    fn flood(
+        &mut self,
+        goal: Position,
+        stop: FloodLimit,
+        cell_costs: &[Vec<u16>],
+        banned: &[Vec<bool>],
+    ) {
+        let robot = self.location.pos;
+        let mut queue = BinaryHeap::new();
+        let mut done = vec![vec![false; self.maze.get_width()]; self.maze.get_height()];
+        queue.push(Reverse((0u16, goal.y, goal.x)));
+        let mut limit = match stop {
+            FloodLimit::Robot(_) => Adachi::NONE,
+            FloodLimit::Radius(radius) => radius,
+        };
+        while let Some(Reverse((step, y, x))) = queue.pop() {
+            if done[y][x] || step > self.step_map[y][x] {
+                continue;
+            }
+            if step > limit {
+                break;
+            }
+            done[y][x] = true;
+            if let (FloodLimit::Robot(margin), true) = (stop, Position::new(x, y) == robot) {
+                limit = step.saturating_add(margin);
+            }
+            for compass in Compass::iter() {
+                let Some((ny, nx)) = self.maze.get_neighbor_cell(y, x, compass) else {
+                    continue;
+                };
+                if banned[ny][nx] {
+                    continue;
+                }
+                // SAFETY: y < height and x < width
+                let wall = unsafe { self.maze.get_unchecked(y, x, compass) };
+                if let Some(cost) = self.mode.cost(wall) {
+                    let next = step
+                        .saturating_add(cost)
+                        .saturating_add(cell_costs[ny][nx])
+                        .min(Adachi::NONE - 1);
+                    if next < self.step_map[ny][nx] {
+                        self.step_map[ny][nx] = next;
+                        queue.push(Reverse((next, ny, nx)));
+                    }
+                }
+            }
+        }
+        // Drop tentative values beyond the limit
+        for (row, done) in self.step_map.iter_mut().zip(done) {
+            for (step, done) in row.iter_mut().zip(done) {
+                if !done {
+                    *step = Adachi::NONE;
+                }
+            }
+        }
+    }
+
     pub fn calc_step_map(&mut self, goal: Position) {
+        self.calc_step_map_limited(goal, None);
+    }
+
+    // Partial step map: only cells at most `radius` steps from `center`
+    // (the robot or a target) get a value, the rest stay NONE. Cheap for
+    // local decisions on small controllers.
+    pub fn calc_step_map_within(&mut self, center: Position, radius: u16) {
+        self.calc_step_map_limited(center, Some(FloodLimit::Radius(radius)));
+    }
+
+    // Nearest cell (by steps from the robot, at most `radius`) that still
+    // has an unexplored wall. Replaces the step map with a partial one.
+    pub fn nearest_frontier_within(&mut self, radius: u16) -> Option<Position> {
+        self.calc_step_map_within(self.location.pos, radius);
+        let maze = &self.maze;
+        (0..maze.get_height())
+            .flat_map(|y| (0..maze.get_width()).map(move |x| Position::new(x, y)))
+            .filter(|p| self.step_map[p.y][p.x] != Adachi::NONE)
+            .filter(|p| Compass::iter().any(|c| maze.get(p.y, p.x, c) == Wall::Unexplored))
+            .min_by_key(|p| self.step_map[p.y][p.x])
+    }
+
+    /*
+        Distance (in steps through known open walls) from every cell to the
+        nearest cell that still has an unexplored wall. Frontier cells are 0,
+        cells with no known route to the frontier are Adachi::NONE. Seeking
+        the goal and covering the map can be balanced with it, e.g. by
+        TieBreak::PreferFrontier or by adding it to the step map.
+    */
+    pub fn frontier_distance_map(&self) -> Vec<Vec<u16>> {
+        let maze = &self.maze;
+        let (width, height) = (maze.get_width(), maze.get_height());
+        let mut map = vec![vec![Adachi::NONE; width]; height];
+        let mut queue: VecDeque<(usize, usize)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .filter(|&(y, x)| {
+                maze.cell_exists(y, x)
+                    && Compass::iter().any(|c| maze.get(y, x, c) == Wall::Unexplored)
+            })
+            .collect();
+        for &(y, x) in &queue {
+            map[y][x] = 0;
+        }
+        let known_open = StepMapMode::UnexploredAsPresent;
+        while let Some((y, x)) = queue.pop_front() {
+            for compass in Compass::iter() {
+                if !maze.can_move(y, x, compass, known_open) {
+                    continue;
+                }
+                if let Some((ny, nx)) = maze.get_neighbor_cell(y, x, compass) {
+                    if map[ny][nx] == Adachi::NONE {
+                        map[ny][nx] = map[y][x] + 1;
+                        queue.push_back((ny, nx));
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    fn calc_step_map_limited(&mut self, goal: Position, limit: Option<FloodLimit>) {
         let mut no_cell_updated: bool;
         no_cell_updated = false;
 
         // step_mapのサイズとmazeのサイズが異なる場合はstep_mapを再確保
-        if self.step_map.is_empty() {
-            self.step_map = vec![vec![Adachi::NONE; self.maze.get_width()]; self.maze.get_height()];
-        } else if self.step_map.len() != self.maze.get_height()
-            && self.step_map[0].len() != self.maze.get_width()
+        if self.step_map.is_empty()
+            || (self.step_map.len() != self.maze.get_height()
+                && self.step_map[0].len() != self.maze.get_width())
         {
             self.step_map = vec![vec![Adachi::NONE; self.maze.get_width()]; self.maze.get_height()];
         }
 
-        let is_wall = match self.mode {
-            StepMapMode::UnexploredAsAbsent => {
-                |wall| wall == Wall::Absent || wall == Wall::Unexplored
-            }
-            StepMapMode::UnexploredAsPresent => |wall| wall == Wall::Absent,
-        };
-
         // Initialize step_map
         for v in self.step_map.iter_mut() {
             for x in v.iter_mut() {
@@ -66,7 +959,28 @@ impl Adachi {
             }
         }
 
+        // Nothing can reach a banned goal; navigate reports TargetBanned
+        if self.banned.contains(&goal) {
+            log::warn!("Goal {:?} is banned", goal);
+            return;
+        }
         self.step_map[goal.y][goal.x] = 0;
+        self.refresh_margin_costs();
+        let cell_costs = self.cell_costs();
+        let cell_cost = |y: usize, x: usize| cell_costs[y][x];
+
+        let mut banned = vec![vec![false; self.maze.get_width()]; self.maze.get_height()];
+        for pos in &self.banned {
+            if let Some(cell) = banned.get_mut(pos.y).and_then(|row| row.get_mut(pos.x)) {
+                *cell = true;
+            }
+        }
+
+        if let Some(limit) = limit {
+            self.flood(goal, limit, &cell_costs, &banned);
+            return;
+        }
+        let is_banned = |y: usize, x: usize| banned[y][x];
 
         // calculate step_map
         while !no_cell_updated {
@@ -75,19 +989,27 @@ impl Adachi {
                 // y
                 for j in 0..self.maze.get_width() {
                     // x
+                    if is_banned(i, j) {
+                        continue;
+                    }
                     for compass in Compass::iter() {
-                        match self.maze.get_neighbor_cell(i, j, compass) {
-                            Some((y, x)) => {
-                                let neighbor = self.step_map[y][x];
-                                let current = self.step_map[i][j];
-                                if is_wall(self.maze.get(i, j, compass)) {
-                                    if current > neighbor + 1 {
-                                        self.step_map[i][j] = neighbor + 1;
-                                        no_cell_updated = false;
-                                    }
+                        if let Some((y, x)) = self.maze.get_neighbor_cell(i, j, compass) {
+                            let neighbor = self.step_map[y][x];
+                            if neighbor == Adachi::NONE {
+                                continue;
+                            }
+                            // SAFETY: i < height and j < width
+                            let wall = unsafe { self.maze.get_unchecked(i, j, compass) };
+                            if let Some(cost) = self.mode.cost(wall) {
+                                let step = neighbor
+                                    .saturating_add(cost)
+                                    .saturating_add(cell_cost(i, j))
+                                    .min(Adachi::NONE - 1);
+                                if self.step_map[i][j] > step {
+                                    self.step_map[i][j] = step;
+                                    no_cell_updated = false;
                                 }
                             }
-                            None => (),
                         }
                     }
                 }
@@ -95,111 +1017,424 @@ impl Adachi {
         }
     }
 
-    pub fn get_step(&self, x: usize, y: usize) -> u16 {
-        self.step_map[y][x]
+    // Plan the fast run from start to goal through known-absent walls only.
+    // Fails if the explored part of the maze has no route to the goal.
+    // The step map mode is restored afterwards, revisit penalties do not apply.
+    // Planned from the solver's cell, the route begins in its heading.
+    pub fn plan_fast_run(
+        &mut self,
+        start: Position,
+        goal: Position,
+    ) -> anyhow::Result<Vec<Position>> {
+        self.plan_fast_run_with(start, goal, StepMapMode::UnexploredAsPresent)
     }
 
-    pub fn display_step_map(&self) -> String {
-        let maze_text = self
+    // Same as plan_fast_run with another mode, e.g. SafetyMargin. The route
+    // must still cross known-absent walls only, so modes that let the step
+    // map through unexplored walls are rejected.
+    pub fn plan_fast_run_with(
+        &mut self,
+        start: Position,
+        goal: Position,
+        plan_mode: StepMapMode,
+    ) -> anyhow::Result<Vec<Position>> {
+        if plan_mode.cost(Wall::Unexplored).is_some() {
+            return Err(anyhow::anyhow!(
+                "Fast run plans need a mode that blocks unexplored walls, got {:?}",
+                plan_mode
+            ));
+        }
+        let inside = |p: Position| p.x < self.maze.get_width() && p.y < self.maze.get_height();
+        if !inside(start) || !inside(goal) {
+            return Err(anyhow::anyhow!(
+                "Fast run from {:?} to {:?} leaves the maze",
+                start,
+                goal
+            ));
+        }
+        let (mode, penalty) = (self.mode, self.revisit_penalty);
+        self.mode = plan_mode;
+        self.revisit_penalty = 0;
+        self.calc_step_map(goal);
+        let cell_costs = self.cell_costs();
+        self.mode = mode;
+        self.revisit_penalty = penalty;
+
+        let reachable = self
+            .try_get_step(start.x, start.y)
+            .filter(|s| *s != Adachi::NONE);
+        if reachable.is_none() {
+            log::error!("No explored route from {:?} to {:?}", start, goal);
+            return Err(anyhow::anyhow!("No explored route to the goal"));
+        }
+
+        // Descend the step map
+        let frontier = self.frontier_for_tie_break();
+        let mut path = vec![start];
+        let mut pos = start;
+        // Heading-based tie breaks start from the mouse's heading when
+        // the plan begins at its cell
+        let mut dir = if start == self.location.pos {
+            self.location.dir
+        } else {
+            self.maze.get_start().dir
+        };
+        while pos != goal {
+            let step = self.step_map[pos.y][pos.x];
+            let candidates = self.tie_break_order(pos, dir, frontier.as_deref());
+            let next = candidates.into_iter().find_map(|compass| {
+                if self.maze.get(pos.y, pos.x, compass) != Wall::Absent {
+                    return None;
+                }
+                // Only known-absent walls, so every move is verified
+                let (y, x) = self.maze.get_neighbor_cell(pos.y, pos.x, compass)?;
+                let cost = self.step_map[y][x] as u32 + 1 + cell_costs[pos.y][pos.x] as u32;
+                (cost == step as u32).then_some((compass, y, x))
+            });
+            let (compass, y, x) = match next {
+                Some(n) => n,
+                None => return Err(anyhow::anyhow!("Step map is inconsistent at {:?}", pos)),
+            };
+            dir = compass;
+            pos = Position { x, y };
+            path.push(pos);
+        }
+        Ok(path)
+    }
+
+    // Drive the search to the target: `sense` is asked for the observation
+    // at each location, every decision is applied to the solver's location
+    // (moves are assumed to succeed). Yields an error once and stops when
+    // no move is possible; ends when the target is reached.
+    pub fn solve_iter<F>(&mut self, sense: F) -> SolveIter<'_, F>
+    where
+        F: FnMut(Location) -> Observation,
+    {
+        SolveIter {
+            solver: self,
+            sense,
+            done: false,
+        }
+    }
+
+    // True when no unexplored wall can lead to a shorter start-goal route:
+    // the optimistic distance (unexplored = absent) equals the distance
+    // through known-absent walls only. Stop searching once this holds.
+    pub fn is_shortest_path_confirmed(&self) -> bool {
+        let (start, goal) = (self.maze.get_start().pos, self.maze.get_goal());
+        let pessimistic = self
             .maze
-            .to_text_data("   ", "---", "???", " ", "|", "?", "+", "   ");
-        let lines = maze_text.lines().collect::<Vec<&str>>();
-
-        let mut result: Vec<String> = vec![];
-
-        let mut index = 0;
-        for i in (0..self.maze.get_height()).rev() {
-            result.push(lines[index].to_string()); // horizontal wall
-            index += 1;
-            let chars = lines[index].to_string().chars().collect::<Vec<char>>(); // vertical wall
-            index += 1;
-            let mut vline = String::new();
-            for j in 0..self.maze.get_width() {
-                let step = self.step_map[i][j];
-                let step_str = if step == Adachi::NONE {
-                    "   ".to_string()
-                } else {
-                    format!("{:3}", step)
-                };
+            .distance(start, goal, StepMapMode::UnexploredAsPresent);
+        pessimistic.is_some()
+            && pessimistic
+                == self
+                    .maze
+                    .distance(start, goal, StepMapMode::UnexploredAsAbsent)
+    }
+
+    // Cells the robot can provably reach from where it is: through walls
+    // confirmed absent only (unexplored = present). Includes the robot cell.
+    pub fn reachable_cells(&self) -> Vec<Position> {
+        let graph = self.maze.to_graph(StepMapMode::UnexploredAsPresent);
+        graph
+            .distances_from(graph.node(self.location.pos))
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.is_some())
+            .map(|(n, _)| graph.position(n))
+            .collect()
+    }
 
-                // lineにcharsのj*4文字目を追加
-                vline.push(chars[j * 4]);
-                // step_strを追加
-                vline.push_str(&step_str);
+    // reachable_cells plus whether `goal` is among them and which of them
+    // border an unexplored wall into unreached cells (exploring there is
+    // the only way to reach more)
+    pub fn reachability(&self, goal: Position) -> Reachability {
+        let cells = self.reachable_cells();
+        let frontier = cells
+            .iter()
+            .filter(|pos| {
+                Compass::iter().any(|compass| {
+                    self.maze.get(pos.y, pos.x, compass) == Wall::Unexplored
+                        && self
+                            .maze
+                            .get_neighbor_cell(pos.y, pos.x, compass)
+                            .is_some_and(|(y, x)| !cells.contains(&Position::new(x, y)))
+                })
+            })
+            .copied()
+            .collect();
+        Reachability {
+            goal_reachable: cells.contains(&goal),
+            cells,
+            frontier,
+        }
+    }
+
+    // Unexplored walls crossed by the optimistic (unexplored = absent)
+    // shortest route from the current location to the target, in route
+    // order. Empty when the route is already fully verified or when there
+    // is no route at all.
+    pub fn unverified_walls_on_best_path(&self) -> Vec<(Position, Compass)> {
+        self.unverified_walls_towards(self.target)
+    }
+
+    // Same as unverified_walls_on_best_path for an arbitrary goal
+    pub fn unverified_walls_towards(&self, goal: Position) -> Vec<(Position, Compass)> {
+        let optimistic = StepMapMode::UnexploredAsAbsent;
+        let graph = self.maze.to_graph(optimistic);
+        let distances = graph.distances_from(graph.node(goal));
+        let frontier = self.frontier_for_tie_break();
+        let mut pos = self.location.pos;
+        let mut dir = self.location.dir;
+        let mut walls = vec![];
+        while let Some(distance) = distances[graph.node(pos)].filter(|d| *d > 0) {
+            let candidates = self.tie_break_order(pos, dir, frontier.as_deref());
+            let next = candidates.into_iter().find_map(|compass| {
+                if !self.maze.can_move(pos.y, pos.x, compass, optimistic) {
+                    return None;
+                }
+                let (y, x) = self.maze.get_neighbor_cell(pos.y, pos.x, compass)?;
+                let next = Position::new(x, y);
+                (distances[graph.node(next)] == Some(distance - 1)).then_some((compass, next))
+            });
+            let Some((compass, next)) = next else {
+                break;
+            };
+            if self.maze.get(pos.y, pos.x, compass) == Wall::Unexplored {
+                walls.push((pos, compass));
             }
-            vline.push_str("| "); // Outwall is always present
-            vline.push_str(i.to_string().as_str()); // y-axis
-            result.push(vline);
+            pos = next;
+            dir = compass;
         }
-        result.push(lines[0].to_string()); // bottom line
-        let mut line = "".to_string();
-        for i in 0..self.maze.get_width() {
-            line.push_str(format!(" {:3}", i).as_str());
+        walls
+    }
+
+    // Panics before the first calc_step_map or outside the maze
+    pub fn get_step(&self, x: usize, y: usize) -> u16 {
+        self.step_map[y][x]
+    }
+
+    // None before the first calc_step_map or outside the maze
+    pub fn try_get_step(&self, x: usize, y: usize) -> Option<u16> {
+        self.step_map.get(y)?.get(x).copied()
+    }
+
+    // The last computed step map indexed [y][x], empty before the first
+    // calc_step_map. Unreachable cells hold Adachi::NONE.
+    pub fn step_map(&self) -> &[Vec<u16>] {
+        &self.step_map
+    }
+
+    // Wall updates of the map, see Maze::subscribe
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<crate::maze::WallChange> {
+        self.maze.subscribe()
+    }
+
+    // Write the last computed step map for plotting tools (see StepMapFormat)
+    pub fn export_step_map(
+        &self,
+        format: StepMapFormat,
+        mut writer: impl std::io::Write,
+    ) -> anyhow::Result<()> {
+        if self.step_map.is_empty() {
+            return Err(anyhow::anyhow!("No step map yet, call calc_step_map first"));
         }
-        result.push(line); // x-axis
+        let steps: Vec<Vec<Option<u16>>> = self
+            .step_map
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|s| (*s != Adachi::NONE).then_some(*s))
+                    .collect()
+            })
+            .collect();
+        match format {
+            StepMapFormat::Csv => {
+                for row in steps.iter().rev() {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|s| s.map(|s| s.to_string()).unwrap_or_default())
+                        .collect();
+                    writeln!(writer, "{}", cells.join(","))?;
+                }
+            }
+            StepMapFormat::Json => {
+                // The cell the map was computed for
+                let goal = (0..self.maze.get_height())
+                    .flat_map(|y| (0..self.maze.get_width()).map(move |x| Position::new(x, y)))
+                    .find(|p| self.step_map[p.y][p.x] == 0)
+                    .unwrap_or(self.target);
+                let json = StepMapJson {
+                    width: self.maze.get_width(),
+                    height: self.maze.get_height(),
+                    goal,
+                    steps: &steps,
+                };
+                serde_json::to_writer(&mut writer, &json)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Plain step map, no markers
+    pub fn display_step_map(&self) -> String {
+        self.display_step_map_markers(&crate::render::Markers {
+            robot: false,
+            goal: false,
+            visited: &[],
+        })
+    }
 
-        result.join("\n")
+    // Same as display_step_map with the robot (direction arrow), the goal
+    // ("G") and the `visited` cells (".") marked
+    pub fn display_step_map_with(&self, visited: &[Position]) -> String {
+        self.display_step_map_markers(&crate::render::Markers {
+            robot: true,
+            goal: true,
+            visited,
+        })
+    }
+
+    fn display_step_map_markers(&self, markers: &crate::render::Markers) -> String {
+        crate::render::render_step_map(
+            self,
+            &crate::render::RenderOptions { color: false },
+            markers,
+        )
     }
 }
 
-impl PathFinder for Adachi {
-    fn navigate(
+pub struct SolveIter<'a, F> {
+    solver: &'a mut Adachi,
+    sense: F,
+    done: bool,
+}
+
+impl<F> Iterator for SolveIter<'_, F>
+where
+    F: FnMut(Location) -> Observation,
+{
+    // Location where the decision was made and the direction taken
+    type Item = Result<(Location, Direction), NavigationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let location = self.solver.location;
+        if self.done || location.pos == self.solver.target {
+            return None;
+        }
+        let observation = (self.sense)(location);
+        let target = self.solver.target;
+        match self.solver.navigate_observed(&observation, target) {
+            Ok(direction) => {
+                let mut next = location;
+                next.turn(direction);
+                next.forward();
+                self.solver.location = next;
+                Some(Ok((location, direction)))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Adachi {
+    /*
+        navigate_step_observed heading for the cell `retarget` picks
+        instead of the goal. It is called once the readings, the range
+        reading included, are recorded, so a finder that chooses its own
+        targets (explorer::TargetedExplorer) sees every reading exactly
+        once. The goal-reached check and the remaining step estimate still
+        refer to `goal`.
+    */
+    pub fn navigate_step_retargeted(
         &mut self,
-        front: Wall,
-        left: Wall,
-        right: Wall,
+        observation: &Observation,
         goal: Position,
-    ) -> anyhow::Result<Direction> {
-        if self.maze.get_goal() == self.location.pos {
+        retarget: impl FnOnce(&Adachi) -> Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        let (front, left, right) = (observation.front, observation.left, observation.right);
+        let pos = self.location.pos;
+        let inside = |p: Position| p.x < self.maze.get_width() && p.y < self.maze.get_height();
+        if !inside(pos) || !inside(goal) {
+            log::error!("Out of the maze: location {:?}, goal {:?}", pos, goal);
+            return Err(NavigationError::OutOfBounds);
+        }
+        for (direction, wall) in [
+            (Direction::Forward, front),
+            (Direction::Left, left),
+            (Direction::Right, right),
+        ] {
+            let compass = self.location.dir.turn(direction);
+            if wall == Wall::Absent && self.maze.get_neighbor_cell(pos.y, pos.x, compass).is_none()
+            {
+                log::error!("Opening in the outer wall at {}", self.location);
+                return Err(NavigationError::InconsistentMap);
+            }
+        }
+        if !self.sweep_done && self.goal_sweep.as_ref().is_some_and(|a| a.contains(&pos)) {
+            self.sweeping = true;
+        }
+        if goal == pos && !self.sweeping {
             log::info!("Goal reached");
-            return Err(anyhow::anyhow!("Goal reached"));
+            return Ok(NavigationStep::goal_reached());
         }
 
-        // Set wall info
-        let cur_x = self.location.pos.x;
-        let cur_y = self.location.pos.y;
+        self.record_visit(pos);
+
+        // Set wall info, range reading included, under this step's number
+        #[cfg(feature = "provenance")]
+        self.provenance.next_step();
+        let discoveries = self.discoveries;
+        if let Some(reading) = observation.ahead {
+            self.observe_range(reading);
+        }
+        self.observe(front, left, right);
+        self.check_stall(discoveries)?;
         let cur_d = self.location.dir;
-        self.maze
-            .set(cur_y, cur_x, cur_d.turn(Direction::Forward), front);
-        self.maze
-            .set(cur_y, cur_x, cur_d.turn(Direction::Left), left);
-        self.maze
-            .set(cur_y, cur_x, cur_d.turn(Direction::Right), right);
 
         // Update step_map
-        self.calc_step_map(goal);
-
-        // 壁がなく、かつステップマップの値が一番小さい方向へ進む
-        let mut min_step = std::u16::MAX;
-        let mut result = None;
-
-        if self.maze.get(cur_y, cur_x, Compass::North) == Wall::Absent {
-            if self.step_map[cur_y + 1][cur_x] < min_step {
-                min_step = self.step_map[cur_y + 1][cur_x];
-                result = Some(Compass::North);
-            }
+        let mut target = retarget(self);
+        if target.x >= self.maze.get_width() || target.y >= self.maze.get_height() {
+            log::error!("Out of the maze: target {:?}", target);
+            return Err(NavigationError::OutOfBounds);
         }
-        if self.maze.get(cur_y, cur_x, Compass::East) == Wall::Absent {
-            if self.step_map[cur_y][cur_x + 1] < min_step {
-                min_step = self.step_map[cur_y][cur_x + 1];
-                result = Some(Compass::East);
+        if self.sweeping {
+            match self.next_sweep_cell() {
+                Some(cell) => target = cell,
+                None => {
+                    log::info!("Goal area sweep done");
+                    self.sweeping = false;
+                    self.sweep_done = true;
+                    if goal == pos {
+                        return Ok(NavigationStep::goal_reached());
+                    }
+                }
             }
         }
-        if self.maze.get(cur_y, cur_x, Compass::South) == Wall::Absent {
-            if self.step_map[cur_y - 1][cur_x] < min_step {
-                min_step = self.step_map[cur_y - 1][cur_x];
-                result = Some(Compass::South);
-            }
+        if self.banned.contains(&target) {
+            log::error!("Target {:?} is banned", target);
+            return Err(NavigationError::TargetBanned);
         }
-        if self.maze.get(cur_y, cur_x, Compass::West) == Wall::Absent {
-            if self.step_map[cur_y][cur_x - 1] < min_step {
-                result = Some(Compass::West);
+        self.calc_step_map_limited(target, self.early_exit.map(FloodLimit::Robot));
+
+        // 壁がなく、かつステップマップの値が一番小さい方向へ進む
+        let result = self.choose_compass();
+        self.last_move = result;
+        self.last_decision = match result {
+            Some(chosen) if self.explain => {
+                let decision = self.explain_decision(target, chosen);
+                log::debug!("{}", decision);
+                Some(decision)
             }
-        }
+            _ => None,
+        };
 
         if result.is_none() {
             log::error!("No path to go");
-            return Err(anyhow::anyhow!("No path to go"));
+            return Err(NavigationError::NoPath);
         }
 
         let result = cur_d.get_direction_to(result.unwrap());
@@ -210,7 +1445,30 @@ impl PathFinder for Adachi {
             Wall::make_wall_detection_log(left, front, right),
             result.to_log()
         );
-        Ok(result)
+        // Only meaningful while heading for the goal itself (not sweeping)
+        let remaining = self.step_map[pos.y][pos.x];
+        let expected = (target == goal && remaining != Adachi::NONE).then_some(remaining);
+        Ok(NavigationStep::go(result, expected))
+    }
+}
+
+impl PathFinder for Adachi {
+    fn navigate_step(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        self.navigate_step_observed(&Observation::new(front, left, right), goal)
+    }
+
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        self.navigate_step_retargeted(observation, goal, |_| goal)
     }
 
     fn get_location(&self) -> Location {
@@ -224,4 +1482,46 @@ impl PathFinder for Adachi {
     fn get_maze(&self) -> &Maze {
         &self.maze
     }
+
+    fn reset(&mut self, keep_map: bool) {
+        if !keep_map {
+            // init() moves the goal back to the center
+            let goal = self.maze.get_goal();
+            self.maze.init();
+            self.maze.set_goal(goal);
+            self.margin_costs = None;
+            if let Some(fusion) = self.fusion.as_mut() {
+                fusion.clear();
+            }
+            #[cfg(feature = "provenance")]
+            self.provenance.clear();
+        }
+        #[cfg(feature = "provenance")]
+        self.provenance.next_run();
+        self.location = self.maze.get_start();
+        self.target = self.maze.get_goal();
+        self.step_map.clear();
+        self.last_move = None;
+        self.last_decision = None;
+        self.sweeping = false;
+        self.sweep_done = false;
+        self.visits.clear();
+        self.stall_history.clear();
+    }
+
+    // A blocked move means the wall in that direction is present after all;
+    // it goes through the conflict policy like any other observation
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        match failure {
+            MoveFailure::Aborted => (),
+            MoveFailure::Blocked => {
+                if let Some(compass) = self.last_move {
+                    self.observe_wall(self.location.pos, compass, Wall::Present, Sensor::Front);
+                }
+            }
+            MoveFailure::Displaced(location) => self.location = location,
+        }
+        log::warn!("Move failed: {:?} at {}", failure, self.location);
+        self.last_move = None;
+    }
 }
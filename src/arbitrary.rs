@@ -0,0 +1,99 @@
+use crate::generator;
+use crate::maze::{Compass, Location, Maze, Position};
+use crate::rng::XorShift64;
+use proptest::prelude::*;
+
+/*
+    proptest strategies (proptest feature). Mazes come from
+    generator::generate: even sizes from 4x4 to 16x16 (so the goal area
+    is the usual 2x2), always solvable, start side wall in place, with a
+    few extra openings so that not every maze is a perfect one.
+    any::<Position>() and any::<Location>() are anywhere on a 32x32 grid;
+    use position_in, location_in or maze_and_location for values that
+    must lie inside a given maze.
+*/
+impl Arbitrary for Maze {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Maze>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (2usize..=8, 2usize..=8, any::<u64>(), 0usize..8)
+            .prop_map(|(w, h, seed, loops)| {
+                let (width, height) = (w * 2, h * 2);
+                let mut rng = XorShift64::new(seed);
+                let mut maze = generator::generate(width, height, &mut rng);
+                let fixed = Maze::new(width, height);
+                for _ in 0..loops {
+                    let (y, x) = (rng.below(height), rng.below(width));
+                    let compass = [Compass::North, Compass::East][rng.below(2)];
+                    if maze.get_neighbor_cell(y, x, compass).is_some()
+                        && fixed.get(y, x, compass) != crate::maze::Wall::Present
+                    {
+                        maze.set(y, x, compass, crate::maze::Wall::Absent);
+                    }
+                }
+                maze
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Compass {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Compass>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Compass::North),
+            Just(Compass::East),
+            Just(Compass::South),
+            Just(Compass::West),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Position {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Position>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0usize..32, 0usize..32)
+            .prop_map(|(x, y)| Position::new(x, y))
+            .boxed()
+    }
+}
+
+impl Arbitrary for Location {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Location>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<Position>(), any::<Compass>())
+            .prop_map(|(pos, dir)| Location::new(pos, dir))
+            .boxed()
+    }
+}
+
+// Cells of a width x height maze
+pub fn position_in(width: usize, height: usize) -> BoxedStrategy<Position> {
+    (0..width, 0..height)
+        .prop_map(|(x, y)| Position::new(x, y))
+        .boxed()
+}
+
+pub fn location_in(width: usize, height: usize) -> BoxedStrategy<Location> {
+    (position_in(width, height), any::<Compass>())
+        .prop_map(|(pos, dir)| Location::new(pos, dir))
+        .boxed()
+}
+
+// A maze and a location inside it
+pub fn maze_and_location() -> BoxedStrategy<(Maze, Location)> {
+    any::<Maze>()
+        .prop_flat_map(|maze| {
+            let location = location_in(maze.get_width(), maze.get_height());
+            (Just(maze), location)
+        })
+        .boxed()
+}
@@ -0,0 +1,160 @@
+use crate::evaluate::{
+    run_episode_with_faults, Episode, Evaluation, EvaluationConfig, FinderFactory,
+};
+use crate::maze::Maze;
+use crate::simulator::FaultConfig;
+use crate::stats::{stats, MazeStats};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// A named exploration policy to compare
+pub struct Strategy<'a> {
+    pub name: &'a str,
+    pub make_finder: &'a FinderFactory<'a>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BatchConfig {
+    pub evaluation: EvaluationConfig,
+    // Runs per maze and strategy, seeds 0..seeds
+    pub seeds: u64,
+    // Fault injection for every run, the seed field is replaced by the
+    // run seed. Without faults the runs of a maze are identical, so one
+    // seed is enough.
+    pub faults: Option<FaultConfig>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            evaluation: EvaluationConfig::default(),
+            seeds: 1,
+            faults: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BatchRow {
+    // Index into the strategies given to simulate_all
+    pub strategy: usize,
+    pub seed: u64,
+    // episode.maze is the index into the mazes
+    pub episode: Episode,
+}
+
+/*
+    Results of simulate_all, one row per (maze, strategy, seed) in that
+    order. CSV output:
+
+    maze,strategy,seed,success,search_steps,fast_run_quality
+    0,adachi,0,true,58,1.0
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultsTable {
+    pub strategies: Vec<String>,
+    pub rows: Vec<BatchRow>,
+}
+
+impl ResultsTable {
+    // Aggregate of one strategy over all mazes and seeds
+    pub fn summary(&self, strategy: usize) -> Evaluation {
+        Evaluation::from_episodes(
+            self.rows
+                .iter()
+                .filter(|row| row.strategy == strategy)
+                .map(|row| row.episode)
+                .collect(),
+        )
+    }
+
+    pub fn write_csv(&self, writer: impl std::io::Write) -> Result<(), String> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let header = [
+            "maze",
+            "strategy",
+            "seed",
+            "success",
+            "search_steps",
+            "fast_run_quality",
+        ];
+        csv_writer.write_record(header).map_err(|e| e.to_string())?;
+        for row in &self.rows {
+            let quality = row
+                .episode
+                .fast_run_quality
+                .map_or(String::new(), |q| q.to_string());
+            csv_writer
+                .write_record([
+                    row.episode.maze.to_string(),
+                    self.strategies[row.strategy].clone(),
+                    row.seed.to_string(),
+                    row.episode.success.to_string(),
+                    row.episode.search_steps.to_string(),
+                    quality,
+                ])
+                .map_err(|e| e.to_string())?;
+        }
+        csv_writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+// `run` on every job in order, on config.threads workers with the rayon
+// feature (see EvaluationConfig::threads)
+#[cfg(feature = "rayon")]
+fn map_jobs<J: Sync, T: Send>(jobs: &[J], threads: usize, run: impl Fn(&J) -> T + Sync) -> Vec<T> {
+    match threads {
+        1 => jobs.iter().map(run).collect(),
+        0 => jobs.par_iter().map(&run).collect(),
+        n => match rayon::ThreadPoolBuilder::new().num_threads(n).build() {
+            Ok(pool) => pool.install(|| jobs.par_iter().map(&run).collect()),
+            Err(e) => {
+                log::warn!("Cannot start {} worker threads: {}", n, e);
+                jobs.par_iter().map(&run).collect()
+            }
+        },
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_jobs<J, T>(jobs: &[J], _threads: usize, run: impl Fn(&J) -> T) -> Vec<T> {
+    jobs.iter().map(run).collect()
+}
+
+/*
+    Search every maze with every strategy, `config.seeds` times each. With
+    the rayon feature the runs are spread over config.evaluation.threads
+    workers; the table is the same either way.
+*/
+pub fn simulate_all(mazes: &[Maze], strategies: &[Strategy], config: &BatchConfig) -> ResultsTable {
+    let seeds = config.seeds;
+    let jobs: Vec<(usize, usize, u64)> = (0..mazes.len())
+        .flat_map(|maze| {
+            (0..strategies.len())
+                .flat_map(move |strategy| (0..seeds).map(move |seed| (maze, strategy, seed)))
+        })
+        .collect();
+    let run = |&(maze, strategy, seed): &(usize, usize, u64)| {
+        let faults = config.faults.map(|faults| FaultConfig { seed, ..faults });
+        BatchRow {
+            strategy,
+            seed,
+            episode: run_episode_with_faults(
+                maze,
+                &mazes[maze],
+                strategies[strategy].make_finder,
+                &config.evaluation,
+                faults,
+            ),
+        }
+    };
+    ResultsTable {
+        strategies: strategies.iter().map(|s| s.name.to_string()).collect(),
+        rows: map_jobs(&jobs, config.evaluation.threads, run),
+    }
+}
+
+// stats::stats of every maze, in parallel with the rayon feature
+pub fn analyze_all(mazes: &[Maze]) -> Vec<MazeStats> {
+    map_jobs(mazes, 0, stats)
+}
@@ -0,0 +1,192 @@
+use crate::framing::FrameDecoder;
+use crate::maze::{Compass, Direction, Location, Maze, Position, Wall};
+use crate::protocol::{Message, Mirror};
+use std::io::Read;
+use std::time::Duration;
+
+// What the robot sends over the link
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BridgeFormat {
+    // Text lines containing the Adachi navigation log,
+    // e.g. "Y: 0, X: 0, Dir:N, Wall:| |, Go:F^"
+    WallLog,
+    // protocol::Message in framing frames
+    Binary,
+}
+
+// One update applied to the mirror
+#[derive(Clone, Debug, PartialEq)]
+pub enum BridgeEvent {
+    // Walls seen from a location (WallLog)
+    Walls {
+        location: Location,
+        left: Wall,
+        front: Wall,
+        right: Wall,
+    },
+    Message(Message),
+}
+
+pub type BridgeCallback = Box<dyn FnMut(&BridgeEvent, &Mirror)>;
+
+// Location and left / front / right walls of one wall detection log line.
+// Anything before "Y:" (timestamps, log level) is ignored.
+pub fn parse_wall_log_line(line: &str) -> Option<(Location, Wall, Wall, Wall)> {
+    let rest = &line[line.find("Y:")? + 2..];
+    let (y, rest) = rest.split_once(',')?;
+    let rest = rest.trim_start().strip_prefix("X:")?;
+    let (x, rest) = rest.split_once(',')?;
+    let rest = rest.trim_start().strip_prefix("Dir:")?;
+    let dir = Compass::try_from(rest.chars().next()?).ok()?;
+    let walls: Vec<char> = rest[rest.find("Wall:")? + 5..].chars().take(3).collect();
+    let wall = |c: char, present: char| match c {
+        ' ' => Some(Wall::Absent),
+        '?' => Some(Wall::Unexplored),
+        c if c == present => Some(Wall::Present),
+        _ => None,
+    };
+    let pos = Position::new(x.trim().parse().ok()?, y.trim().parse().ok()?);
+    Some((
+        Location::new(pos, dir),
+        wall(*walls.first()?, '|')?,
+        wall(*walls.get(1)?, '-')?,
+        wall(*walls.get(2)?, '|')?,
+    ))
+}
+
+/*
+    Host side of a serial link to the robot: keeps a live Maze + Location
+    (a protocol::Mirror) from what the robot sends and calls the callback
+    after every update. feed() takes raw bytes from any source; run() reads
+    them from a serial port.
+*/
+pub struct Bridge {
+    mirror: Mirror,
+    format: BridgeFormat,
+    line: Vec<u8>,
+    decoder: FrameDecoder,
+    callback: Option<BridgeCallback>,
+}
+
+impl Bridge {
+    pub fn new(maze: Maze, format: BridgeFormat) -> Self {
+        Bridge {
+            mirror: Mirror::new(maze),
+            format,
+            line: Vec::new(),
+            decoder: FrameDecoder::default(),
+            callback: None,
+        }
+    }
+
+    pub fn set_callback(&mut self, callback: Option<BridgeCallback>) {
+        self.callback = callback;
+    }
+
+    pub fn mirror(&self) -> &Mirror {
+        &self.mirror
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.mirror.maze
+    }
+
+    pub fn location(&self) -> Location {
+        self.mirror.location
+    }
+
+    fn notify(&mut self, event: BridgeEvent) {
+        if let Some(callback) = self.callback.as_mut() {
+            callback(&event, &self.mirror);
+        }
+    }
+
+    // True if the line was a wall detection log line
+    fn apply_line(&mut self) -> bool {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        let Some((location, left, front, right)) = parse_wall_log_line(&line) else {
+            // Other log output of the robot
+            return false;
+        };
+        let maze = &mut self.mirror.maze;
+        let pos = location.pos;
+        if pos.x >= maze.get_width() || pos.y >= maze.get_height() {
+            log::warn!("Bridge: location outside the maze: {}", line.trim());
+            return false;
+        }
+        for (direction, wall) in [
+            (Direction::Left, left),
+            (Direction::Forward, front),
+            (Direction::Right, right),
+        ] {
+            if wall != Wall::Unexplored {
+                maze.set(pos.y, pos.x, location.dir.turn(direction), wall);
+            }
+        }
+        self.mirror.location = location;
+        self.notify(BridgeEvent::Walls {
+            location,
+            left,
+            front,
+            right,
+        });
+        true
+    }
+
+    // Number of updates applied
+    pub fn feed(&mut self, bytes: &[u8]) -> usize {
+        let mut updates = 0;
+        match self.format {
+            BridgeFormat::WallLog => {
+                for byte in bytes {
+                    if *byte != b'\n' {
+                        self.line.push(*byte);
+                    } else if self.apply_line() {
+                        updates += 1;
+                    }
+                }
+            }
+            BridgeFormat::Binary => {
+                for message in self.decoder.feed(bytes) {
+                    match self.mirror.apply(&message) {
+                        Ok(()) => {
+                            self.notify(BridgeEvent::Message(message));
+                            updates += 1;
+                        }
+                        Err(e) => log::warn!("Bridge: {}", e),
+                    }
+                }
+            }
+        }
+        updates
+    }
+
+    /*
+        Read from the serial port until it closes or `keep_going` returns
+        false (checked after every read timeout and every chunk).
+    */
+    pub fn run(
+        &mut self,
+        port_name: &str,
+        baud_rate: u32,
+        mut keep_going: impl FnMut(&Mirror) -> bool,
+    ) -> Result<(), String> {
+        let mut port = serialport::new(port_name, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .map_err(|e| format!("Cannot open {}: {}", port_name, e))?;
+        let mut buffer = [0u8; 256];
+        while keep_going(&self.mirror) {
+            match port.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n) => {
+                    self.feed(&buffer[..n]);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => (),
+                Err(e) => return Err(format!("Reading {} failed: {}", port_name, e)),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::maze::{Location, Maze, Position, Wall};
+use crate::path_finder::{MoveFailure, NavigationError, NavigationStep, Observation, PathFinder};
+
+/*
+    Tries strategies in order on every move and uses the first one that
+    returns a direction, e.g. targeted exploration -> Adachi -> wall follower.
+
+    Locations are kept in sync across all strategies. Each strategy keeps
+    its own map and only learns walls on the moves it is asked about.
+
+    When every strategy fails the error of the last one is returned;
+    failures() tells which strategies failed on the last move and why.
+*/
+pub struct ChainedPathFinder {
+    strategies: Vec<Box<dyn PathFinder>>,
+    // Strategy that answered the last navigate
+    active: usize,
+    // (strategy index, error) of the strategies that failed on the last move
+    failures: Vec<(usize, NavigationError)>,
+}
+
+impl ChainedPathFinder {
+    pub fn new(strategies: Vec<Box<dyn PathFinder>>) -> Self {
+        assert!(!strategies.is_empty(), "ChainedPathFinder needs a strategy");
+        ChainedPathFinder {
+            strategies,
+            active: 0,
+            failures: vec![],
+        }
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn strategies(&self) -> &[Box<dyn PathFinder>] {
+        &self.strategies
+    }
+
+    // Strategies that failed on the last move, in the order they were tried
+    pub fn failures(&self) -> &[(usize, NavigationError)] {
+        &self.failures
+    }
+}
+
+impl PathFinder for ChainedPathFinder {
+    fn navigate_step(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        self.navigate_step_observed(&Observation::new(front, left, right), goal)
+    }
+
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        self.failures.clear();
+        if goal == self.get_location().pos {
+            return Ok(NavigationStep::goal_reached());
+        }
+        for (index, strategy) in self.strategies.iter_mut().enumerate() {
+            match strategy.navigate_step_observed(observation, goal) {
+                Ok(step) => {
+                    if index != self.active {
+                        log::info!("Strategy {} takes over", index);
+                    }
+                    self.active = index;
+                    return Ok(step);
+                }
+                Err(e) => {
+                    log::warn!("Strategy {} failed: {}", index, e);
+                    self.failures.push((index, e));
+                }
+            }
+        }
+        log::error!("Every strategy in the chain failed");
+        Err(self.failures.last().unwrap().1)
+    }
+
+    fn get_location(&self) -> Location {
+        self.strategies[self.active].get_location()
+    }
+
+    fn set_location(&mut self, location: Location) {
+        for strategy in self.strategies.iter_mut() {
+            strategy.set_location(location);
+        }
+    }
+
+    fn get_maze(&self) -> &Maze {
+        self.strategies[self.active].get_maze()
+    }
+
+    fn reset(&mut self, keep_map: bool) {
+        for strategy in self.strategies.iter_mut() {
+            strategy.reset(keep_map);
+        }
+        self.active = 0;
+        self.failures.clear();
+    }
+
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        self.strategies[self.active].report_move_failure(failure);
+        if let MoveFailure::Displaced(location) = failure {
+            self.set_location(location);
+        }
+    }
+}
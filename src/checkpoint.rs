@@ -0,0 +1,109 @@
+use crate::adachi::Adachi;
+use crate::maze::Position;
+use crate::path_finder::PathFinder;
+use crate::simulator::Simulator;
+use serde::{Deserialize, Serialize};
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/*
+    A simulated search that can be stopped and resumed: simulator (real
+    maze, location, fault RNG), solver (learned maze and settings) and the
+    steps taken so far. Save it with checkpoint() at any point of run_for
+    and continue with resume() in another process:
+
+        let mut sim = Simulation::new(Simulator::new(maze), solver, goal);
+        while !sim.run_for(1000)? {
+            sim.checkpoint("search.ckpt")?;
+        }
+
+    Like Adachi::to_bytes the step map and the callbacks are not saved;
+    everything else, the stall detection history included, continues
+    exactly as without the interruption.
+*/
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    version: u32,
+    simulator: Simulator,
+    solver: Adachi,
+    goal: Position,
+    steps: usize,
+}
+
+impl Simulation {
+    // The simulator is placed at the solver's location
+    pub fn new(mut simulator: Simulator, solver: Adachi, goal: Position) -> Self {
+        simulator.set_location(solver.get_location());
+        Simulation {
+            version: CHECKPOINT_VERSION,
+            simulator,
+            solver,
+            goal,
+            steps: 0,
+        }
+    }
+
+    pub fn simulator(&self) -> &Simulator {
+        &self.simulator
+    }
+
+    pub fn solver(&self) -> &Adachi {
+        &self.solver
+    }
+
+    pub fn goal(&self) -> Position {
+        self.goal
+    }
+
+    // Steps taken so far, over all resumes
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.simulator.get_location().pos == self.goal
+    }
+
+    // At most `steps` more steps; true once the goal is reached
+    pub fn run_for(&mut self, steps: usize) -> Result<bool, String> {
+        for _ in 0..steps {
+            if self.is_done() {
+                break;
+            }
+            self.steps += 1;
+            self.simulator.tick(&mut self.solver, self.goal)?;
+        }
+        Ok(self.is_done())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Simulation, String> {
+        let simulation: Simulation = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        if simulation.version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "Checkpoint version {} is not supported (expected {})",
+                simulation.version, CHECKPOINT_VERSION
+            ));
+        }
+        Ok(simulation)
+    }
+
+    // Written to a temporary file first so a crash never leaves a
+    // half-written checkpoint behind
+    pub fn checkpoint(&self, filename: &str) -> Result<(), String> {
+        let bytes = self.to_bytes()?;
+        let temporary = format!("{}.tmp", filename);
+        std::fs::write(&temporary, bytes)
+            .and_then(|_| std::fs::rename(&temporary, filename))
+            .map_err(|e| format!("Cannot write {}: {}", filename, e))
+    }
+
+    pub fn resume(filename: &str) -> Result<Simulation, String> {
+        let bytes =
+            std::fs::read(filename).map_err(|e| format!("Cannot read {}: {}", filename, e))?;
+        Simulation::from_bytes(&bytes).map_err(|e| format!("{}: {}", filename, e))
+    }
+}
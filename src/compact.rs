@@ -0,0 +1,15 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+/*
+    postcard encoding of anything serializable in this crate (Maze,
+    Location, protocol::Message, ...). Much smaller than JSON: a 16x16
+    maze is about 600 bytes instead of several kilobytes. Not
+    self-describing, both sides must use the same crate version.
+*/
+pub fn to_postcard<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    postcard::to_allocvec(value).map_err(|e| format!("postcard encoding failed: {}", e))
+}
+
+pub fn from_postcard<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    postcard::from_bytes(bytes).map_err(|e| format!("postcard decoding failed: {}", e))
+}
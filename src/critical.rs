@@ -0,0 +1,112 @@
+use crate::maze::StepMapMode;
+use crate::maze::{Compass, Maze, Position, Wall};
+
+/*
+    Critical-wall analysis between the start and the goal of a maze.
+
+    critical_walls: for a fully known maze, walls whose removal would
+    shorten the start-goal distance.
+    rank_unexplored_walls: for a partial maze, how much each unexplored
+    wall decides the optimistic (unexplored = absent) start-goal distance.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallImpact {
+    pub pos: Position,
+    pub compass: Compass,
+    // Distance change caused by the wall. u32::MAX when the wall alone
+    // decides whether the goal can be reached at all.
+    pub impact: u32,
+}
+
+// Interior walls, each listed once as the north or east side of a cell
+fn interior_walls(maze: &Maze) -> Vec<(Position, Compass)> {
+    let mut walls = vec![];
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            if y + 1 < maze.get_height() {
+                walls.push((Position::new(x, y), Compass::North));
+            }
+            if x + 1 < maze.get_width() {
+                walls.push((Position::new(x, y), Compass::East));
+            }
+        }
+    }
+    walls
+}
+
+fn start_goal_distance(maze: &Maze, mode: StepMapMode) -> Option<u16> {
+    maze.distance(maze.get_start().pos, maze.get_goal(), mode)
+}
+
+// Distance with one wall replaced
+fn distance_with(
+    maze: &mut Maze,
+    pos: Position,
+    compass: Compass,
+    wall: Wall,
+    mode: StepMapMode,
+) -> Option<u16> {
+    let original = maze.get(pos.y, pos.x, compass);
+    maze.set(pos.y, pos.x, compass, wall);
+    let distance = start_goal_distance(maze, mode);
+    maze.set(pos.y, pos.x, compass, original);
+    distance
+}
+
+fn sorted(mut impacts: Vec<WallImpact>) -> Vec<WallImpact> {
+    impacts.sort_by_key(|w| std::cmp::Reverse(w.impact));
+    impacts
+}
+
+// Present walls whose removal shortens the start-goal distance, largest
+// gain first. Unexplored walls are treated as present.
+pub fn critical_walls(maze: &Maze) -> Vec<WallImpact> {
+    let mode = StepMapMode::UnexploredAsPresent;
+    let base = start_goal_distance(maze, mode);
+    let mut maze = maze.clone();
+    let mut impacts = vec![];
+    for (pos, compass) in interior_walls(&maze) {
+        if maze.get(pos.y, pos.x, compass) != Wall::Present {
+            continue;
+        }
+        let distance = distance_with(&mut maze, pos, compass, Wall::Absent, mode);
+        let impact = match (base, distance) {
+            (Some(b), Some(d)) if d < b => (b - d) as u32,
+            (None, Some(_)) => u32::MAX,
+            _ => continue,
+        };
+        impacts.push(WallImpact {
+            pos,
+            compass,
+            impact,
+        });
+    }
+    sorted(impacts)
+}
+
+// Unexplored walls ranked by the difference of the optimistic start-goal
+// distance between "wall present" and "wall absent". Walls with no
+// influence are left out. Good candidates for the next exploration target.
+pub fn rank_unexplored_walls(maze: &Maze) -> Vec<WallImpact> {
+    let mode = StepMapMode::UnexploredAsAbsent;
+    let mut maze = maze.clone();
+    let mut impacts = vec![];
+    for (pos, compass) in interior_walls(&maze) {
+        if maze.get(pos.y, pos.x, compass) != Wall::Unexplored {
+            continue;
+        }
+        let open = distance_with(&mut maze, pos, compass, Wall::Absent, mode);
+        let closed = distance_with(&mut maze, pos, compass, Wall::Present, mode);
+        let impact = match (open, closed) {
+            (Some(o), Some(c)) if c > o => (c - o) as u32,
+            (Some(_), None) => u32::MAX,
+            _ => continue,
+        };
+        impacts.push(WallImpact {
+            pos,
+            compass,
+            impact,
+        });
+    }
+    sorted(impacts)
+}
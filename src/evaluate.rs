@@ -0,0 +1,212 @@
+use crate::batch::{simulate_all, BatchConfig, Strategy};
+use crate::fast_run::{fastest_run, TimeModel};
+use crate::generator;
+use crate::maze::Maze;
+use crate::path_finder::PathFinder;
+use crate::rng::XorShift64;
+use crate::simulator::{FaultConfig, Simulator};
+
+// Creates a fresh finder for a maze to be searched. The finder gets the
+// real maze only to read its size, goal and start; it must not peek.
+pub type FinderFactory<'a> = dyn Fn(&Maze) -> Box<dyn PathFinder> + Sync + 'a;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvaluationConfig {
+    // Search step limit per maze
+    pub max_steps: usize,
+    // Worker threads with the rayon feature: 0 for all cores, 1 runs
+    // everything on the calling thread. Without rayon runs are serial.
+    pub threads: usize,
+    // Model the fast run quality is measured with
+    pub time_model: TimeModel,
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        EvaluationConfig {
+            max_steps: 10_000,
+            threads: 0,
+            time_model: TimeModel::default(),
+        }
+    }
+}
+
+// Outcome of the search of one maze
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Episode {
+    pub maze: usize,
+    pub success: bool,
+    // Moves to reach the goal (the step limit on failure)
+    pub search_steps: usize,
+    /*
+        Fastest run time over the real maze divided by the fastest run time
+        over the walls the finder saw, both from fast_run::fastest_run:
+        1.0 when the search found the optimal run, lower when it missed a
+        faster route. None when the search failed or no run exists.
+    */
+    pub fast_run_quality: Option<f64>,
+}
+
+// Mean with a 95 % confidence interval (normal approximation)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub samples: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Estimate {
+    // None without samples
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = if samples.len() > 1 {
+            samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        let std_dev = variance.sqrt();
+        let margin = 1.96 * std_dev / n.sqrt();
+        Some(Estimate {
+            samples: samples.len(),
+            mean,
+            std_dev,
+            ci_low: mean - margin,
+            ci_high: mean + margin,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub episodes: Vec<Episode>,
+    // Wilson score interval, which stays inside [0, 1] for few runs
+    pub success_rate: Estimate,
+    // Over the successful searches only
+    pub search_steps: Option<Estimate>,
+    pub fast_run_quality: Option<Estimate>,
+}
+
+impl Evaluation {
+    // Aggregate episodes from any source (e.g. one strategy of a batch)
+    pub fn from_episodes(episodes: Vec<Episode>) -> Self {
+        let n = episodes.len() as f64;
+        let successes = episodes.iter().filter(|e| e.success).count() as f64;
+        let rate = if n > 0.0 { successes / n } else { 0.0 };
+        let z: f64 = 1.96;
+        let (center, margin) = if n > 0.0 {
+            let denominator = 1.0 + z * z / n;
+            let center = (rate + z * z / (2.0 * n)) / denominator;
+            let margin = z * (rate * (1.0 - rate) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+            (center, margin)
+        } else {
+            (0.0, 0.0)
+        };
+        let success_rate = Estimate {
+            samples: episodes.len(),
+            mean: rate,
+            std_dev: (rate * (1.0 - rate)).sqrt(),
+            ci_low: (center - margin).max(0.0),
+            ci_high: (center + margin).min(1.0),
+        };
+        let steps: Vec<f64> = episodes
+            .iter()
+            .filter(|e| e.success)
+            .map(|e| e.search_steps as f64)
+            .collect();
+        let quality: Vec<f64> = episodes.iter().filter_map(|e| e.fast_run_quality).collect();
+        Evaluation {
+            success_rate,
+            search_steps: Estimate::from_samples(&steps),
+            fast_run_quality: Estimate::from_samples(&quality),
+            episodes,
+        }
+    }
+}
+
+// Search one maze from its start to its goal
+pub fn run_episode(
+    index: usize,
+    maze: &Maze,
+    make_finder: &FinderFactory,
+    config: &EvaluationConfig,
+) -> Episode {
+    run_episode_with_faults(index, maze, make_finder, config, None)
+}
+
+// Same as run_episode with the simulator misbehaving (Simulator::set_faults)
+pub fn run_episode_with_faults(
+    index: usize,
+    maze: &Maze,
+    make_finder: &FinderFactory,
+    config: &EvaluationConfig,
+    faults: Option<FaultConfig>,
+) -> Episode {
+    let mut finder = make_finder(maze);
+    let mut sim = Simulator::new(maze.clone());
+    sim.set_faults(faults);
+    let goal = maze.get_goal();
+    let (success, search_steps) = match sim.run(finder.as_mut(), goal, config.max_steps) {
+        Ok(steps) => (true, steps),
+        Err(e) => {
+            log::info!("Maze {}: search failed: {}", index, e);
+            (false, config.max_steps)
+        }
+    };
+    let fast_run_quality = if success {
+        let start = maze.get_start();
+        let best = fastest_run(maze, start, goal, &config.time_model);
+        let learned = fastest_run(finder.get_maze(), start, goal, &config.time_model);
+        match (best, learned) {
+            (Some(best), Some(learned)) if learned.time > 0.0 => {
+                Some(best.time as f64 / learned.time as f64)
+            }
+            _ => None,
+        }
+    } else {
+        None
+    };
+    Episode {
+        maze: index,
+        success,
+        search_steps,
+        fast_run_quality,
+    }
+}
+
+/*
+    Monte-Carlo evaluation of an exploration policy: one search per maze,
+    results aggregated with confidence intervals. Mazes can come from an
+    archive (maze_data) or from generated_mazes. Runs through
+    batch::simulate_all with a single strategy and seed, so config.threads
+    applies the same way; the episodes are in maze order either way.
+*/
+pub fn evaluate(
+    mazes: &[Maze],
+    make_finder: &FinderFactory,
+    config: &EvaluationConfig,
+) -> Evaluation {
+    let strategy = Strategy {
+        name: "",
+        make_finder,
+    };
+    let batch = BatchConfig {
+        evaluation: *config,
+        seeds: 1,
+        faults: None,
+    };
+    simulate_all(mazes, &[strategy], &batch).summary(0)
+}
+
+// `count` random mazes of one size, reproducible from the seed
+pub fn generated_mazes(count: usize, width: usize, height: usize, seed: u64) -> Vec<Maze> {
+    let mut rng = XorShift64::new(seed);
+    (0..count)
+        .map(|_| generator::generate(width, height, &mut rng))
+        .collect()
+}
@@ -0,0 +1,86 @@
+use crate::maze::{Compass, Direction, Location, Position, Wall};
+use serde::{Deserialize, Serialize};
+
+// Why a neighbor was or was not taken
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Verdict {
+    Chosen,
+    // Edge of the maze (or a blocked cell)
+    OuterWall,
+    // The wall is present, or unexplored and the mode counts it as present
+    Wall(Wall),
+    // Open, but no route from the neighbor to the target
+    Unreachable,
+    // Open, but another neighbor has a smaller step value
+    HigherStep,
+    // Same step value as the chosen one, lost on the tie break order
+    TieBreak,
+}
+
+// One of the four neighbors of the cell a decision was made in
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Candidate {
+    pub compass: Compass,
+    pub wall: Wall,
+    // Step value of the neighbor, None if it has none
+    pub step: Option<u16>,
+    pub verdict: Verdict,
+}
+
+/*
+    Why navigate chose a direction (see Adachi::set_explain). Display gives
+    one line, e.g.
+
+    Y: 1, X: 0, Dir:N step 4 -> L< (W): N wall, E 5 higher, S 3 tie, W 3 chosen
+*/
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Decision {
+    pub location: Location,
+    // Cell the step map was computed for (goal or goal area sweep cell)
+    pub target: Position,
+    pub step: Option<u16>,
+    // North, East, South, West
+    pub candidates: Vec<Candidate>,
+    pub chosen: Compass,
+    pub direction: Direction,
+}
+
+impl Decision {
+    pub fn candidate(&self, compass: Compass) -> Option<&Candidate> {
+        self.candidates.iter().find(|c| c.compass == compass)
+    }
+}
+
+fn step_text(step: Option<u16>) -> String {
+    step.map_or("-".to_string(), |s| s.to_string())
+}
+
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} step {} -> {} ({}):",
+            self.location,
+            step_text(self.step),
+            self.direction.to_log(),
+            self.chosen.to_log()
+        )?;
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            let compass = candidate.compass.to_log();
+            let step = step_text(candidate.step);
+            match candidate.verdict {
+                Verdict::Chosen => write!(f, "{}{} {} chosen", separator, compass, step)?,
+                Verdict::OuterWall => write!(f, "{}{} outer wall", separator, compass)?,
+                Verdict::Wall(Wall::Unexplored) => {
+                    write!(f, "{}{} unexplored wall", separator, compass)?
+                }
+                Verdict::Wall(_) => write!(f, "{}{} wall", separator, compass)?,
+                Verdict::Unreachable => write!(f, "{}{} unreachable", separator, compass)?,
+                Verdict::HigherStep => write!(f, "{}{} {} higher", separator, compass, step)?,
+                Verdict::TieBreak => write!(f, "{}{} {} tie", separator, compass, step)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::adachi::{Adachi, StepMapMode};
+use crate::maze::{Location, Maze, Position, Wall};
+use crate::path_finder::{MoveFailure, NavigationError, NavigationStep, Observation, PathFinder};
+
+/*
+    Targeted exploration: instead of heading for the goal, head for the
+    nearest cell next to an unexplored wall that the optimistic shortest
+    route to the goal relies on. Once that route is fully verified the
+    explorer drives to the goal like plain Adachi.
+*/
+pub struct TargetedExplorer {
+    solver: Adachi,
+    // Cell the last navigate call was heading for
+    current_target: Option<Position>,
+}
+
+impl TargetedExplorer {
+    pub fn new(solver: Adachi) -> Self {
+        TargetedExplorer {
+            solver,
+            current_target: None,
+        }
+    }
+
+    pub fn current_target(&self) -> Option<Position> {
+        self.current_target
+    }
+
+    pub fn solver(&self) -> &Adachi {
+        &self.solver
+    }
+
+    pub fn into_solver(self) -> Adachi {
+        self.solver
+    }
+
+    // Nearest cell (optimistic distance from the robot) on either side of an
+    // unverified wall of the best route. None if the route is verified.
+    fn informative_cell(solver: &Adachi, goal: Position) -> Option<Position> {
+        let maze = solver.get_maze();
+        let here = solver.get_location().pos;
+        let graph = maze.to_graph(StepMapMode::UnexploredAsAbsent);
+        let distances = graph.distances_from(graph.node(here));
+        solver
+            .unverified_walls_towards(goal)
+            .into_iter()
+            .flat_map(|(pos, compass)| {
+                let neighbor = maze
+                    .get_neighbor_cell(pos.y, pos.x, compass)
+                    .map(|(y, x)| Position::new(x, y));
+                [Some(pos), neighbor]
+            })
+            .flatten()
+            .filter(|cell| *cell != here)
+            .filter_map(|cell| distances[graph.node(cell)].map(|d| (d, cell)))
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, cell)| cell)
+    }
+}
+
+impl PathFinder for TargetedExplorer {
+    fn navigate_step(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        self.navigate_step_observed(&Observation::new(front, left, right), goal)
+    }
+
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        if goal == self.solver.get_location().pos {
+            return Ok(NavigationStep::goal_reached());
+        }
+        // The target is picked after the solver recorded the readings
+        let current_target = &mut self.current_target;
+        self.solver
+            .navigate_step_retargeted(observation, goal, |solver| {
+                let target = TargetedExplorer::informative_cell(solver, goal).unwrap_or(goal);
+                *current_target = Some(target);
+                target
+            })
+    }
+
+    fn get_location(&self) -> Location {
+        self.solver.get_location()
+    }
+
+    fn set_location(&mut self, location: Location) {
+        self.solver.set_location(location);
+    }
+
+    fn get_maze(&self) -> &Maze {
+        self.solver.get_maze()
+    }
+
+    fn reset(&mut self, keep_map: bool) {
+        self.solver.reset(keep_map);
+        self.current_target = None;
+    }
+
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        self.solver.report_move_failure(failure);
+    }
+}
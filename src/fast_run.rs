@@ -0,0 +1,440 @@
+use crate::maze::{Compass, Location, Maze, Position, Wall};
+use crate::path::{compass_between, PathError};
+use crate::profiles::Profile;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/*
+    Time model of a fast run. Lengths are in cells, speeds in cells/s.
+    A straight accelerates from turn_speed up to its max speed and brakes
+    back to turn_speed before the next turn (trapezoid profile); a turn
+    takes a fixed time. Runs start and end at turn_speed.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeModel {
+    pub accel: f32,
+    pub max_speed: f32,
+    pub diagonal_max_speed: f32,
+    pub turn_speed: f32,
+    // Orthogonal <-> diagonal
+    pub turn45: f32,
+    // Orthogonal to orthogonal inside a cell
+    pub turn90: f32,
+    // Orthogonal <-> diagonal the other way round
+    pub turn135: f32,
+    // Diagonal to diagonal around a pillar
+    pub turn_v90: f32,
+}
+
+impl Default for TimeModel {
+    fn default() -> Self {
+        // About 4.5 m/s^2, 2.7 m/s, 0.45 m/s in turns with 90 mm cells
+        TimeModel {
+            accel: 50.0,
+            max_speed: 30.0,
+            diagonal_max_speed: 20.0,
+            turn_speed: 5.0,
+            turn45: 0.12,
+            turn90: 0.2,
+            turn135: 0.22,
+            turn_v90: 0.15,
+        }
+    }
+}
+
+impl TimeModel {
+    // Time of a straight of `length` cells between two turns
+    pub fn straight_time(&self, length: f32, diagonal: bool) -> f32 {
+        let v0 = self.turn_speed;
+        let vmax = if diagonal {
+            self.diagonal_max_speed
+        } else {
+            self.max_speed
+        };
+        if vmax <= v0 || self.accel <= 0.0 {
+            return length / v0;
+        }
+        let ramp = (vmax * vmax - v0 * v0) / (2.0 * self.accel);
+        if length >= 2.0 * ramp {
+            2.0 * (vmax - v0) / self.accel + (length - 2.0 * ramp) / vmax
+        } else {
+            let peak = (v0 * v0 + self.accel * length).sqrt();
+            2.0 * (peak - v0) / self.accel
+        }
+    }
+
+    // Turn by `eighths` of a full turn starting on an orthogonal heading
+    // if `orthogonal`, None for turns the run does not use
+    fn turn_time(&self, eighths: i8, orthogonal: bool) -> Option<f32> {
+        match (eighths.abs(), orthogonal) {
+            (1, _) => Some(self.turn45),
+            (2, true) => Some(self.turn90),
+            (2, false) => Some(self.turn_v90),
+            (3, _) => Some(self.turn135),
+            _ => None,
+        }
+    }
+}
+
+// One piece of a fast run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    // Half cells along an orthogonal heading, or wall-to-wall hops
+    // (half a cell diagonal each) along a diagonal
+    Straight { half_steps: usize, diagonal: bool },
+    // Eighths of a full turn, positive to the right (clockwise)
+    Turn { eighths: i8 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FastRun {
+    // Seconds under the time model
+    pub time: f32,
+    pub motions: Vec<Motion>,
+    // Cells entered, start first
+    pub cells: Vec<Position>,
+}
+
+/*
+    Points of the run live on a doubled grid: (2x+1, 2y+1) is the center of
+    cell (x, y), points with one even coordinate are wall midpoints, both
+    even are pillars. Orthogonal moves go center -> wall -> center, diagonal
+    moves hop from wall midpoint to wall midpoint. Headings are eighths of
+    a turn clockwise from North.
+*/
+const HEADINGS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+fn heading_of(compass: Compass) -> usize {
+    match compass {
+        Compass::North => 0,
+        Compass::East => 2,
+        Compass::South => 4,
+        Compass::West => 6,
+    }
+}
+
+struct Grid<'a> {
+    maze: &'a Maze,
+    width: i32,
+    height: i32,
+}
+
+impl Grid<'_> {
+    fn passable(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x > 2 * self.width || y > 2 * self.height {
+            return false;
+        }
+        let (cx, cy) = (x as usize / 2, y as usize / 2);
+        match (x % 2, y % 2) {
+            (1, 1) => self.maze.cell_exists(cy, cx),
+            (1, 0) => {
+                y > 0 && y < 2 * self.height && {
+                    self.maze.get(cy - 1, cx, Compass::North) == Wall::Absent
+                }
+            }
+            (0, 1) => {
+                x > 0 && x < 2 * self.width && {
+                    self.maze.get(cy, cx - 1, Compass::East) == Wall::Absent
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn index(&self, x: i32, y: i32, heading: usize, turned: bool) -> usize {
+        ((y * (2 * self.width + 1) + x) as usize * 8 + heading) * 2 + turned as usize
+    }
+
+    // Cell whose inside the hop from (x, y) along `heading` passes through
+    fn cell_on_hop(&self, x: i32, y: i32, heading: usize) -> Position {
+        let (dx, dy) = HEADINGS[heading];
+        let center = x % 2 == 1 && y % 2 == 1;
+        let (cx, cy) = if heading % 2 == 1 {
+            if x % 2 == 1 {
+                // From a horizontal wall into the cell above or below
+                (x, y + dy)
+            } else {
+                (x + dx, y)
+            }
+        } else if center {
+            // Orthogonal: the center left or the center reached
+            (x, y)
+        } else {
+            (x + dx, y + dy)
+        };
+        Position::new(cx as usize / 2, cy as usize / 2)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    from: usize,
+    motion: Motion,
+}
+
+/*
+    Fastest run from `start` (standing in the cell center) to the center of
+    `goal` over the walls known absent in `maze`, for the given time model:
+    Dijkstra over (point, heading) with whole straights and turns as edges,
+    so acceleration on long straights and diagonal shortcuts are both
+    weighed against the turns they need. U-turns are never used.
+    None if the goal cannot be reached through known open walls.
+*/
+pub fn fastest_run(
+    maze: &Maze,
+    start: Location,
+    goal: Position,
+    model: &TimeModel,
+) -> Option<FastRun> {
+    let grid = Grid {
+        maze,
+        width: maze.get_width() as i32,
+        height: maze.get_height() as i32,
+    };
+    let (sx, sy) = (2 * start.pos.x as i32 + 1, 2 * start.pos.y as i32 + 1);
+    let (gx, gy) = (2 * goal.x as i32 + 1, 2 * goal.y as i32 + 1);
+    if !grid.passable(sx, sy) || !grid.passable(gx, gy) {
+        return None;
+    }
+    let micros = |seconds: f32| (seconds * 1_000_000.0).round() as u64;
+
+    let states = grid.index(2 * grid.width, 2 * grid.height, 7, true) + 1;
+    let mut best = vec![u64::MAX; states];
+    let mut previous: Vec<Option<Step>> = vec![None; states];
+    let mut queue = BinaryHeap::new();
+    let first = heading_of(start.dir);
+    let begin = grid.index(sx, sy, first, false);
+    best[begin] = 0;
+    queue.push(Reverse((0u64, sx, sy, first, false)));
+
+    while let Some(Reverse((time, x, y, heading, turned))) = queue.pop() {
+        let here = grid.index(x, y, heading, turned);
+        if best[here] != time {
+            continue;
+        }
+        if (x, y) == (gx, gy) {
+            return Some(build_run(&grid, &previous, here, time, start));
+        }
+        let mut relax = |time: u64, x: i32, y: i32, heading: usize, turned: bool, motion| {
+            let next = grid.index(x, y, heading, turned);
+            if time < best[next] {
+                best[next] = time;
+                previous[next] = Some(Step { from: here, motion });
+                queue.push(Reverse((time, x, y, heading, turned)));
+            }
+        };
+
+        // Straights of every length
+        let (dx, dy) = HEADINGS[heading];
+        let diagonal = heading % 2 == 1;
+        let half = if diagonal {
+            std::f32::consts::SQRT_2 / 2.0
+        } else {
+            0.5
+        };
+        let mut k = 1;
+        while grid.passable(x + k * dx, y + k * dy) {
+            let length = k as f32 * half;
+            let cost = micros(model.straight_time(length, diagonal));
+            let motion = Motion::Straight {
+                half_steps: k as usize,
+                diagonal,
+            };
+            relax(time + cost, x + k * dx, y + k * dy, heading, false, motion);
+            k += 1;
+        }
+
+        // One turn between two straights, only towards an open point
+        if turned {
+            continue;
+        }
+        for eighths in -3i8..=3 {
+            let new_heading = (heading as i32 + eighths as i32).rem_euclid(8) as usize;
+            let (dx, dy) = HEADINGS[new_heading];
+            let Some(cost) = model.turn_time(eighths, !diagonal) else {
+                continue;
+            };
+            if grid.passable(x + dx, y + dy) {
+                let motion = Motion::Turn { eighths };
+                relax(time + micros(cost), x, y, new_heading, true, motion);
+            }
+        }
+    }
+    None
+}
+
+fn build_run(
+    grid: &Grid,
+    previous: &[Option<Step>],
+    end: usize,
+    time: u64,
+    start: Location,
+) -> FastRun {
+    let mut steps = vec![];
+    let mut state = end;
+    while let Some(step) = previous[state] {
+        steps.push((step.from, step.motion));
+        state = step.from;
+    }
+    steps.reverse();
+
+    // Replay the motions to list the cells
+    let decode = |index: usize| {
+        let point = index / 16;
+        let columns = (2 * grid.width + 1) as usize;
+        (
+            (point % columns) as i32,
+            (point / columns) as i32,
+            (index / 2) % 8,
+        )
+    };
+    let mut cells = vec![start.pos];
+    for (from, motion) in &steps {
+        if let Motion::Straight { half_steps, .. } = motion {
+            let (mut x, mut y, heading) = decode(*from);
+            let (dx, dy) = HEADINGS[heading];
+            for _ in 0..*half_steps {
+                let cell = grid.cell_on_hop(x, y, heading);
+                if cells.last() != Some(&cell) {
+                    cells.push(cell);
+                }
+                x += dx;
+                y += dy;
+            }
+        }
+    }
+    FastRun {
+        time: time as f32 / 1_000_000.0,
+        motions: steps.into_iter().map(|(_, motion)| motion).collect(),
+        cells,
+    }
+}
+
+// How a cell path would be driven under a time model
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathEstimate {
+    // Moves from cell to cell
+    pub length: usize,
+    pub turns: usize,
+    pub diagonal_segments: usize,
+    pub time: f32,
+    pub run: FastRun,
+}
+
+/*
+    Fastest way to drive along `path` (consecutive cells adjacent, no cell
+    twice), starting in the first cell facing the first move: the fast run
+    search restricted to a corridor made of the path cells, so zigzags
+    become diagonals when the model says that is faster. A single cell
+    takes no time.
+*/
+pub fn estimate_path(path: &[Position], model: &TimeModel) -> Result<PathEstimate, PathError> {
+    let still = |pos: Position| FastRun {
+        time: 0.0,
+        motions: vec![],
+        cells: vec![pos],
+    };
+    let Some(&first) = path.first() else {
+        return Ok(PathEstimate {
+            length: 0,
+            turns: 0,
+            diagonal_segments: 0,
+            time: 0.0,
+            run: still(Position::new(0, 0)),
+        });
+    };
+    for (index, pos) in path.iter().enumerate() {
+        if path[..index].contains(pos) {
+            return Err(PathError::Revisited { index, pos: *pos });
+        }
+    }
+    let width = path.iter().map(|p| p.x).max().unwrap_or(0) + 1;
+    let height = path.iter().map(|p| p.y).max().unwrap_or(0) + 1;
+    let mut corridor = Maze::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            for compass in [Compass::North, Compass::East] {
+                corridor.set(y, x, compass, Wall::Present);
+            }
+        }
+    }
+    let mut heading = None;
+    for (index, pair) in path.windows(2).enumerate() {
+        let (from, to) = (pair[0], pair[1]);
+        let compass = compass_between(from, to).ok_or(PathError::NotAdjacent {
+            index: index + 1,
+            from,
+            to,
+        })?;
+        corridor.set(from.y, from.x, compass, Wall::Absent);
+        heading.get_or_insert(compass);
+    }
+    let last = path[path.len() - 1];
+    let run = match heading {
+        None => still(first),
+        // A path turning back on itself can need another start heading,
+        // the run search never makes U-turns
+        Some(heading) => std::iter::once(heading)
+            .chain(Compass::iter())
+            .find_map(|heading| fastest_run(&corridor, Location::new(first, heading), last, model))
+            .ok_or(PathError::NoRun {
+                from: first,
+                to: last,
+            })?,
+    };
+    Ok(PathEstimate {
+        length: path.len() - 1,
+        turns: run
+            .motions
+            .iter()
+            .filter(|m| matches!(m, Motion::Turn { .. }))
+            .count(),
+        diagonal_segments: run
+            .motions
+            .iter()
+            .filter(|m| matches!(m, Motion::Straight { diagonal: true, .. }))
+            .count(),
+        time: run.time,
+        run,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathComparison {
+    pub a: PathEstimate,
+    pub b: PathEstimate,
+}
+
+impl PathComparison {
+    // Seconds saved by taking `a` instead of `b` (negative if b is faster)
+    pub fn time_saved_by_a(&self) -> f32 {
+        self.b.time - self.a.time
+    }
+
+    pub fn a_is_faster(&self) -> bool {
+        self.a.time < self.b.time
+    }
+}
+
+// Estimate two candidate routes (e.g. from k_shortest_paths) with the
+// time model of `profile`
+pub fn compare_paths(
+    a: &[Position],
+    b: &[Position],
+    profile: &Profile,
+) -> Result<PathComparison, PathError> {
+    let model = profile.time_model();
+    Ok(PathComparison {
+        a: estimate_path(a, &model)?,
+        b: estimate_path(b, &model)?,
+    })
+}
@@ -0,0 +1,167 @@
+use crate::protocol::Message;
+
+/*
+    Framing of protocol messages for noisy serial links:
+
+      COBS(encoded message + CRC16 big endian) 0x00
+
+    COBS removes every zero byte from the frame, so 0x00 only ever marks
+    the end of a frame and a decoder that lost bytes resynchronizes at the
+    next one. The CRC is CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF).
+*/
+
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+pub fn cobs_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 254 + 2);
+    let mut code_index = 0;
+    out.push(0);
+    let mut code = 1u8;
+    for byte in bytes {
+        if *byte != 0 {
+            out.push(*byte);
+            code += 1;
+        }
+        if *byte == 0 || code == 0xFF {
+            out[code_index] = code;
+            code_index = out.len();
+            out.push(0);
+            code = 1;
+        }
+    }
+    out[code_index] = code;
+    out
+}
+
+pub fn cobs_decode(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes[i] as usize;
+        if code == 0 || i + code > bytes.len() {
+            return Err(format!("Invalid COBS code {} at {}", code, i));
+        }
+        out.extend_from_slice(&bytes[i + 1..i + code]);
+        i += code;
+        if code < 0xFF && i < bytes.len() {
+            out.push(0);
+        }
+    }
+    Ok(out)
+}
+
+// Complete frame, delimiter included
+pub fn encode_frame(message: &Message) -> Result<Vec<u8>, String> {
+    let mut payload = message.encode()?;
+    payload.extend_from_slice(&crc16(&payload).to_be_bytes());
+    let mut frame = cobs_encode(&payload);
+    frame.push(0);
+    Ok(frame)
+}
+
+// One frame without the delimiter
+pub fn decode_frame(frame: &[u8]) -> Result<Message, String> {
+    let payload = cobs_decode(frame)?;
+    if payload.len() < 3 {
+        return Err(format!("Frame too short ({} bytes)", payload.len()));
+    }
+    let (body, crc) = payload.split_at(payload.len() - 2);
+    let expected = u16::from_be_bytes([crc[0], crc[1]]);
+    if crc16(body) != expected {
+        return Err(format!(
+            "CRC mismatch: {:04X} != {:04X}",
+            crc16(body),
+            expected
+        ));
+    }
+    Message::decode(body)
+}
+
+/*
+    Streaming decoder: feed it whatever arrives on the link. Broken frames
+    (bad CRC, bad COBS, unknown message, longer than max_frame) are dropped
+    and counted, decoding continues with the next frame.
+*/
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    max_frame: usize,
+    // Skipping an oversized frame until its delimiter
+    overflow: bool,
+    dropped: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(max_frame: usize) -> Self {
+        FrameDecoder {
+            buffer: Vec::new(),
+            max_frame,
+            overflow: false,
+            dropped: 0,
+        }
+    }
+
+    // Frames dropped so far
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    // A message (or the reason the frame was dropped) when `byte` ends a frame
+    pub fn push(&mut self, byte: u8) -> Option<Result<Message, String>> {
+        if byte != 0 {
+            if self.buffer.len() < self.max_frame {
+                self.buffer.push(byte);
+            } else {
+                self.overflow = true;
+            }
+            return None;
+        }
+        let result = if self.overflow {
+            Some(Err(format!("Frame longer than {} bytes", self.max_frame)))
+        } else if self.buffer.is_empty() {
+            // Back to back delimiters, e.g. sent to flush the line
+            None
+        } else {
+            Some(decode_frame(&self.buffer))
+        };
+        self.buffer.clear();
+        self.overflow = false;
+        if let Some(Err(_)) = result {
+            self.dropped += 1;
+        }
+        result
+    }
+
+    // Good messages in `bytes`; a partial frame at the end is kept
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Message> {
+        bytes
+            .iter()
+            .filter_map(|byte| match self.push(*byte)? {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    log::warn!("Dropped frame: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        // A 32x32 snapshot with framing overhead
+        FrameDecoder::new(1024)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::maze::{Compass, Position, Wall};
+use serde::{Deserialize, Serialize};
+
+// Which sensor made an observation. Long-range readings and bumps count
+// as front observations.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Sensor {
+    Front,
+    Side,
+}
+
+// Vote weight per sensor; equal weights make it a plain majority vote
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct FusionWeights {
+    pub front: u16,
+    pub side: u16,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        FusionWeights { front: 1, side: 1 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct WallVotes {
+    pub present: u32,
+    pub absent: u32,
+}
+
+/*
+    Accumulates every observation of every wall across visits. The verdict
+    is the side with the larger total weight; on a tie the observation just
+    made wins, so with a single observation it behaves like Overwrite.
+
+    Votes are stored per wall in the same layout as the wall list:
+    horizontal walls (south side of cell (x, y)) first, then vertical walls
+    (west side of cell (x, y)).
+*/
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct WallFusion {
+    width: usize,
+    height: usize,
+    weights: FusionWeights,
+    votes: Vec<WallVotes>,
+}
+
+impl WallFusion {
+    pub fn new(width: usize, height: usize, weights: FusionWeights) -> Self {
+        WallFusion {
+            width,
+            height,
+            weights,
+            votes: vec![WallVotes::default(); (height + 1) * width + height * (width + 1)],
+        }
+    }
+
+    fn index(&self, pos: Position, compass: Compass) -> usize {
+        let vertical_base = (self.height + 1) * self.width;
+        match compass {
+            Compass::South => pos.y * self.width + pos.x,
+            Compass::North => (pos.y + 1) * self.width + pos.x,
+            Compass::West => vertical_base + pos.y * (self.width + 1) + pos.x,
+            Compass::East => vertical_base + pos.y * (self.width + 1) + pos.x + 1,
+        }
+    }
+
+    pub fn votes(&self, pos: Position, compass: Compass) -> WallVotes {
+        self.votes[self.index(pos, compass)]
+    }
+
+    // Add one observation and return the verdict for that wall.
+    // Unexplored observations carry no vote.
+    pub fn add(&mut self, pos: Position, compass: Compass, observed: Wall, sensor: Sensor) -> Wall {
+        let weight = match sensor {
+            Sensor::Front => self.weights.front,
+            Sensor::Side => self.weights.side,
+        } as u32;
+        let index = self.index(pos, compass);
+        let votes = &mut self.votes[index];
+        match observed {
+            Wall::Present => votes.present += weight,
+            Wall::Absent => votes.absent += weight,
+            Wall::Unexplored => return Wall::Unexplored,
+        }
+        if votes.present > votes.absent {
+            Wall::Present
+        } else if votes.absent > votes.present {
+            Wall::Absent
+        } else {
+            observed
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.votes.fill(WallVotes::default());
+    }
+}
@@ -0,0 +1,253 @@
+use crate::maze::StepMapMode;
+use crate::maze::{Compass, Maze, Position, Wall};
+use crate::pillar::cell_to_pillar;
+use crate::rng::XorShift64;
+use crate::shortest_paths::count_shortest_paths;
+use crate::similarity;
+
+// Inner walls that are present in a freshly initialized maze (the start
+// side wall); the generator never removes them
+fn fixed_walls(maze: &Maze) -> Vec<(Position, Compass)> {
+    let mut fixed = vec![];
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            for compass in Compass::iter() {
+                if maze.get(y, x, compass) == Wall::Present
+                    && maze.get_neighbor_cell(y, x, compass).is_some()
+                {
+                    fixed.push((Position::new(x, y), compass));
+                }
+            }
+        }
+    }
+    fixed
+}
+
+fn is_fixed(fixed: &[(Position, Compass)], pos: Position, compass: Compass) -> bool {
+    fixed.contains(&(pos, compass))
+}
+
+/*
+    Random perfect maze (exactly one route between any two cells) made by
+    a depth-first search from the start, then the goal area
+    (Maze::center_goal_area) is opened up inside. The start side wall of
+    Maze::new is kept. Same seed, same maze.
+*/
+pub fn generate(width: usize, height: usize, rng: &mut XorShift64) -> Maze {
+    let mut maze = Maze::new(width, height);
+    let fixed = fixed_walls(&maze);
+    for y in 0..height {
+        for x in 0..width {
+            for compass in Compass::iter() {
+                if maze.get(y, x, compass) == Wall::Unexplored {
+                    maze.set(y, x, compass, Wall::Present);
+                }
+            }
+        }
+    }
+
+    let mut visited = vec![vec![false; width]; height];
+    let start = maze.get_start().pos;
+    visited[start.y][start.x] = true;
+    let mut stack = vec![start];
+    while let Some(&pos) = stack.last() {
+        let options: Vec<(Compass, Position)> = Compass::iter()
+            .filter(|c| !is_fixed(&fixed, pos, *c))
+            .filter_map(|c| {
+                let (y, x) = maze.get_neighbor_cell(pos.y, pos.x, c)?;
+                (!visited[y][x]).then_some((c, Position::new(x, y)))
+            })
+            .collect();
+        if options.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (compass, next) = options[rng.below(options.len())];
+        maze.set(pos.y, pos.x, compass, Wall::Absent);
+        visited[next.y][next.x] = true;
+        stack.push(next);
+    }
+
+    let area = maze.center_goal_area();
+    for a in &area {
+        for compass in Compass::iter() {
+            if let Some((y, x)) = maze.get_neighbor_cell(a.y, a.x, compass) {
+                if area.contains(&Position::new(x, y)) {
+                    maze.set(a.y, a.x, compass, Wall::Absent);
+                }
+            }
+        }
+    }
+    maze
+}
+
+// Properties a generated maze must have. None / 0 means no constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Constraints {
+    // Inclusive range of the start-goal distance in steps
+    pub shortest_path: Option<(u16, u16)>,
+    // Distinct shortest start-goal routes
+    pub min_shortest_paths: u64,
+    // Inclusive range of dead ends per cell
+    pub dead_end_density: Option<(f64, f64)>,
+}
+
+impl Constraints {
+    pub fn is_satisfied(&self, maze: &Maze) -> bool {
+        let (start, goal) = (maze.get_start().pos, maze.get_goal());
+        if let Some((min, max)) = self.shortest_path {
+            match similarity::shortest_path_length(maze) {
+                Some(length) if (min..=max).contains(&length) => (),
+                _ => return false,
+            }
+        }
+        if let Some((min, max)) = self.dead_end_density {
+            let density = similarity::dead_end_count(maze) as f64
+                / (maze.get_width() * maze.get_height()) as f64;
+            if density < min || density > max {
+                return false;
+            }
+        }
+        self.min_shortest_paths <= 1
+            || count_shortest_paths(maze, start, goal, StepMapMode::UnexploredAsPresent)
+                >= self.min_shortest_paths
+    }
+
+    // True when removing walls can no longer help: it only ever shortens
+    // the route and removes dead ends
+    fn is_hopeless(&self, maze: &Maze) -> bool {
+        let too_short = self.shortest_path.is_some_and(|(min, _)| {
+            similarity::shortest_path_length(maze).is_none_or(|length| length < min)
+        });
+        let too_open = self.dead_end_density.is_some_and(|(min, _)| {
+            (similarity::dead_end_count(maze) as f64)
+                < min * (maze.get_width() * maze.get_height()) as f64
+        });
+        too_short || too_open
+    }
+}
+
+/*
+    Search for a maze satisfying `constraints`: start from a perfect maze
+    (generate) and knock down random inner walls one at a time, which adds
+    loops (more shortest routes) and removes dead ends, until the
+    constraints hold. When that cannot work any more, start over from a
+    new perfect maze. Fails after `max_attempts` perfect mazes.
+*/
+pub fn generate_constrained(
+    width: usize,
+    height: usize,
+    constraints: &Constraints,
+    seed: u64,
+    max_attempts: usize,
+) -> Result<Maze, String> {
+    let mut rng = XorShift64::new(seed);
+    for _ in 0..max_attempts {
+        let mut maze = generate(width, height, &mut rng);
+        let fixed = fixed_walls(&Maze::new(width, height));
+        for _ in 0..width * height {
+            if constraints.is_satisfied(&maze) {
+                return Ok(maze);
+            }
+            if constraints.is_hopeless(&maze) {
+                break;
+            }
+            let y = rng.below(height);
+            let x = rng.below(width);
+            let compass = [Compass::North, Compass::East][rng.below(2)];
+            let pos = Position::new(x, y);
+            if maze.get_neighbor_cell(y, x, compass).is_some() && !is_fixed(&fixed, pos, compass) {
+                maze.set(y, x, compass, Wall::Absent);
+            }
+        }
+    }
+    Err(format!(
+        "No maze satisfying {:?} in {} attempts",
+        constraints, max_attempts
+    ))
+}
+
+// True if every pillar at either end of the wall still touches a present wall
+fn pillars_keep_a_wall(maze: &Maze, pos: Position, compass: Compass) -> bool {
+    let (pillar, towards) = cell_to_pillar(pos, compass);
+    let (dx, dy) = towards.to_delta();
+    let other = Position::new(pillar.x + dx as usize, pillar.y + dy as usize);
+    [pillar, other]
+        .iter()
+        .all(|p| Compass::iter().any(|c| maze.get_pillar_wall(*p, c) == Some(Wall::Present)))
+}
+
+// Known inner walls (North / East side of a cell) a perturbation may toggle:
+// not the start side wall and not inside the goal area
+fn toggleable_walls(maze: &Maze) -> Vec<(Position, Compass)> {
+    let fixed = fixed_walls(&Maze::with_options(
+        maze.get_width(),
+        maze.get_height(),
+        maze.get_init_options(),
+    ));
+    let area = maze.center_goal_area();
+    let mut walls = vec![];
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            let pos = Position::new(x, y);
+            for compass in [Compass::North, Compass::East] {
+                let Some((ny, nx)) = maze.get_neighbor_cell(y, x, compass) else {
+                    continue;
+                };
+                let neighbor = Position::new(nx, ny);
+                let in_goal_area = area.contains(&pos) && area.contains(&neighbor);
+                if maze.get(y, x, compass) != Wall::Unexplored
+                    && !is_fixed(&fixed, pos, compass)
+                    && !is_fixed(&fixed, neighbor, compass.opposite())
+                    && !in_goal_area
+                {
+                    walls.push((pos, compass));
+                }
+            }
+        }
+    }
+    walls
+}
+
+/*
+    Toggle one random inner wall of `maze` such that the result is still
+    legal: the start side wall and the goal area stay as they are, every
+    pillar keeps at least one wall and the goal can still be reached from
+    the start. None if no wall can be toggled.
+*/
+pub fn perturb(maze: &Maze, rng: &mut XorShift64) -> Option<Maze> {
+    let mut candidates = toggleable_walls(maze);
+    while !candidates.is_empty() {
+        let (pos, compass) = candidates.swap_remove(rng.below(candidates.len()));
+        let mut variant = maze.clone();
+        let wall = match maze.get(pos.y, pos.x, compass) {
+            Wall::Present => Wall::Absent,
+            _ => Wall::Present,
+        };
+        variant.set(pos.y, pos.x, compass, wall);
+        if (wall == Wall::Present || pillars_keep_a_wall(&variant, pos, compass))
+            && similarity::shortest_path_length(&variant).is_some()
+        {
+            return Some(variant);
+        }
+    }
+    None
+}
+
+// `count` variants of `maze`, each `toggles` perturbations away from it
+// (fewer if the maze runs out of legal toggles). Same seed, same variants.
+pub fn variants(maze: &Maze, count: usize, toggles: usize, seed: u64) -> Vec<Maze> {
+    let mut rng = XorShift64::new(seed);
+    (0..count)
+        .map(|_| {
+            let mut variant = maze.clone();
+            for _ in 0..toggles {
+                match perturb(&variant, &mut rng) {
+                    Some(next) => variant = next,
+                    None => break,
+                }
+            }
+            variant
+        })
+        .collect()
+}
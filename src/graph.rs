@@ -0,0 +1,247 @@
+use crate::maze::{Compass, Maze, Position, StepMapMode, Wall, NO_STEP};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Cell connectivity of a maze as an adjacency list.
+// Node index = y * width + x, edge weight = cost of the move (see StepMapMode::cost)
+// plus the zone cost of the cell it leaves (Maze::zone_cost).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeGraph {
+    width: usize,
+    height: usize,
+    adjacency: Vec<Vec<(usize, u16)>>,
+}
+
+impl MazeGraph {
+    pub fn node(&self, pos: Position) -> usize {
+        pos.y * self.width + pos.x
+    }
+
+    pub fn position(&self, node: usize) -> Position {
+        Position::new(node % self.width, node / self.width)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    // Number of undirected edges
+    pub fn edge_count(&self) -> usize {
+        self.adjacency.iter().map(|a| a.len()).sum::<usize>() / 2
+    }
+
+    // (neighbor node, cost) pairs
+    pub fn neighbors(&self, node: usize) -> &[(usize, u16)] {
+        &self.adjacency[node]
+    }
+
+    pub fn adjacency(&self) -> &[Vec<(usize, u16)>] {
+        &self.adjacency
+    }
+
+    // Dijkstra from `from`; None for nodes that cannot be reached
+    pub fn distances_from(&self, from: usize) -> Vec<Option<u32>> {
+        let mut dist: Vec<Option<u32>> = vec![None; self.node_count()];
+        let mut queue = BinaryHeap::new();
+        dist[from] = Some(0);
+        queue.push(Reverse((0u32, from)));
+        while let Some(Reverse((d, node))) = queue.pop() {
+            if dist[node].is_some_and(|best| best < d) {
+                continue;
+            }
+            for (next, cost) in &self.adjacency[node] {
+                let nd = d + *cost as u32;
+                if dist[*next].is_none_or(|best| nd < best) {
+                    dist[*next] = Some(nd);
+                    queue.push(Reverse((nd, *next)));
+                }
+            }
+        }
+        dist
+    }
+
+    // Zone costs (Maze::zone_cost) can make the two directions of a
+    // connection differ; the edge keeps the cost from the lower node.
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::graph::UnGraph<Position, u16> {
+        let mut graph =
+            petgraph::graph::UnGraph::with_capacity(self.node_count(), self.edge_count());
+        let nodes: Vec<_> = (0..self.node_count())
+            .map(|n| graph.add_node(self.position(n)))
+            .collect();
+        for (from, edges) in self.adjacency.iter().enumerate() {
+            for (to, cost) in edges {
+                if from < *to {
+                    graph.add_edge(nodes[from], nodes[*to], *cost);
+                }
+            }
+        }
+        graph
+    }
+}
+
+// Shortest distances between every pair of cells, indexed like MazeGraph nodes
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceMatrix {
+    width: usize,
+    distances: Vec<Vec<Option<u32>>>,
+}
+
+impl DistanceMatrix {
+    pub fn get(&self, from: Position, to: Position) -> Option<u32> {
+        self.distances[from.y * self.width + from.x][to.y * self.width + to.x]
+    }
+
+    // One row per source node
+    pub fn rows(&self) -> &[Vec<Option<u32>>] {
+        &self.distances
+    }
+
+    // Longest finite shortest distance between two cells
+    pub fn diameter(&self) -> u32 {
+        self.distances
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Maze {
+    // Passable cell connections under the given treatment of unexplored walls
+    pub fn to_graph(&self, mode: StepMapMode) -> MazeGraph {
+        self.to_graph_with_cell_costs(mode, &[])
+    }
+
+    // Same as to_graph, leaving cell [y][x] costs cell_costs[y][x] extra
+    // (e.g. Adachi::set_cell_penalty); cells missing from it cost nothing
+    pub fn to_graph_with_cell_costs(
+        &self,
+        mode: StepMapMode,
+        cell_costs: &[Vec<u16>],
+    ) -> MazeGraph {
+        let (width, height) = (self.get_width(), self.get_height());
+        let mut adjacency = vec![vec![]; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                for compass in Compass::iter() {
+                    if let Some((ny, nx)) = self.get_neighbor_cell(y, x, compass) {
+                        if let Some(cost) = mode.cost(self.get(y, x, compass)) {
+                            let extra = cell_costs.get(y).and_then(|row| row.get(x));
+                            let cost = cost
+                                .saturating_add(self.zone_cost(y, x))
+                                .saturating_add(extra.copied().unwrap_or(0));
+                            adjacency[y * width + x].push((ny * width + nx, cost));
+                        }
+                    }
+                }
+            }
+        }
+        MazeGraph {
+            width,
+            height,
+            adjacency,
+        }
+    }
+
+    // Shortest step distance between two cells, None if unreachable
+    // (or outside the maze)
+    pub fn distance(&self, from: Position, to: Position, mode: StepMapMode) -> Option<u16> {
+        let inside = |p: Position| p.x < self.get_width() && p.y < self.get_height();
+        if !inside(from) || !inside(to) {
+            return None;
+        }
+        let graph = self.to_graph(mode);
+        graph.distances_from(graph.node(from))[graph.node(to)]
+            .map(|d| d.min(u16::MAX as u32) as u16)
+    }
+
+    // All-pairs distances, one Dijkstra per cell. Fine for analysis, keep
+    // in mind a 32x32 maze gives a 1024x1024 matrix.
+    pub fn distance_matrix(&self, mode: StepMapMode) -> DistanceMatrix {
+        let graph = self.to_graph(mode);
+        DistanceMatrix {
+            width: self.get_width(),
+            distances: (0..graph.node_count())
+                .map(|n| graph.distances_from(n))
+                .collect(),
+        }
+    }
+
+    // Cells with no path from `from`. With UnexploredAsAbsent this is the
+    // area sealed off for good, with UnexploredAsPresent the area not yet
+    // provably reachable.
+    pub fn unreachable_cells(&self, from: Position, mode: StepMapMode) -> Vec<Position> {
+        let graph = self.to_graph(mode);
+        graph
+            .distances_from(graph.node(from))
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.is_none())
+            .map(|(n, _)| graph.position(n))
+            .collect()
+    }
+
+    /*
+        Graphviz description of the cell connectivity: one node per cell
+        (pinned to its grid position for neato), a solid edge for every
+        absent wall and a dashed one for every unexplored wall. The start
+        and the goal are filled. With `steps` ([y][x], e.g. collected with
+        Adachi::step_map) each node is labeled with its step value;
+        NO_STEP values are left out.
+
+        neato -n -Tsvg maze.dot > maze.svg
+    */
+    pub fn to_dot(&self, steps: Option<&[Vec<u16>]>) -> String {
+        let (width, height) = (self.get_width(), self.get_height());
+        let (start, goal) = (self.get_start().pos, self.get_goal());
+        let mut dot = String::from("graph maze {\n    node [shape=box];\n");
+        for y in 0..height {
+            for x in 0..width {
+                let pos = Position::new(x, y);
+                let mut label = format!("{},{}", x, y);
+                if let Some(step) = steps.and_then(|s| s.get(y)?.get(x)) {
+                    if *step < NO_STEP {
+                        label += &format!("\\n{}", step);
+                    }
+                }
+                let fill = if pos == start {
+                    ", style=filled, fillcolor=lightblue"
+                } else if pos == goal {
+                    ", style=filled, fillcolor=gold"
+                } else {
+                    ""
+                };
+                dot += &format!(
+                    "    c{}_{} [label=\"{}\", pos=\"{},{}!\"{}];\n",
+                    x,
+                    y,
+                    label,
+                    x * 72,
+                    y * 72,
+                    fill
+                );
+            }
+        }
+        for y in 0..height {
+            for x in 0..width {
+                // North and east only, each wall once
+                for compass in [Compass::North, Compass::East] {
+                    let Some((ny, nx)) = self.get_neighbor_cell(y, x, compass) else {
+                        continue;
+                    };
+                    let style = match self.get(y, x, compass) {
+                        Wall::Absent => "",
+                        Wall::Unexplored => " [style=dashed]",
+                        Wall::Present => continue,
+                    };
+                    dot += &format!("    c{}_{} -- c{}_{}{};\n", x, y, nx, ny, style);
+                }
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
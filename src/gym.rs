@@ -0,0 +1,196 @@
+use crate::maze::{Compass, Direction, Location, Maze, Position, Wall};
+use crate::path_finder;
+use crate::simulator::Simulator;
+
+// What the agent gets after reset and every step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    pub location: Location,
+    pub walls: path_finder::Observation,
+}
+
+impl Observation {
+    /*
+        Flat input for a learning agent:
+        [x, y, north, east, south, west, front, left, right]
+        heading one-hot, walls 1.0 present, 0.0 absent, 0.5 unexplored.
+    */
+    pub fn features(&self) -> [f32; 9] {
+        let wall = |wall: Wall| match wall {
+            Wall::Present => 1.0,
+            Wall::Absent => 0.0,
+            Wall::Unexplored => 0.5,
+        };
+        let heading = |compass: Compass| (self.location.dir == compass) as u8 as f32;
+        [
+            self.location.pos.x as f32,
+            self.location.pos.y as f32,
+            heading(Compass::North),
+            heading(Compass::East),
+            heading(Compass::South),
+            heading(Compass::West),
+            wall(self.walls.front),
+            wall(self.walls.left),
+            wall(self.walls.right),
+        ]
+    }
+}
+
+// Everything the reward function may look at for one step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepInfo {
+    pub action: Direction,
+    pub location: Location,
+    // Drove into a wall and stayed in the cell
+    pub crashed: bool,
+    // First visit of the cell in this episode
+    pub new_cell: bool,
+    pub reached_goal: bool,
+    // Steps of this episode, this one included
+    pub steps: usize,
+    // Step limit hit without reaching the goal
+    pub timed_out: bool,
+}
+
+pub type RewardFn = Box<dyn Fn(&StepInfo) -> f32 + Send>;
+
+// +100 at the goal, -5 for a crash, +1 for a new cell, -0.1 otherwise
+pub fn default_reward(info: &StepInfo) -> f32 {
+    if info.reached_goal {
+        100.0
+    } else if info.crashed {
+        -5.0
+    } else if info.new_cell {
+        1.0
+    } else {
+        -0.1
+    }
+}
+
+// Discrete action space: 0 forward, 1 left, 2 right, 3 backward
+pub fn action(index: usize) -> Option<Direction> {
+    Direction::iter().nth(index)
+}
+
+/*
+    Reinforcement-learning environment around the simulator (gym style):
+
+        let mut env = Env::new(Maze::from_file("maze_data/...")?);
+        let mut observation = env.reset();
+        loop {
+            let (next, reward, done) = env.step(agent.act(&observation));
+            ...
+            if done { break; }
+        }
+
+    An episode ends at the goal or after max_steps steps (default four
+    times the number of cells). Driving into a wall turns the mouse
+    towards it without moving.
+*/
+pub struct Env {
+    sim: Simulator,
+    goal: Position,
+    max_steps: usize,
+    steps: usize,
+    visited: Vec<Vec<bool>>,
+    reward: RewardFn,
+    last_info: Option<StepInfo>,
+}
+
+impl Env {
+    // Episodes go from the start of `maze` to its goal
+    pub fn new(maze: Maze) -> Self {
+        let (width, height) = (maze.get_width(), maze.get_height());
+        Env {
+            goal: maze.get_goal(),
+            max_steps: 4 * width * height,
+            steps: 0,
+            visited: vec![vec![false; width]; height],
+            sim: Simulator::new(maze),
+            reward: Box::new(default_reward),
+            last_info: None,
+        }
+    }
+
+    pub fn set_reward(&mut self, reward: RewardFn) {
+        self.reward = reward;
+    }
+
+    pub fn set_goal(&mut self, goal: Position) {
+        self.goal = goal;
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn maze(&self) -> &Maze {
+        self.sim.get_maze()
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // What the reward was computed from, None right after reset
+    pub fn last_info(&self) -> Option<&StepInfo> {
+        self.last_info.as_ref()
+    }
+
+    fn observe(&self) -> Observation {
+        Observation {
+            location: self.sim.get_location(),
+            walls: self.sim.sense(),
+        }
+    }
+
+    // Back to the start of the maze for a new episode
+    pub fn reset(&mut self) -> Observation {
+        let start = self.sim.get_maze().get_start();
+        self.sim.set_location(start);
+        self.steps = 0;
+        self.last_info = None;
+        for row in self.visited.iter_mut() {
+            row.fill(false);
+        }
+        self.visited[start.pos.y][start.pos.x] = true;
+        self.observe()
+    }
+
+    // Observation after the move, reward and whether the episode is over.
+    // Stepping a finished episode does nothing and gives no reward.
+    pub fn step(&mut self, action: Direction) -> (Observation, f32, bool) {
+        if self.is_done() {
+            return (self.observe(), 0.0, true);
+        }
+        self.steps += 1;
+        let crashed = self.sim.step(action).is_err();
+        if crashed {
+            let mut turned = self.sim.get_location();
+            turned.turn(action);
+            self.sim.set_location(turned);
+        }
+        let location = self.sim.get_location();
+        let visited = &mut self.visited[location.pos.y][location.pos.x];
+        let new_cell = !*visited;
+        *visited = true;
+        let reached_goal = location.pos == self.goal;
+        let info = StepInfo {
+            action,
+            location,
+            crashed,
+            new_cell,
+            reached_goal,
+            steps: self.steps,
+            timed_out: !reached_goal && self.steps >= self.max_steps,
+        };
+        let reward = (self.reward)(&info);
+        self.last_info = Some(info);
+        (self.observe(), reward, self.is_done())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.last_info
+            .is_some_and(|info| info.reached_goal || info.timed_out)
+    }
+}
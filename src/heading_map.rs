@@ -0,0 +1,145 @@
+use crate::maze::StepMapMode;
+use crate::maze::{Compass, Location, Maze, Position};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Time of one move into the next cell, by the turn it needs first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveCosts {
+    pub straight: u32,
+    pub turn: u32,
+    pub u_turn: u32,
+}
+
+impl Default for MoveCosts {
+    fn default() -> Self {
+        MoveCosts {
+            straight: 1,
+            turn: 3,
+            u_turn: 8,
+        }
+    }
+}
+
+impl MoveCosts {
+    fn cost(&self, heading: Compass, next: Compass) -> u32 {
+        if next == heading {
+            self.straight
+        } else if next == heading.opposite() {
+            self.u_turn
+        } else {
+            self.turn
+        }
+    }
+}
+
+fn compass_index(compass: Compass) -> usize {
+    match compass {
+        Compass::North => 0,
+        Compass::East => 1,
+        Compass::South => 2,
+        Compass::West => 3,
+    }
+}
+
+/*
+    Cost to the goal for every (cell, heading) pair: four layers, one per
+    heading the robot has in the cell. Turns are charged exactly where they
+    happen instead of being approximated on a cell-only step map, so
+    descending it gives the fastest grid route for the given MoveCosts.
+    Walls are passable as in `mode`; an extra unexplored wall penalty
+    (UnexploredWithPenalty) and the zone cost of the cell left
+    (Maze::zone_cost) are added to the move.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingCostMap {
+    width: usize,
+    height: usize,
+    goal: Position,
+    costs: MoveCosts,
+    mode: StepMapMode,
+    map: Vec<Option<u32>>,
+}
+
+impl HeadingCostMap {
+    pub fn compute(maze: &Maze, goal: Position, mode: StepMapMode, costs: MoveCosts) -> Self {
+        let (width, height) = (maze.get_width(), maze.get_height());
+        let index =
+            |pos: Position, heading: Compass| (pos.y * width + pos.x) * 4 + compass_index(heading);
+        let mut map = vec![None; width * height * 4];
+        let mut queue = BinaryHeap::new();
+        for heading in Compass::iter() {
+            map[index(goal, heading)] = Some(0);
+            queue.push(Reverse((0u32, goal.x, goal.y, compass_index(heading))));
+        }
+        let headings: Vec<Compass> = Compass::iter().collect();
+        // Backwards: the robot entered `pos` heading `entered`
+        while let Some(Reverse((cost, x, y, entered))) = queue.pop() {
+            let (pos, entered) = (Position::new(x, y), headings[entered]);
+            if map[index(pos, entered)] != Some(cost) {
+                continue;
+            }
+            let back = entered.opposite();
+            let Some((py, px)) = maze.get_neighbor_cell(y, x, back) else {
+                continue;
+            };
+            let Some(wall_cost) = mode.cost(maze.get(py, px, entered)) else {
+                continue;
+            };
+            let previous = Position::new(px, py);
+            let zone_cost = maze.zone_cost(py, px) as u32;
+            for heading in Compass::iter() {
+                let total = cost
+                    + costs.cost(heading, entered)
+                    + (wall_cost as u32).saturating_sub(1)
+                    + zone_cost;
+                let slot = &mut map[index(previous, heading)];
+                if slot.is_none_or(|c| total < c) {
+                    *slot = Some(total);
+                    queue.push(Reverse((total, px, py, compass_index(heading))));
+                }
+            }
+        }
+        HeadingCostMap {
+            width,
+            height,
+            goal,
+            costs,
+            mode,
+            map,
+        }
+    }
+
+    // Cost to the goal from the cell with the given heading, None if the
+    // goal cannot be reached
+    pub fn get(&self, location: Location) -> Option<u32> {
+        let pos = location.pos;
+        if pos.x >= self.width || pos.y >= self.height {
+            return None;
+        }
+        self.map[(pos.y * self.width + pos.x) * 4 + compass_index(location.dir)]
+    }
+
+    // Fastest route from `start` to the goal (both cells included)
+    pub fn route(&self, maze: &Maze, start: Location) -> Option<Vec<Position>> {
+        let mut location = start;
+        let mut cost = self.get(location)?;
+        let mut route = vec![location.pos];
+        while location.pos != self.goal {
+            let pos = location.pos;
+            let next = Compass::iter().find_map(|compass| {
+                let wall_cost = self.mode.cost(maze.get(pos.y, pos.x, compass))?;
+                let (y, x) = maze.get_neighbor_cell(pos.y, pos.x, compass)?;
+                let next = Location::new(Position::new(x, y), compass);
+                let step = self.costs.cost(location.dir, compass)
+                    + (wall_cost as u32).saturating_sub(1)
+                    + maze.zone_cost(pos.y, pos.x) as u32;
+                (self.get(next)? + step == cost).then_some(next)
+            })?;
+            cost = self.get(next)?;
+            location = next;
+            route.push(location.pos);
+        }
+        Some(route)
+    }
+}
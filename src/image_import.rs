@@ -0,0 +1,119 @@
+use crate::maze::{Compass, Maze, Wall};
+use image::GrayImage;
+
+/*
+    Maze from a top-down black-and-white picture (scan, photo, screenshot).
+    Pixels darker than `threshold` are walls. The outer wall is found as
+    the bounding box of the dark pixels, the number of cells per side is
+    the grid pitch that puts the most dark pixels on grid lines and the
+    fewest on cell centers (2 to 32 cells), then every wall segment is
+    decided by sampling the middle of it. The picture should be cropped
+    to the maze (labels outside the outer wall confuse the bounding box)
+    and roughly straight.
+*/
+pub fn maze_from_image(image: &GrayImage, threshold: u8) -> Result<Maze, String> {
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let dark: Vec<bool> = image.pixels().map(|p| p.0[0] < threshold).collect();
+    let is_dark = |x: usize, y: usize| dark[y * w + x];
+
+    // Bounding box of the outer wall
+    let columns: Vec<usize> = (0..w).filter(|x| (0..h).any(|y| is_dark(*x, y))).collect();
+    let rows: Vec<usize> = (0..h).filter(|y| (0..w).any(|x| is_dark(x, *y))).collect();
+    let (Some(&x0), Some(&x1), Some(&y0), Some(&y1)) =
+        (columns.first(), columns.last(), rows.first(), rows.last())
+    else {
+        return Err("No dark pixels in the image".to_string());
+    };
+
+    // Share of dark pixels per column / row inside the box
+    let column_profile: Vec<f64> = (x0..=x1)
+        .map(|x| (y0..=y1).filter(|y| is_dark(x, *y)).count() as f64 / (y1 - y0 + 1) as f64)
+        .collect();
+    let row_profile: Vec<f64> = (y0..=y1)
+        .map(|y| (x0..=x1).filter(|x| is_dark(*x, y)).count() as f64 / (x1 - x0 + 1) as f64)
+        .collect();
+    let width = detect_cells(&column_profile)?;
+    let height = detect_cells(&row_profile)?;
+
+    let pitch_x = (x1 - x0) as f64 / width as f64;
+    let pitch_y = (y1 - y0) as f64 / height as f64;
+    // Dark share of a rectangle around the middle of a segment
+    let sample = |cx: f64, cy: f64, rx: f64, ry: f64| {
+        let xs = (cx - rx).round().max(0.0) as usize..=((cx + rx).round() as usize).min(w - 1);
+        let ys = (cy - ry).round().max(0.0) as usize..=((cy + ry).round() as usize).min(h - 1);
+        let mut count = 0;
+        let mut total = 0;
+        for y in ys {
+            for x in xs.clone() {
+                total += 1;
+                count += is_dark(x, y) as usize;
+            }
+        }
+        count as f64 / total.max(1) as f64
+    };
+    let wall = |share: f64| {
+        if share > 0.3 {
+            Wall::Present
+        } else {
+            Wall::Absent
+        }
+    };
+
+    let mut maze = Maze::new(width, height);
+    // Image rows go down, maze y goes up
+    let line_x = |i: usize| x0 as f64 + i as f64 * pitch_x;
+    let line_y = |j: usize| y1 as f64 - j as f64 * pitch_y;
+    let (rx, ry) = ((pitch_x / 8.0).max(1.0), (pitch_y / 8.0).max(1.0));
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = (line_x(x) + pitch_x / 2.0, line_y(y) - pitch_y / 2.0);
+            let south = sample(cx, line_y(y), pitch_x / 4.0, ry);
+            let west = sample(line_x(x), cy, rx, pitch_y / 4.0);
+            maze.set(y, x, Compass::South, wall(south));
+            maze.set(y, x, Compass::West, wall(west));
+            if y == height - 1 {
+                let north = sample(cx, line_y(y + 1), pitch_x / 4.0, ry);
+                maze.set(y, x, Compass::North, wall(north));
+            }
+            if x == width - 1 {
+                let east = sample(line_x(x + 1), cy, rx, pitch_y / 4.0);
+                maze.set(y, x, Compass::East, wall(east));
+            }
+        }
+    }
+    Ok(maze)
+}
+
+// Cell count along one axis from its darkness profile. Only the inner
+// grid lines count: the outer wall is dark for any cell count.
+fn detect_cells(profile: &[f64]) -> Result<usize, String> {
+    let last = profile.len() - 1;
+    // Darkest pixel next to the position, for lines a pixel off
+    let at = |pos: f64| {
+        let i = pos.round() as usize;
+        (i.saturating_sub(1)..=(i + 1).min(last))
+            .map(|i| profile[i])
+            .fold(0.0, f64::max)
+    };
+    let score = |n: usize| {
+        let pitch = last as f64 / n as f64;
+        let lines = (1..n).map(|i| at(i as f64 * pitch)).sum::<f64>() / (n - 1) as f64;
+        let centers = (0..n).map(|i| at((i as f64 + 0.5) * pitch)).sum::<f64>() / n as f64;
+        lines - centers
+    };
+    (2..=32)
+        // Every cell needs a few pixels
+        .filter(|n| last as f64 / *n as f64 >= 4.0)
+        .map(|n| (n, score(n)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(n, _)| n)
+        .ok_or_else(|| "Maze too small to detect the grid".to_string())
+}
+
+impl Maze {
+    // Load a picture file (PNG, JPEG, PGM) with maze_from_image
+    pub fn from_image_file(filename: &str, threshold: u8) -> Result<Maze, String> {
+        let image = image::open(filename).map_err(|e| e.to_string())?;
+        maze_from_image(&image.to_luma8(), threshold)
+    }
+}
@@ -1,6 +1,57 @@
 pub mod adachi;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+pub mod batch;
+#[cfg(feature = "serial")]
+pub mod bridge;
+pub mod chain;
+pub mod checkpoint;
+#[cfg(feature = "postcard")]
+pub mod compact;
+pub mod critical;
+pub mod evaluate;
+pub mod explain;
+pub mod explorer;
+pub mod fast_run;
+pub mod framing;
+pub mod fusion;
+pub mod generator;
+pub mod graph;
+pub mod gym;
+pub mod heading_map;
+#[cfg(feature = "image")]
+pub mod image_import;
+pub mod localization;
 pub mod maze;
+#[cfg(feature = "maze_library")]
+pub mod maze_library;
+pub mod maze_text;
+pub mod mission;
+pub mod mms;
+pub mod mouse;
+pub mod occupancy;
+pub mod path;
 pub mod path_finder;
+pub mod pillar;
+pub mod profiles;
+pub mod protocol;
+#[cfg(feature = "provenance")]
+pub mod provenance;
+pub mod render;
+pub mod replay;
+pub mod rng;
+pub mod scoring;
+pub mod shared;
+pub mod shortest_paths;
+pub mod similarity;
+pub mod simulator;
+pub mod stats;
+pub mod symmetry;
+#[cfg(feature = "websocket")]
+pub mod telemetry;
+pub mod wall_follower;
+pub mod wall_list;
+pub mod waypoint;
 
 #[cfg(test)]
 mod tests {
@@ -16,6 +67,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_return)]
     fn read() {
         let mut maze = maze::Maze::new(16, 16);
         maze.init();
@@ -35,6 +87,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn solve() {
         let mut actual_maze = maze::Maze::new(16, 16);
         actual_maze.init();
@@ -107,4 +160,3256 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn read_with_detected_size() {
+        let maze =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        assert_eq!(maze.get_width(), 16);
+        assert_eq!(maze.get_height(), 16);
+
+        let mut sized = maze::Maze::new(16, 16);
+        sized
+            .read_maze_file(
+                "maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt",
+                16,
+                16,
+            )
+            .unwrap();
+        assert_eq!(maze, sized);
+    }
+
+    // Maze with every inner wall absent
+    fn open_field(width: usize, height: usize) -> maze::Maze {
+        let mut maze = maze::Maze::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                for compass in [maze::Compass::North, maze::Compass::East] {
+                    if maze.get_neighbor_cell(y, x, compass).is_some() {
+                        maze.set(y, x, compass, maze::Wall::Absent);
+                    }
+                }
+            }
+        }
+        maze
+    }
+
+    const SMALL_MAZE: &str = "
+        +-+-+-+-+
+        |       |
+        + +-+-+ +
+        | |G  | |
+        + + + + +
+        | |   | |
+        + +-+ + +
+        | |     |
+        +-+-+-+-+
+    ";
+
+    #[test]
+    fn parse_from_str() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        assert_eq!(maze.get_width(), 4);
+        assert_eq!(maze.get_height(), 4);
+        assert_eq!(maze.get_goal(), maze::Position::new(1, 2));
+        assert_eq!(maze.get(0, 0, maze::Compass::East), maze::Wall::Present);
+        assert_eq!(maze.get(0, 0, maze::Compass::North), maze::Wall::Absent);
+        assert_eq!(maze.get(2, 1, maze::Compass::North), maze::Wall::Present);
+
+        let text = maze.to_text_data(" ", "-", " ", " ", "|", " ", "+", "G");
+        let reparsed: maze::Maze = text.parse().unwrap();
+        assert_eq!(maze, reparsed);
+    }
+
+    #[test]
+    fn read_maze_text_trims_lines() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let text = maze.to_text_data(" ", "-", " ", " ", "|", " ", "+", "G");
+        // Indentation, trailing blanks and blank lines do not change the maze
+        let padded: String = text
+            .lines()
+            .map(|line| format!("\t  {}   \n\n", line))
+            .collect();
+        let mut plain = maze::Maze::new(4, 4);
+        plain.read_maze_text(&text, 4, 4).unwrap();
+        let mut trimmed = maze::Maze::new(4, 4);
+        trimmed.read_maze_text(&padded, 4, 4).unwrap();
+        assert_eq!(plain, trimmed);
+        assert_eq!(trimmed, maze);
+        // Columns in errors still count the indentation
+        let broken = padded.replacen("+-+", "+x+", 1);
+        let error = trimmed.read_maze_text(&broken, 4, 4).unwrap_err();
+        assert!(error.starts_with("line 1, column 5:"), "{}", error);
+    }
+
+    #[test]
+    fn parse_wide_dialect() {
+        let wide = "
+            o---o---o---o---o
+            |               |
+            o   o---o---o   o
+            |   | G     |   |
+            o   o   o   o   o
+            |   | 3     |   |
+            o   o---o   o   o
+            |   | 4   5 6   |
+            o---o---o---o---o
+        ";
+        let expected: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let maze = maze::Maze::from_text_with(wide, maze_text::Dialect::Auto).unwrap();
+        assert_eq!(maze, expected);
+    }
+
+    #[test]
+    fn wall_list_csv() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        maze.write_wall_list_csv(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with("x,y,orientation,state\n0,0,Horizontal,Present"));
+
+        let mut reparsed = maze::Maze::read_wall_list_csv(buffer.as_slice(), 4, 4).unwrap();
+        reparsed.set_goal(maze.get_goal());
+        assert_eq!(reparsed, maze);
+    }
+
+    #[test]
+    fn consistency_check() {
+        use maze::{Compass, Wall};
+        use wall_list::{Inconsistency, Orientation, WallDiff};
+        let reference: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut partial = maze::Maze::new(4, 4);
+        assert_eq!(partial.is_consistent_with(&reference), Ok(()));
+        partial.set(1, 1, Compass::West, Wall::Present);
+        partial.set(1, 1, Compass::North, Wall::Present);
+        assert_eq!(
+            partial.is_consistent_with(&reference),
+            Err(Inconsistency::Walls(vec![WallDiff {
+                x: 1,
+                y: 2,
+                orientation: Orientation::Horizontal,
+                mine: Wall::Present,
+                reference: Wall::Absent,
+            }]))
+        );
+        assert_eq!(
+            reference.is_consistent_with(&maze::Maze::new(3, 4)),
+            Err(Inconsistency::SizeMismatch {
+                mine: (4, 4),
+                reference: (3, 4),
+            })
+        );
+    }
+
+    #[test]
+    fn similarity_against_partial_map() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let same = similarity::compare(&maze, &maze).unwrap();
+        assert_eq!(same.wall_agreement, 1.0);
+        assert_eq!(same.path_length_difference, Some(0));
+        assert_eq!(similarity::shortest_path_length(&maze), Some(13));
+
+        let mut blank = maze::Maze::new(4, 4);
+        blank.set_goal(maze.get_goal());
+        let partial = similarity::compare(&blank, &maze).unwrap();
+        assert!(partial.wall_agreement < 0.05);
+        assert_eq!(partial.path_length_difference, None);
+    }
+
+    #[test]
+    fn configurable_start() {
+        let mut maze = maze::Maze::new(4, 4);
+        let start = maze::Location::new(maze::Position::new(3, 3), maze::Compass::South);
+        maze.set_start(start).unwrap();
+        // The side wall moves without init
+        assert_eq!(maze.get(3, 3, maze::Compass::West), maze::Wall::Present);
+        assert_eq!(maze.get(0, 0, maze::Compass::East), maze::Wall::Unexplored);
+        maze.init();
+        assert_eq!(maze.get(3, 3, maze::Compass::West), maze::Wall::Present);
+        assert!(maze
+            .set_start(maze::Location::new(
+                maze::Position::new(4, 0),
+                maze::Compass::North
+            ))
+            .is_err());
+        assert_eq!(maze.get_start(), start);
+
+        let reparsed: maze::Maze = maze.to_maze_string().parse().unwrap();
+        assert_eq!(reparsed.get_start(), start);
+        assert_eq!(adachi::Adachi::new(reparsed).get_location(), start);
+    }
+
+    #[test]
+    fn start_wall_rule() {
+        let options = maze::InitOptions {
+            start_wall: maze::StartWall::Left,
+        };
+        let mut maze = maze::Maze::with_options(4, 4, options);
+        maze.set_start(maze::Location::new(
+            maze::Position::new(3, 0),
+            maze::Compass::North,
+        ))
+        .unwrap();
+        maze.init();
+        assert_eq!(maze.get(0, 3, maze::Compass::West), maze::Wall::Present);
+        assert_eq!(maze.get(0, 0, maze::Compass::East), maze::Wall::Unexplored);
+
+        maze.init_with(maze::InitOptions {
+            start_wall: maze::StartWall::None,
+        });
+        assert_eq!(maze.get(0, 3, maze::Compass::West), maze::Wall::Unexplored);
+    }
+
+    #[test]
+    fn unexplored_round_trip() {
+        let mut maze = maze::Maze::new(4, 4);
+        maze.set(0, 0, maze::Compass::North, maze::Wall::Absent);
+        maze.set(1, 0, maze::Compass::East, maze::Wall::Present);
+
+        let text = maze.to_text_with(maze_text::Dialect::Partial);
+        assert_eq!(
+            maze::Maze::from_text_with(&text, maze_text::Dialect::Auto).unwrap(),
+            maze
+        );
+        assert_eq!(text.parse::<maze::Maze>().unwrap(), maze);
+
+        let wide = maze_text::Dialect::Custom {
+            pillar: 'o',
+            cell_width: 3,
+        };
+        let text = maze.to_text_with(wide);
+        assert!(text.starts_with("o---o---o---o---o\n"));
+        assert_eq!(maze::Maze::from_text_with(&text, wide).unwrap(), maze);
+        let plus = maze_text::Dialect::Custom {
+            pillar: '+',
+            cell_width: 3,
+        };
+        assert!(maze::Maze::from_text_with(&text, plus)
+            .unwrap_err()
+            .contains("expected pillar '+' at column 1"));
+    }
+
+    #[test]
+    fn outer_walls_cannot_be_removed() {
+        use maze::{Compass, Wall};
+        let mut maze = maze::Maze::new(4, 3);
+        // The outer walls are on the last row and column, not past them
+        for x in 0..4 {
+            maze.set(2, x, Compass::North, Wall::Absent);
+            maze.set(0, x, Compass::South, Wall::Absent);
+            assert_eq!(maze.get(2, x, Compass::North), Wall::Present);
+            assert_eq!(maze.get(0, x, Compass::South), Wall::Present);
+        }
+        for y in 0..3 {
+            maze.set(y, 3, Compass::East, Wall::Unexplored);
+            maze.set(y, 0, Compass::West, Wall::Absent);
+            assert_eq!(maze.get(y, 3, Compass::East), Wall::Present);
+            assert_eq!(maze.get(y, 0, Compass::West), Wall::Present);
+        }
+        // Inner walls next to them can
+        maze.set(1, 3, Compass::North, Wall::Absent);
+        maze.set(2, 2, Compass::East, Wall::Absent);
+        assert_eq!(maze.get(1, 3, Compass::North), Wall::Absent);
+        assert_eq!(maze.get(2, 2, Compass::East), Wall::Absent);
+    }
+
+    #[test]
+    fn parse_errors_point_at_character() {
+        let crlf = "+-+-+\r\n|G  |\r\n+ +-+\r\n|   |\r\n+-+-+\r\n";
+        assert!(crlf.parse::<maze::Maze>().is_ok());
+
+        let bad = "+-+-+\n|G  |\n+ +x+\n|   |\n+-+-+\n";
+        assert_eq!(
+            bad.parse::<maze::Maze>().unwrap_err(),
+            "line 3, column 4: expected ' ', '-' or '?', found 'x'"
+        );
+
+        let short = "+-+-+\n|G  |\n+ +-+\n| \n+-+-+\n";
+        assert_eq!(
+            short.parse::<maze::Maze>().unwrap_err(),
+            "line 4, column 2: expected ' ', 'G', 'X' or a start marker, found end of line"
+        );
+    }
+
+    #[test]
+    fn step_map_unexplored_penalty() {
+        // Known corridor along the west and north edges, unknown shortcut in the middle
+        let mut maze = maze::Maze::new(3, 3);
+        maze.set_goal(maze::Position::new(2, 2));
+        for y in 0..2 {
+            maze.set(y, 0, maze::Compass::North, maze::Wall::Absent);
+        }
+        for x in 0..2 {
+            maze.set(2, x, maze::Compass::East, maze::Wall::Absent);
+        }
+        let mut solver = adachi::Adachi::new(maze);
+        solver.calc_step_map(maze::Position::new(2, 2));
+        assert_eq!(solver.get_step(0, 0), 4);
+
+        solver.set_mode(adachi::StepMapMode::UnexploredWithPenalty(1));
+        solver.calc_step_map(maze::Position::new(2, 2));
+        assert_eq!(solver.get_step(0, 0), 4);
+        assert_eq!(solver.get_step(1, 1), 3);
+
+        solver.set_mode(adachi::StepMapMode::UnexploredAsPresent);
+        solver.calc_step_map(maze::Position::new(2, 2));
+        assert_eq!(solver.get_step(1, 1), adachi::Adachi::NONE);
+    }
+
+    #[test]
+    fn tie_break_prefers_straight() {
+        // Open 3x3 field: from (1,0) facing East both North and East lead
+        // to the goal (2,2) in the same number of steps
+        let mut maze = maze::Maze::new(3, 3);
+        maze.set_goal(maze::Position::new(2, 2));
+        let mut solver = adachi::Adachi::new(maze);
+        solver.set_location(maze::Location::new(
+            maze::Position::new(1, 0),
+            maze::Compass::East,
+        ));
+        let open = maze::Wall::Absent;
+        let goal = maze::Position::new(2, 2);
+
+        let dir = solver.navigate(open, open, maze::Wall::Present, goal);
+        assert_eq!(dir.unwrap(), maze::Direction::Left);
+
+        solver.set_tie_break(adachi::TieBreak::PreferStraight);
+        let dir = solver.navigate(open, open, maze::Wall::Present, goal);
+        assert_eq!(dir.unwrap(), maze::Direction::Forward);
+    }
+
+    #[test]
+    fn fast_run_requires_explored_route() {
+        let reference: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let start = reference.get_start().pos;
+        let goal = reference.get_goal();
+
+        let mut blank = maze::Maze::new(4, 4);
+        blank.set_goal(goal);
+        let mut solver = adachi::Adachi::new(blank);
+        assert!(solver.plan_fast_run(start, goal).is_err());
+
+        let mut solver = adachi::Adachi::new(reference);
+        let path = solver.plan_fast_run(start, goal).unwrap();
+        assert_eq!(path.len(), 14);
+        assert_eq!(path[0], start);
+        assert_eq!(*path.last().unwrap(), goal);
+
+        let outside = maze::Position::new(4, 0);
+        assert!(solver.plan_fast_run(outside, goal).is_err());
+        assert!(solver.plan_fast_run(start, outside).is_err());
+
+        // Back from the far corner: straight on in the solver's heading
+        let corner = maze::Location::new(maze::Position::new(2, 2), maze::Compass::South);
+        let mut solver = adachi::Adachi::new(open_field(3, 3));
+        solver.set_location(corner);
+        solver.set_tie_break(adachi::TieBreak::PreferStraight);
+        let path = solver.plan_fast_run(corner.pos, start).unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!(path[1], maze::Position::new(2, 1));
+        assert_eq!(path[2], maze::Position::new(2, 0));
+    }
+
+    #[test]
+    fn navigate_to_target() {
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let target = maze::Position::new(3, 0);
+        solver.set_target(target);
+
+        let mut steps = 0;
+        while solver.get_location().pos != target {
+            let loc = solver.get_location();
+            let (y, x, d) = (loc.pos.y, loc.pos.x, loc.dir);
+            let dir = solver
+                .navigate_to(
+                    actual.get(y, x, d.turn(maze::Direction::Forward)),
+                    actual.get(y, x, d.turn(maze::Direction::Left)),
+                    actual.get(y, x, d.turn(maze::Direction::Right)),
+                )
+                .unwrap();
+            let mut next = loc;
+            next.turn(dir);
+            next.forward();
+            solver.set_location(next);
+            steps += 1;
+            assert!(steps < 100);
+        }
+        let (f, l, r) = (
+            maze::Wall::Present,
+            maze::Wall::Present,
+            maze::Wall::Present,
+        );
+        assert!(solver.navigate_to(f, l, r).is_err());
+    }
+
+    #[test]
+    fn waypoint_round_trip() {
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let start = actual.get_start().pos;
+        let goal = actual.get_goal();
+        let mut blank = maze::Maze::new(4, 4);
+        blank.set_goal(goal);
+        let mut mission =
+            waypoint::Waypoints::new(adachi::Adachi::new(blank), vec![goal, start, goal]);
+
+        let mut events = vec![];
+        for _ in 0..200 {
+            let loc = mission.finder().get_location();
+            let (y, x, d) = (loc.pos.y, loc.pos.x, loc.dir);
+            let dir = mission
+                .navigate(
+                    actual.get(y, x, d.turn(maze::Direction::Forward)),
+                    actual.get(y, x, d.turn(maze::Direction::Left)),
+                    actual.get(y, x, d.turn(maze::Direction::Right)),
+                )
+                .unwrap();
+            events.extend(mission.take_events());
+            let dir = match dir {
+                Some(dir) => dir,
+                None => break,
+            };
+            let mut next = loc;
+            next.turn(dir);
+            next.forward();
+            mission.finder_mut().set_location(next);
+        }
+        assert!(mission.is_finished());
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[1],
+            waypoint::WaypointEvent::Reached {
+                index: 1,
+                position: start
+            }
+        );
+        assert_eq!(events[3], waypoint::WaypointEvent::Completed);
+    }
+
+    #[test]
+    fn adachi_state_round_trip() {
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = actual.get_goal();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let mut sim = simulator::Simulator::new(actual);
+        for _ in 0..3 {
+            sim.tick(&mut solver, goal).unwrap();
+        }
+        let bytes = solver.to_bytes().unwrap();
+        let restored = adachi::Adachi::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.get_maze(), solver.get_maze());
+        assert_eq!(restored.get_location(), solver.get_location());
+        assert_eq!(restored.get_target(), solver.get_target());
+        assert!(adachi::Adachi::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+        #[cfg(feature = "postcard")]
+        assert!(bytes.len() < serde_json::to_vec(&solver).unwrap().len());
+    }
+
+    #[test]
+    fn reset_to_start() {
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = actual.get_goal();
+        let mut blank = maze::Maze::new(4, 4);
+        blank.set_goal(goal);
+        let mut solver = adachi::Adachi::new(blank);
+        let mut sim = simulator::Simulator::new(actual.clone());
+        sim.run(&mut solver, goal, 200).unwrap();
+        assert_eq!(solver.get_location().pos, goal);
+
+        // Back to the start cell, the map is kept
+        solver.reset(true);
+        assert_eq!(solver.get_location(), actual.get_start());
+        assert_eq!(solver.get_target(), goal);
+        assert_eq!(
+            solver.get_maze().get(0, 0, maze::Compass::North),
+            maze::Wall::Absent
+        );
+
+        // The map is cleared, the goal stays
+        solver.reset(false);
+        assert_eq!(solver.get_location(), actual.get_start());
+        assert_eq!(
+            solver.get_maze().get(0, 0, maze::Compass::North),
+            maze::Wall::Unexplored
+        );
+        assert_eq!(solver.get_goal(), goal);
+    }
+
+    #[test]
+    fn absorb_prior_knowledge() {
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut prior = maze::Maze::new(4, 4);
+        prior.set(0, 0, maze::Compass::North, maze::Wall::Absent);
+        prior.set(1, 0, maze::Compass::North, maze::Wall::Present);
+
+        let mut solver = adachi::Adachi::with_known_maze(prior, actual.get_start());
+        // Everything the solver does not know yet is learned, the rest is kept
+        let learned = solver.absorb(&actual).unwrap();
+        assert_eq!(learned, 24 - 2 - 1);
+        assert_eq!(
+            solver.get_maze().get(1, 0, maze::Compass::North),
+            maze::Wall::Present
+        );
+        assert!(solver.absorb(&maze::Maze::new(3, 3)).is_err());
+    }
+
+    #[test]
+    fn wall_conflicts() {
+        let mut prior = maze::Maze::new(4, 4);
+        prior.set(0, 0, maze::Compass::North, maze::Wall::Present);
+        let mut solver = adachi::Adachi::new(prior);
+        solver.set_conflict_policy(adachi::ConflictPolicy::KeepFirst);
+
+        let (open, wall) = (maze::Wall::Absent, maze::Wall::Present);
+        let goal = solver.get_goal();
+        // Front is now seen open, but was recorded as a wall
+        let _ = solver.navigate(open, wall, wall, goal);
+        assert_eq!(solver.conflicts().len(), 1);
+        assert_eq!(solver.conflicts()[0].previous, wall);
+        assert_eq!(solver.get_maze().get(0, 0, maze::Compass::North), wall);
+
+        solver.set_conflict_policy(adachi::ConflictPolicy::Callback);
+        solver.set_conflict_callback(Box::new(|_| maze::Wall::Unexplored));
+        let _ = solver.navigate(open, wall, wall, goal);
+        assert_eq!(solver.conflicts().len(), 2);
+        assert_eq!(
+            solver.get_maze().get(0, 0, maze::Compass::North),
+            maze::Wall::Unexplored
+        );
+    }
+
+    #[test]
+    fn graph_export() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let graph = maze.to_graph(adachi::StepMapMode::UnexploredAsPresent);
+        assert_eq!(graph.node_count(), 16);
+        // 15 edges of a spanning tree plus the loop around the goal
+        assert_eq!(graph.edge_count(), 16);
+        let start = graph.node(maze::Position::new(0, 0));
+        assert_eq!(
+            graph.neighbors(start),
+            &[(graph.node(maze::Position::new(0, 1)), 1)]
+        );
+    }
+
+    #[test]
+    fn shortest_path_enumeration() {
+        let maze = maze::Maze::new(3, 3);
+        let start = maze::Position::new(0, 0);
+        let goal = maze::Position::new(2, 2);
+        let mode = adachi::StepMapMode::UnexploredAsAbsent;
+        // Open 3x3 field minus the east wall of the start cell
+        assert_eq!(
+            shortest_paths::count_shortest_paths(&maze, start, goal, mode),
+            3
+        );
+
+        let paths = shortest_paths::enumerate_shortest_paths(&maze, start, goal, mode, 10);
+        assert_eq!(paths.len(), 3);
+        assert!(paths.iter().all(|p| p.len() == 5 && p[4] == goal));
+        assert_eq!(
+            shortest_paths::enumerate_shortest_paths(&maze, start, goal, mode, 2).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn k_shortest() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mode = adachi::StepMapMode::UnexploredAsPresent;
+        let paths =
+            shortest_paths::k_shortest_paths(&maze, maze.get_start().pos, maze.get_goal(), mode, 5);
+        // Only the loop next to the goal gives a second route, of equal length
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].0, 13);
+        assert_eq!(paths[1].0, 13);
+        assert_ne!(paths[0].1, paths[1].1);
+    }
+
+    #[test]
+    fn unreachable_region() {
+        let mut maze = maze::Maze::new(3, 3);
+        // Seal off the top-right cell
+        maze.set(2, 2, maze::Compass::West, maze::Wall::Present);
+        maze.set(2, 2, maze::Compass::South, maze::Wall::Present);
+        let start = maze.get_start().pos;
+        assert_eq!(
+            maze.unreachable_cells(start, adachi::StepMapMode::UnexploredAsAbsent),
+            vec![maze::Position::new(2, 2)]
+        );
+        assert_eq!(
+            maze.unreachable_cells(start, adachi::StepMapMode::UnexploredAsPresent)
+                .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn compass_direction_utils() {
+        use maze::{Compass, Direction};
+        for compass in Compass::iter() {
+            assert_eq!(compass.opposite().opposite(), compass);
+            assert_eq!(compass.rotate(1), compass.turn(Direction::Right));
+            assert_eq!(compass.rotate(-1), compass.turn(Direction::Left));
+            assert_eq!(compass.rotate(6), compass.opposite());
+            let (dx, dy) = compass.to_delta();
+            let (ox, oy) = compass.opposite().to_delta();
+            assert_eq!((dx + ox, dy + oy), (0, 0));
+        }
+        for direction in Direction::iter() {
+            assert_eq!(
+                Compass::North.turn(direction.opposite()),
+                Compass::North.turn(direction).opposite()
+            );
+        }
+        assert_eq!(Direction::Left.rotate(1), Direction::Forward);
+        assert_eq!(Compass::try_from('w'), Ok(Compass::West));
+        assert_eq!("South".parse::<Compass>(), Ok(Compass::South));
+        assert_eq!("r".parse::<Direction>(), Ok(Direction::Right));
+        assert_eq!("backward".parse::<Direction>(), Ok(Direction::Backward));
+        assert!("X".parse::<Compass>().is_err());
+        assert!(Direction::try_from('N').is_err());
+    }
+
+    #[test]
+    fn cell_walls() {
+        use maze::{Compass, Wall};
+        let mut maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut walls = maze.cell_walls(1, 1);
+        assert_eq!(walls.north, Wall::Absent);
+        assert_eq!(walls.west, Wall::Present);
+        assert_eq!(walls.south, Wall::Present);
+        assert_eq!(walls.get(Compass::East), Wall::Absent);
+
+        walls.set(Compass::East, Wall::Present);
+        maze.set_cell_walls(1, 1, walls);
+        assert_eq!(maze.cell_walls(1, 1), walls);
+        assert_eq!(maze.get(1, 2, Compass::West), Wall::Present);
+    }
+
+    #[test]
+    fn can_move() {
+        use adachi::StepMapMode;
+        use maze::{Compass, Wall};
+        let mut maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        assert!(maze.can_move(0, 0, Compass::North, StepMapMode::UnexploredAsPresent));
+        assert!(!maze.can_move(0, 0, Compass::East, StepMapMode::UnexploredAsAbsent));
+        assert!(!maze.can_move(0, 0, Compass::South, StepMapMode::UnexploredAsAbsent));
+        assert!(!maze.can_move(4, 0, Compass::North, StepMapMode::UnexploredAsAbsent));
+
+        maze.set(1, 1, Compass::North, Wall::Unexplored);
+        assert!(!maze.can_move(1, 1, Compass::North, StepMapMode::UnexploredAsPresent));
+        assert!(maze.can_move(1, 1, Compass::North, StepMapMode::UnexploredAsAbsent));
+        assert!(maze.can_move(2, 1, Compass::South, StepMapMode::UnexploredWithPenalty(5)));
+    }
+
+    #[test]
+    fn validate_path() {
+        use adachi::StepMapMode;
+        use maze::{Position, Wall};
+        use path::PathError;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        let route = solver
+            .plan_fast_run(maze.get_start().pos, maze.get_goal())
+            .unwrap();
+        assert_eq!(
+            maze.validate_path(&route, StepMapMode::UnexploredAsPresent),
+            Ok(())
+        );
+
+        let p = Position::new;
+        assert_eq!(
+            maze.validate_path(&[p(0, 0), p(1, 0)], StepMapMode::UnexploredAsAbsent),
+            Err(PathError::Blocked {
+                index: 1,
+                from: p(0, 0),
+                to: p(1, 0),
+                wall: Wall::Present
+            })
+        );
+        assert_eq!(
+            maze.validate_path(&[p(0, 0), p(0, 2)], StepMapMode::UnexploredAsAbsent),
+            Err(PathError::NotAdjacent {
+                index: 1,
+                from: p(0, 0),
+                to: p(0, 2)
+            })
+        );
+        assert!(matches!(
+            maze.validate_path(&[p(0, 4)], StepMapMode::UnexploredAsAbsent),
+            Err(PathError::OutOfBounds { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn pillar_addressing() {
+        use maze::{Compass, Position, Wall};
+        let mut maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let (w, h) = (maze.get_width(), maze.get_height());
+        for y in 0..h {
+            for x in 0..w {
+                for compass in [Compass::North, Compass::East, Compass::South, Compass::West] {
+                    let (pillar, towards) = pillar::cell_to_pillar(Position::new(x, y), compass);
+                    assert_eq!(
+                        maze.get_pillar_wall(pillar, towards),
+                        Some(maze.get(y, x, compass))
+                    );
+                }
+            }
+        }
+        // Same wall addressed from both ends
+        let a = maze.get_pillar_wall(Position::new(1, 1), Compass::North);
+        let b = maze.get_pillar_wall(Position::new(1, 2), Compass::South);
+        assert_eq!(a, Some(Wall::Present));
+        assert_eq!(a, b);
+        assert_eq!(
+            maze.get_pillar_wall(Position::new(4, 4), Compass::North),
+            None
+        );
+        assert_eq!(
+            maze.get_pillar_wall(Position::new(0, 0), Compass::West),
+            None
+        );
+
+        maze.set_pillar_wall(Position::new(2, 2), Compass::West, Wall::Absent)
+            .unwrap();
+        assert_eq!(maze.get(2, 1, Compass::South), Wall::Absent);
+        assert!(maze
+            .set_pillar_wall(Position::new(4, 0), Compass::East, Wall::Absent)
+            .is_err());
+    }
+
+    #[test]
+    fn distance_between_cells() {
+        use adachi::StepMapMode;
+        use maze::Position;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mode = StepMapMode::UnexploredAsPresent;
+        assert_eq!(
+            maze.distance(maze.get_start().pos, maze.get_goal(), mode),
+            Some(13)
+        );
+        assert_eq!(
+            maze.distance(Position::new(2, 1), Position::new(2, 1), mode),
+            Some(0)
+        );
+        assert_eq!(
+            maze.distance(Position::new(0, 0), Position::new(4, 0), mode),
+            None
+        );
+    }
+
+    #[test]
+    fn distance_matrix() {
+        use adachi::StepMapMode;
+        use maze::Position;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mode = StepMapMode::UnexploredAsPresent;
+        let matrix = maze.distance_matrix(mode);
+        assert_eq!(matrix.rows().len(), 16);
+        for (a, b) in [((0, 0), (1, 2)), ((3, 3), (2, 0)), ((1, 1), (2, 1))] {
+            let (a, b) = (Position::new(a.0, a.1), Position::new(b.0, b.1));
+            assert_eq!(matrix.get(a, b), matrix.get(b, a));
+            assert_eq!(
+                matrix.get(a, b),
+                maze.distance(a, b, mode).map(|d| d as u32)
+            );
+        }
+        assert_eq!(matrix.diameter(), 13);
+    }
+
+    #[test]
+    fn critical_walls() {
+        use adachi::StepMapMode;
+        use maze::{Compass, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mode = StepMapMode::UnexploredAsPresent;
+        let base = maze
+            .distance(maze.get_start().pos, maze.get_goal(), mode)
+            .unwrap();
+        let critical = critical::critical_walls(&maze);
+        assert!(!critical.is_empty());
+        assert!(critical.windows(2).all(|w| w[0].impact >= w[1].impact));
+        let best = critical[0];
+        let mut opened = maze.clone();
+        opened.set(best.pos.y, best.pos.x, best.compass, Wall::Absent);
+        let shorter = opened
+            .distance(maze.get_start().pos, maze.get_goal(), mode)
+            .unwrap();
+        assert_eq!((base - shorter) as u32, best.impact);
+
+        let mut partial = maze.clone();
+        partial.set(0, 0, Compass::North, Wall::Unexplored);
+        partial.set(2, 1, Compass::North, Wall::Unexplored);
+        let ranked = critical::rank_unexplored_walls(&partial);
+        assert_eq!(ranked.len(), 2);
+        // The west column is the only way out of the start cell
+        assert_eq!(ranked[0].pos, maze::Position::new(0, 0));
+        assert_eq!(ranked[0].impact, u32::MAX);
+        // Goal entrance from the top row saves 8 steps
+        assert_eq!(ranked[1].pos, maze::Position::new(1, 2));
+        assert_eq!(ranked[1].impact, 8);
+
+        // On a partial maze every impact holds with unexplored walls present
+        let mut partial = maze.clone();
+        partial.set(2, 1, Compass::North, Wall::Unexplored);
+        let base = partial
+            .distance(maze.get_start().pos, maze.get_goal(), mode)
+            .unwrap();
+        let critical = critical::critical_walls(&partial);
+        assert!(!critical.is_empty());
+        for wall in critical {
+            let mut opened = partial.clone();
+            opened.set(wall.pos.y, wall.pos.x, wall.compass, Wall::Absent);
+            let shorter = opened
+                .distance(maze.get_start().pos, maze.get_goal(), mode)
+                .unwrap();
+            assert_eq!((base - shorter) as u32, wall.impact);
+        }
+    }
+
+    #[test]
+    fn unverified_walls_on_best_path() {
+        use maze::{Compass, Position, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let known = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        assert!(known.unverified_walls_on_best_path().is_empty());
+
+        let mut partial = maze.clone();
+        partial.set(2, 1, Compass::North, Wall::Unexplored);
+        partial.set(0, 2, Compass::East, Wall::Unexplored);
+        let solver = adachi::Adachi::with_known_maze(partial, maze.get_start());
+        // Only the goal entrance from the top row is on the optimistic route
+        assert_eq!(
+            solver.unverified_walls_on_best_path(),
+            vec![(Position::new(1, 3), Compass::South)]
+        );
+    }
+
+    #[test]
+    fn targeted_exploration() {
+        use maze::Direction;
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let mut explorer =
+            explorer::TargetedExplorer::new(adachi::Adachi::new(maze::Maze::new(16, 16)));
+        let goal = actual.get_goal();
+        let mut steps = 0;
+        while explorer.get_location().pos != goal {
+            let loc = explorer.get_location();
+            let wall = |d: Direction| actual.get(loc.pos.y, loc.pos.x, loc.dir.turn(d));
+            let dir = explorer
+                .navigate(
+                    wall(Direction::Forward),
+                    wall(Direction::Left),
+                    wall(Direction::Right),
+                    goal,
+                )
+                .unwrap();
+            assert_ne!(wall(dir), maze::Wall::Present);
+            let mut next = loc;
+            next.turn(dir);
+            next.forward();
+            explorer.set_location(next);
+            steps += 1;
+            assert!(steps < 2000, "explorer did not reach the goal");
+        }
+        assert!(explorer.current_target().is_some());
+
+        // Each reading is recorded once: one conflicting wall, one conflict
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let start = maze.get_start();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), start);
+        solver.set_conflict_policy(adachi::ConflictPolicy::KeepFirst);
+        let mut explorer = explorer::TargetedExplorer::new(solver);
+        let wall = |d: Direction| maze.get(start.pos.y, start.pos.x, start.dir.turn(d));
+        let front = match wall(Direction::Forward) {
+            maze::Wall::Present => maze::Wall::Absent,
+            _ => maze::Wall::Present,
+        };
+        let _ = explorer.navigate_step(
+            front,
+            wall(Direction::Left),
+            wall(Direction::Right),
+            maze.get_goal(),
+        );
+        assert_eq!(explorer.solver().conflicts().len(), 1);
+    }
+
+    #[test]
+    fn difficulty_score() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let small = stats::stats(&maze);
+        assert_eq!(small.shortest_path_length, Some(13));
+        assert_eq!(small.turn_count, Some(5));
+        assert_eq!(small.dead_end_count, 1);
+        assert_eq!(small.false_corridor_depth, 1);
+        // (13 + 2 * 5 + 4 * 1 + 1) / sqrt(16)
+        assert_eq!(small.difficulty(), Some(7.0));
+
+        let blank = stats::stats(&maze::Maze::new(4, 4));
+        assert_eq!(blank.difficulty(), None);
+    }
+
+    #[cfg(feature = "maze_library")]
+    #[test]
+    fn bundled_maze_library() {
+        let list = maze_library::list();
+        let files = std::fs::read_dir("maze_data")
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().is_some_and(|ext| ext == "txt")
+            })
+            .count();
+        assert_eq!(list.len(), files);
+        let info = maze_library::info("AllJapan_032_2011_classic_exp_fin_16x16").unwrap();
+        assert!(list.contains(&info));
+        assert_eq!(maze_library::info("missing"), None);
+        assert_eq!(info.competition, "AllJapan");
+        assert_eq!(info.year, Some(2011));
+        assert_eq!((info.width, info.height), (16, 16));
+
+        let maze = maze_library::load(info.name).unwrap();
+        let file =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        assert_eq!(maze, file);
+        assert!(maze_library::load("missing").is_err());
+        assert!(list.iter().all(|i| maze_library::load(i.name).is_ok()));
+    }
+
+    #[test]
+    fn long_range_sensor() {
+        use maze::Compass;
+        use path_finder::RangeReading;
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let reading = sim.cast_ray(16);
+        assert!(reading.wall);
+        assert_eq!(
+            actual.get(reading.open_cells, 0, Compass::North),
+            maze::Wall::Present
+        );
+        assert_eq!(
+            sim.cast_ray(0),
+            RangeReading {
+                open_cells: 0,
+                wall: false
+            }
+        );
+
+        // One observation reveals the whole west corridor
+        sim.set_range(16);
+        let mut ranged = adachi::Adachi::new(maze::Maze::new(16, 16));
+        let goal = actual.get_goal();
+        ranged.navigate_observed(&sim.sense(), goal).unwrap();
+        let top = reading.open_cells;
+        assert_eq!(
+            ranged.get_maze().get(top, 0, Compass::North),
+            maze::Wall::Present
+        );
+        assert_eq!(
+            ranged.get_maze().get(top - 1, 0, Compass::North),
+            maze::Wall::Absent
+        );
+
+        let mut ranged = adachi::Adachi::new(maze::Maze::new(16, 16));
+        sim.set_range(4);
+        sim.run(&mut ranged, goal, 2000).unwrap();
+        assert_eq!(ranged.get_maze().is_consistent_with(&actual), Ok(()));
+    }
+
+    #[test]
+    fn move_failure_feedback() {
+        use maze::{Compass, Direction, Location, Position, Wall};
+        use path_finder::MoveFailure;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let goal = maze.get_goal();
+        let dir = solver
+            .navigate(Wall::Absent, Wall::Present, Wall::Present, goal)
+            .unwrap();
+        assert_eq!(dir, Direction::Forward);
+
+        // Something stopped the mouse: the map learns the wall
+        solver.report_move_failure(MoveFailure::Blocked);
+        assert_eq!(solver.get_maze().get(0, 0, Compass::North), Wall::Present);
+        assert_eq!(solver.get_location().pos, Position::new(0, 0));
+
+        let moved = Location::new(Position::new(0, 1), Compass::North);
+        solver.report_move_failure(MoveFailure::Displaced(moved));
+        assert_eq!(solver.get_location(), moved);
+    }
+
+    #[test]
+    fn relocalize() {
+        use localization::Sample;
+        use maze::{Compass, Direction, Location, Position};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut sim = simulator::Simulator::new(maze.clone());
+        sim.set_location(Location::new(Position::new(3, 0), Compass::North));
+
+        let mut history = vec![];
+        for moved in [
+            Direction::Forward,
+            Direction::Forward,
+            Direction::Forward,
+            Direction::Left,
+            Direction::Forward,
+            Direction::Forward,
+        ] {
+            history.push(Sample {
+                observation: sim.sense(),
+                moved: Some(moved),
+            });
+            sim.step(moved).unwrap();
+        }
+        let first = localization::localize(&maze, &history[..1]);
+        assert!(first.len() > 1);
+
+        history.push(Sample {
+            observation: sim.sense(),
+            moved: None,
+        });
+        assert_eq!(
+            localization::localize(&maze, &history),
+            vec![sim.get_location()]
+        );
+    }
+
+    #[test]
+    fn sensor_fusion() {
+        use maze::{Compass, Location, Position, Wall};
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        solver.set_fusion(Some(fusion::FusionWeights::default()));
+        let cell = Location::new(Position::new(1, 1), Compass::North);
+        let goal = Position::new(3, 3);
+        let visit = |solver: &mut adachi::Adachi, front: Wall| {
+            solver.set_location(cell);
+            let _ = solver.navigate(front, Wall::Absent, Wall::Absent, goal);
+            solver.get_maze().get(1, 1, Compass::North)
+        };
+        assert_eq!(visit(&mut solver, Wall::Present), Wall::Present);
+        assert_eq!(visit(&mut solver, Wall::Present), Wall::Present);
+        // One noisy reading does not flip the wall
+        assert_eq!(visit(&mut solver, Wall::Absent), Wall::Present);
+        let votes = solver
+            .fusion()
+            .unwrap()
+            .votes(Position::new(1, 2), Compass::South);
+        assert_eq!((votes.present, votes.absent), (2, 1));
+        assert_eq!(visit(&mut solver, Wall::Absent), Wall::Absent);
+        assert!(solver.conflicts().is_empty());
+    }
+
+    #[test]
+    fn solve_iterator() {
+        use maze::Direction;
+        use path_finder::Observation;
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        solver.set_target(actual.get_goal());
+        let sense = |loc: maze::Location| {
+            let wall = |d: Direction| actual.get(loc.pos.y, loc.pos.x, loc.dir.turn(d));
+            Observation::new(
+                wall(Direction::Forward),
+                wall(Direction::Left),
+                wall(Direction::Right),
+            )
+        };
+        let decisions: Vec<_> = solver.solve_iter(sense).collect::<Result<_, _>>().unwrap();
+        assert!(decisions.len() >= 13);
+        assert_eq!(decisions[0].0, actual.get_start());
+        assert_eq!(solver.get_location().pos, actual.get_goal());
+        assert_eq!(solver.solve_iter(sense).count(), 0);
+
+        // Walled in: a single error, then the iterator ends
+        let mut walled_in = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let closed = |_| {
+            Observation::new(
+                maze::Wall::Present,
+                maze::Wall::Present,
+                maze::Wall::Present,
+            )
+        };
+        let mut iter = walled_in.solve_iter(closed);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn chained_fallback() {
+        use maze::{Compass, Location, Position, Wall};
+        use wall_follower::{Hand, WallFollower};
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = actual.get_goal();
+
+        // The solver's map claims the only exit of the start cell is closed
+        let mut wrong = maze::Maze::new(4, 4);
+        wrong.set(0, 0, Compass::North, Wall::Present);
+        let mut solver = adachi::Adachi::with_known_maze(wrong, actual.get_start());
+        solver.set_conflict_policy(adachi::ConflictPolicy::KeepFirst);
+        let mut chain = chain::ChainedPathFinder::new(vec![
+            Box::new(solver),
+            Box::new(WallFollower::new(maze::Maze::new(4, 4), Hand::Left)),
+        ]);
+        let mut sim = simulator::Simulator::new(actual.clone());
+        chain.navigate_observed(&sim.sense(), goal).unwrap();
+        assert_eq!(chain.active_index(), 1);
+        assert_eq!(
+            chain.failures(),
+            &[(0, path_finder::NavigationError::NoPath)]
+        );
+        let steps = sim.run(&mut chain, goal, 100).unwrap();
+        assert!(steps >= 13);
+        assert_eq!(chain.get_location().pos, goal);
+        assert_eq!(chain.strategies()[0].get_location().pos, goal);
+
+        chain.set_location(Location::new(Position::new(0, 0), Compass::North));
+        chain.reset(false);
+        assert_eq!(chain.active_index(), 0);
+        assert!(chain.failures().is_empty());
+
+        // Both fail: the last error is returned, the first one is kept
+        let mut closed = maze::Maze::new(4, 4);
+        closed.set(0, 0, Compass::North, Wall::Present);
+        let mut chain = chain::ChainedPathFinder::new(vec![
+            Box::new(adachi::Adachi::with_known_maze(
+                closed.clone(),
+                actual.get_start(),
+            )),
+            Box::new(adachi::Adachi::with_known_maze(closed, actual.get_start())),
+        ]);
+        let walled = path_finder::Observation::new(Wall::Present, Wall::Present, Wall::Present);
+        assert_eq!(
+            chain.navigate_step_observed(&walled, goal),
+            Err(path_finder::NavigationError::NoPath)
+        );
+        assert_eq!(
+            chain.failures(),
+            &[
+                (0, path_finder::NavigationError::NoPath),
+                (1, path_finder::NavigationError::NoPath)
+            ]
+        );
+    }
+
+    #[test]
+    fn mission_phases() {
+        use mission::{MissionEvent, MissionPhase};
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        let mut mission = mission::Mission::new(solver, mission::MissionConfig::default());
+        let mut sim = simulator::Simulator::new(actual);
+        let mut phases = vec![];
+        let mut fast_moves = 0;
+        for _ in 0..3000 {
+            let o = sim.sense();
+            let Some(direction) = mission.navigate(o.front, o.left, o.right).unwrap() else {
+                break;
+            };
+            if mission.phase() == MissionPhase::FastRun {
+                fast_moves += 1;
+            }
+            mission.set_location(sim.step(direction).unwrap());
+            for MissionEvent::PhaseChanged { to, .. } in mission.take_events() {
+                phases.push(to);
+            }
+        }
+        for MissionEvent::PhaseChanged { to, .. } in mission.take_events() {
+            phases.push(to);
+        }
+        assert_eq!(
+            phases,
+            vec![
+                MissionPhase::Return,
+                MissionPhase::FastRun,
+                MissionPhase::Finished
+            ]
+        );
+        assert_eq!(fast_moves + 1, mission.fast_route().len());
+        assert_eq!(mission.speed(), 1.0);
+    }
+
+    #[test]
+    fn mission_budget() {
+        use mission::MissionPhase;
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let budget = mission::Budget {
+            max_steps: Some(200),
+            max_seconds: Some(30.0),
+            seconds_per_step: 0.25,
+            margin: 2,
+        };
+        assert_eq!(budget.steps(), 120);
+        let config = mission::MissionConfig {
+            budget: Some(budget),
+            ..Default::default()
+        };
+        let solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        let mut mission = mission::Mission::new(solver, config);
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let mut visited_goal = false;
+        for _ in 0..3000 {
+            let o = sim.sense();
+            let Some(direction) = mission.navigate(o.front, o.left, o.right).unwrap() else {
+                break;
+            };
+            mission.set_location(sim.step(direction).unwrap());
+            visited_goal |= mission.get_location().pos == actual.get_goal();
+        }
+        assert!(!visited_goal);
+        assert!(mission.out_of_budget());
+        assert_eq!(mission.phase(), MissionPhase::Finished);
+        assert_eq!(mission.get_location().pos, actual.get_start().pos);
+        assert!(mission.steps_taken() <= 120);
+
+        // Dropped somewhere with no known way back: head home at once
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_location(maze::Location::new(
+            maze::Position::new(3, 3),
+            maze::Compass::North,
+        ));
+        let mut mission = mission::Mission::new(solver, config);
+        let open = maze::Wall::Absent;
+        assert!(mission.navigate(open, open, open).unwrap().is_some());
+        assert!(mission.out_of_budget());
+        assert_eq!(mission.phase(), MissionPhase::Return);
+
+        // A failed navigate does not use up the budget
+        let solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        let mut mission = mission::Mission::new(solver, config);
+        // Opening in the west outer wall
+        assert!(mission.navigate(open, open, maze::Wall::Present).is_err());
+        assert_eq!(mission.steps_taken(), 0);
+    }
+
+    #[test]
+    fn shortest_path_confirmation() {
+        use maze::{Compass, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let start = maze.get_start();
+        assert!(adachi::Adachi::with_known_maze(maze.clone(), start).is_shortest_path_confirmed());
+        assert!(!adachi::Adachi::new(maze::Maze::new(4, 4)).is_shortest_path_confirmed());
+
+        // The goal's north wall might be a shortcut
+        let mut partial = maze.clone();
+        partial.set(2, 1, Compass::North, Wall::Unexplored);
+        assert!(!adachi::Adachi::with_known_maze(partial, start).is_shortest_path_confirmed());
+    }
+
+    #[test]
+    fn goal_area_sweep() {
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let area = actual.center_goal_area();
+        assert_eq!(area.len(), 4);
+        assert!(area.contains(&goal));
+
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_goal_sweep(Some(area.clone()));
+        let mut sim = simulator::Simulator::new(actual);
+        for _ in 0..2000 {
+            match solver.navigate_observed(&sim.sense(), goal) {
+                Ok(direction) => solver.set_location(sim.step(direction).unwrap()),
+                Err(_) => break,
+            }
+        }
+        assert_eq!(solver.get_location().pos, goal);
+        for cell in area {
+            let walls = solver.get_maze().cell_walls(cell.y, cell.x);
+            for compass in maze::Compass::iter() {
+                assert_ne!(walls.get(compass), maze::Wall::Unexplored, "{:?}", cell);
+            }
+        }
+    }
+
+    #[test]
+    fn navigation_step() {
+        use maze::{Direction, Location, Position, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = maze.get_goal();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        let step = solver
+            .navigate_step(Wall::Absent, Wall::Present, Wall::Present, goal)
+            .unwrap();
+        assert_eq!(step.direction, Some(Direction::Forward));
+        assert_eq!(step.expected_remaining_steps, Some(13));
+        assert!(!step.goal_reached);
+
+        solver.set_location(Location::new(goal, maze::Compass::South));
+        let step = solver
+            .navigate_step(Wall::Absent, Wall::Present, Wall::Absent, goal)
+            .unwrap();
+        assert!(step.goal_reached);
+        assert_eq!(step.direction, None);
+        // navigate keeps reporting the goal as an error
+        assert!(solver
+            .navigate(Wall::Absent, Wall::Present, Wall::Absent, goal)
+            .is_err());
+        assert_eq!(solver.get_location().pos, Position::new(1, 2));
+    }
+
+    #[test]
+    fn navigation_errors() {
+        use maze::{Compass, Location, Position, Wall};
+        use path_finder::NavigationError;
+        let goal = Position::new(2, 2);
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        assert_eq!(
+            solver.navigate(Wall::Present, Wall::Present, Wall::Present, goal),
+            Err(NavigationError::NoPath)
+        );
+        // An opening towards the west edge means the location is wrong
+        assert_eq!(
+            solver.navigate(Wall::Absent, Wall::Absent, Wall::Present, goal),
+            Err(NavigationError::InconsistentMap)
+        );
+        assert_eq!(
+            solver.navigate(
+                Wall::Absent,
+                Wall::Present,
+                Wall::Present,
+                Position::new(4, 0)
+            ),
+            Err(NavigationError::OutOfBounds)
+        );
+        solver.set_location(Location::new(goal, Compass::North));
+        assert_eq!(
+            solver.navigate(Wall::Absent, Wall::Absent, Wall::Absent, goal),
+            Err(NavigationError::GoalReached)
+        );
+    }
+
+    #[test]
+    fn dyn_path_finder() {
+        use path_finder::PathFinder;
+        use wall_follower::{Hand, WallFollower};
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = actual.get_goal();
+        for name in ["adachi", "explorer", "left-hand"] {
+            let blank = maze::Maze::new(4, 4);
+            let mut finder: Box<dyn PathFinder> = match name {
+                "adachi" => Box::new(adachi::Adachi::new(blank)),
+                "explorer" => Box::new(explorer::TargetedExplorer::new(adachi::Adachi::new(blank))),
+                _ => Box::new(WallFollower::new(blank, Hand::Left)),
+            };
+            let mut sim = simulator::Simulator::new(actual.clone());
+            assert!(sim.run(&mut finder, goal, 200).is_ok(), "{}", name);
+            assert_eq!(finder.get_location().pos, goal);
+
+            // A Box and a &mut are PathFinders too, e.g. for Waypoints
+            let by_ref: &mut dyn PathFinder = &mut finder;
+            let mut waypoints = waypoint::Waypoints::new(by_ref, vec![goal]);
+            assert_eq!(
+                waypoints
+                    .navigate(maze::Wall::Absent, maze::Wall::Absent, maze::Wall::Absent)
+                    .unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn fault_injection() {
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let faults = simulator::FaultConfig {
+            missed_move: 0.05,
+            spurious_turn: 0.05,
+            delayed_walls: 0.05,
+            restart: 0.002,
+            seed: 42,
+        };
+        let mut logs = vec![];
+        for _ in 0..2 {
+            let mut sim = simulator::Simulator::new(actual.clone());
+            sim.set_faults(Some(faults));
+            let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+            let steps = sim.run(&mut solver, goal, 20000).unwrap();
+            assert_eq!(solver.get_maze().is_consistent_with(&actual), Ok(()));
+            logs.push((steps, sim.fault_log()));
+        }
+        // Same seed, same run
+        assert_eq!(logs[0], logs[1]);
+        let log = logs[0].1;
+        assert!(log.missed_moves > 0 && log.spurious_turns > 0 && log.delayed_walls > 0);
+    }
+
+    #[cfg(feature = "provenance")]
+    #[test]
+    fn wall_provenance() {
+        use maze::{Compass, Position};
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let goal = actual.get_goal();
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        sim.run(&mut solver, goal, 100).unwrap();
+        let first = solver.provenance().added_in(0);
+        assert!(!first.is_empty());
+        // The first observation at the start
+        let start = solver.provenance().get(Position::new(0, 0), Compass::North);
+        assert_eq!(start, Some(provenance::Discovery { run: 0, step: 1 }));
+        assert_eq!(
+            solver.provenance().get(Position::new(0, 1), Compass::South),
+            start
+        );
+
+        solver.reset(true);
+        sim.set_location(solver.get_location());
+        sim.run(&mut solver, goal, 100).unwrap();
+        assert_eq!(solver.provenance().added_in(0), first);
+        assert_eq!(
+            solver.provenance().walls().len(),
+            first.len() + solver.provenance().added_in(1).len()
+        );
+
+        // Range readings belong to the step that made them
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let observation = path_finder::Observation {
+            ahead: Some(path_finder::RangeReading {
+                open_cells: 3,
+                wall: false,
+            }),
+            ..path_finder::Observation::new(
+                maze::Wall::Absent,
+                maze::Wall::Present,
+                maze::Wall::Present,
+            )
+        };
+        solver.navigate_step_observed(&observation, goal).unwrap();
+        assert_eq!(
+            solver.provenance().get(Position::new(0, 2), Compass::North),
+            Some(provenance::Discovery { run: 0, step: 1 })
+        );
+    }
+
+    #[test]
+    fn revisit_penalty() {
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_revisit_penalty(3);
+        let steps = sim.run(&mut solver, goal, 2000).unwrap();
+        let total: usize = solver.visits().iter().flatten().map(|c| *c as usize).sum();
+        assert_eq!(total, steps);
+        assert_eq!(solver.visit_count(actual.get_start().pos), 1);
+        let visited = solver.visited_cells();
+        assert!(visited.contains(&actual.get_start().pos) && !visited.contains(&goal));
+        assert!(solver.display_step_map_with(&visited).contains(" . "));
+
+        // The fast run plan is unaffected by the penalty
+        let route = solver.plan_fast_run(actual.get_start().pos, goal).unwrap();
+        assert!(actual
+            .validate_path(&route, adachi::StepMapMode::UnexploredAsPresent)
+            .is_ok());
+        solver.reset(true);
+        assert!(solver.visits().is_empty());
+    }
+
+    #[test]
+    fn stall_detection() {
+        use adachi::{StallDetection, StallRecovery};
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+
+        // A normal search is not a stall
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_stall_detection(Some(StallDetection::default()));
+        sim.run(&mut solver, goal, 2000).unwrap();
+        assert!(solver.last_stall().is_none());
+
+        // Every move fails: the solver never leaves the start
+        let stuck = || {
+            let mut sim = simulator::Simulator::new(actual.clone());
+            sim.set_faults(Some(simulator::FaultConfig {
+                missed_move: 1.0,
+                ..Default::default()
+            }));
+            sim
+        };
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_stall_detection(Some(StallDetection::default()));
+        let error = stuck().run(&mut solver, goal, 2000).unwrap_err();
+        assert_eq!(
+            error,
+            path_finder::NavigationError::Stuck {
+                steps: 32,
+                cells: 1
+            }
+            .to_string()
+        );
+        assert_eq!(
+            solver.last_stall().unwrap().cells,
+            vec![actual.get_start().pos]
+        );
+
+        // A callback that keeps going hits the step limit instead
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_stall_detection(Some(StallDetection::default()));
+        solver.set_stall_callback(Box::new(|_| StallRecovery::RaisePenalty(2)));
+        let error = stuck().run(&mut solver, goal, 100).unwrap_err();
+        assert!(error.contains("Step limit"));
+        assert!(solver.last_stall().is_some());
+        let restored = adachi::Adachi::from_bytes(&solver.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.last_stall(), solver.last_stall());
+
+        // The history survives a save: both stall after the same steps
+        let steps_to_stall = |mut solver: adachi::Adachi| {
+            let mut sim = stuck();
+            (1..)
+                .find(|_| sim.tick(&mut solver, goal).is_err())
+                .unwrap()
+        };
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_stall_detection(Some(StallDetection::default()));
+        stuck().run(&mut solver, goal, 20).unwrap_err();
+        let restored = adachi::Adachi::from_bytes(&solver.to_bytes().unwrap()).unwrap();
+        let expected = steps_to_stall(solver);
+        assert!(expected < 32);
+        assert_eq!(steps_to_stall(restored), expected);
+
+        // Walls found by the range sensor count as discoveries
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_stall_detection(Some(StallDetection {
+            window: 4,
+            max_cells: 1,
+        }));
+        let start = solver.get_location();
+        for open_cells in 2..10 {
+            solver.set_location(start);
+            let observation = path_finder::Observation {
+                ahead: Some(path_finder::RangeReading {
+                    open_cells,
+                    wall: false,
+                }),
+                ..path_finder::Observation::new(
+                    maze::Wall::Absent,
+                    maze::Wall::Present,
+                    maze::Wall::Present,
+                )
+            };
+            solver.navigate_step_observed(&observation, goal).unwrap();
+        }
+        assert!(solver.last_stall().is_none());
+    }
+
+    #[test]
+    fn safety_margin_route() {
+        use maze::{Compass, Position, Wall};
+        // Open 5x5 field, one unexplored wall between (0,2) and (1,2)
+        let mut maze = open_field(5, 5);
+        maze.set(2, 0, Compass::East, Wall::Unexplored);
+        let (start, goal) = (Position::new(0, 0), Position::new(4, 4));
+        let near = [Position::new(0, 2), Position::new(1, 2)];
+        let mut solver = adachi::Adachi::new(maze);
+
+        let plain = solver.plan_fast_run(start, goal).unwrap();
+        assert!(plain.iter().any(|p| near.contains(p)));
+        let mode = adachi::StepMapMode::SafetyMargin {
+            margin: 0,
+            penalty: 10,
+        };
+        let safe = solver.plan_fast_run_with(start, goal, mode).unwrap();
+        assert_eq!(safe.len(), plain.len());
+        assert!(safe.iter().all(|p| !near.contains(p)));
+
+        // Modes that cross unexplored walls cannot plan a verified route
+        for mode in [
+            adachi::StepMapMode::UnexploredAsAbsent,
+            adachi::StepMapMode::UnexploredWithPenalty(3),
+        ] {
+            assert!(solver.plan_fast_run_with(start, goal, mode).is_err());
+        }
+
+        // Once the wall is known the margin no longer pushes the route away
+        solver.absorb(&open_field(5, 5)).unwrap();
+        let known = solver.plan_fast_run(start, goal).unwrap();
+        assert_eq!(solver.plan_fast_run_with(start, goal, mode).unwrap(), known);
+    }
+
+    #[test]
+    fn multi_mouse_simulation() {
+        use simulator::{Collision, Interleave, MultiSimulator};
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let mut solo = simulator::Simulator::new(actual.clone());
+        let solo_steps = solo
+            .run(
+                &mut adachi::Adachi::new(maze::Maze::new(16, 16)),
+                goal,
+                2000,
+            )
+            .unwrap();
+
+        // The first mouse knows the maze, the second one follows it out
+        let mut multi = MultiSimulator::new(actual.clone(), Interleave::Fixed, Collision::Wait);
+        let start = actual.get_start();
+        multi.add(
+            Box::new(adachi::Adachi::with_known_maze(actual.clone(), start)),
+            goal,
+        );
+        multi.add(Box::new(adachi::Adachi::new(maze::Maze::new(16, 16))), goal);
+        let results = multi.run(2000).unwrap();
+        assert!(results.iter().all(|r| r.reached));
+        assert_eq!(results[1].steps, solo_steps);
+        assert!(results[1].waits > 0);
+        assert_eq!(multi.location(1).pos, goal);
+
+        // Two searchers on the same route meet head-on in a dead end
+        let mut multi = MultiSimulator::new(actual.clone(), Interleave::Rotating, Collision::Wait);
+        for _ in 0..2 {
+            multi.add(Box::new(adachi::Adachi::new(maze::Maze::new(16, 16))), goal);
+        }
+        assert!(multi.run(2000).unwrap_err().starts_with("Deadlock"));
+
+        let mut multi = MultiSimulator::new(actual, Interleave::Fixed, Collision::Ignore);
+        for _ in 0..2 {
+            multi.add(Box::new(adachi::Adachi::new(maze::Maze::new(16, 16))), goal);
+        }
+        let results = multi.run(2000).unwrap();
+        assert!(results.iter().all(|r| r.reached && r.waits == 0));
+    }
+
+    #[test]
+    fn dot_export() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let dot = maze.to_dot(None);
+        assert!(dot.starts_with("graph maze {"));
+        assert!(
+            dot.contains("c0_0 [label=\"0,0\", pos=\"0,0!\", style=filled, fillcolor=lightblue];")
+        );
+        assert!(dot.contains("fillcolor=gold"));
+        // 24 inner walls, 8 of them present
+        assert_eq!(dot.matches(" -- ").count(), 16);
+        assert!(!dot.contains("dashed"));
+
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        solver.calc_step_map(maze.get_goal());
+        let dot = solver.get_maze().to_dot(Some(solver.step_map()));
+        assert!(dot.contains("c1_2 [label=\"1,2\\n0\""));
+        assert!(dot.contains("[style=dashed]"));
+    }
+
+    #[test]
+    fn occupancy_grid() {
+        use occupancy::{OccupancyConfig, FREE, OCCUPIED, UNKNOWN};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let config = OccupancyConfig::new(4, 1, 0.045).unwrap();
+        assert!(OccupancyConfig::new(4, 4, 0.01).is_err());
+        assert!(OccupancyConfig::new(2, 5, 0.01).is_err());
+        assert!(OccupancyConfig::new(4, 0, 0.01).is_err());
+        let grid = maze.to_occupancy_grid(&config);
+        assert_eq!((grid.width, grid.height), (17, 17));
+        // Outer wall, pillar, cell interior (rows from the top)
+        assert_eq!(grid.get(2, 0), OCCUPIED);
+        assert_eq!(grid.get(4, 4), OCCUPIED);
+        assert_eq!(grid.get(2, 2), FREE);
+        // Absent wall between (0,3) and (0,2), present one between (1,3) and (1,2)
+        assert_eq!(grid.get(2, 4), FREE);
+        assert_eq!(grid.get(6, 4), OCCUPIED);
+
+        let unexplored = maze::Maze::new(4, 4).to_occupancy_grid(&config);
+        assert_eq!(unexplored.get(2, 4), UNKNOWN);
+
+        let mut pgm = vec![];
+        grid.write_pgm(&mut pgm).unwrap();
+        assert!(pgm.starts_with(b"P5\n17 17\n255\n"));
+        assert_eq!(pgm.len(), 13 + 17 * 17);
+        let mut yaml = vec![];
+        grid.write_yaml(&mut yaml, "small.pgm").unwrap();
+        let yaml = String::from_utf8(yaml).unwrap();
+        assert!(yaml.contains("image: small.pgm\nresolution: 0.045\n"));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_import() {
+        let check = |maze: maze::Maze, cell_pixels: usize, wall_pixels: usize| {
+            let config = occupancy::OccupancyConfig::new(cell_pixels, wall_pixels, 0.01).unwrap();
+            let grid = maze.to_occupancy_grid(&config);
+            // White margin around the maze like on a scan
+            let margin = 7;
+            let image = ::image::GrayImage::from_fn(
+                (grid.width + 2 * margin) as u32,
+                (grid.height + 2 * margin) as u32,
+                |x, y| {
+                    let (x, y) = (x as usize, y as usize);
+                    let inside = (margin..margin + grid.width).contains(&x)
+                        && (margin..margin + grid.height).contains(&y);
+                    let v = if inside {
+                        grid.get(x - margin, y - margin)
+                    } else {
+                        255
+                    };
+                    ::image::Luma([v])
+                },
+            );
+            let imported = image_import::maze_from_image(&image, 128).unwrap();
+            assert_eq!(imported.is_consistent_with(&maze), Ok(()));
+        };
+        check(SMALL_MAZE.parse().unwrap(), 10, 2);
+        check(
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap(),
+            18,
+            2,
+        );
+        let blank = ::image::GrayImage::from_pixel(20, 20, ::image::Luma([255]));
+        assert!(image_import::maze_from_image(&blank, 128).is_err());
+    }
+
+    #[test]
+    fn constrained_generation() {
+        use maze::Compass;
+        let mut rng = rng::XorShift64::new(7);
+        let maze = generator::generate(16, 16, &mut rng);
+        // A spanning tree has 255 openings, opening the goal area adds 1 to 3
+        let stats = stats::stats(&maze);
+        assert!(stats.shortest_path_length.is_some());
+        let openings = (0..16)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                [Compass::North, Compass::East]
+                    .iter()
+                    .filter(|c| maze.get(y, x, **c) == maze::Wall::Absent)
+                    .count()
+            })
+            .sum::<usize>();
+        assert!((256..=258).contains(&openings));
+        assert_eq!(
+            generator::generate(16, 16, &mut rng::XorShift64::new(7)),
+            maze
+        );
+
+        let constraints = generator::Constraints {
+            shortest_path: Some((30, 60)),
+            min_shortest_paths: 2,
+            dead_end_density: Some((0.05, 0.2)),
+        };
+        let maze = generator::generate_constrained(16, 16, &constraints, 1, 200).unwrap();
+        assert!(constraints.is_satisfied(&maze));
+        let impossible = generator::Constraints {
+            shortest_path: Some((1, 2)),
+            ..Default::default()
+        };
+        assert!(generator::generate_constrained(16, 16, &impossible, 1, 3).is_err());
+    }
+
+    #[test]
+    fn maze_macro() {
+        let maze = maze! {
+            "+-+-+-+-+"
+            "|       |"
+            "+ +-+-+ +"
+            "| |G  | |"
+            "+ + + + +"
+            "| |   | |"
+            "+ +-+ + +"
+            "| |     |"
+            "+-+-+-+-+"
+        };
+        assert_eq!(maze, SMALL_MAZE.parse().unwrap());
+
+        let error = std::panic::catch_unwind(|| {
+            maze! {
+                "+-+-+"
+                "|G  |"
+                "+ +x+"
+                "|   |"
+                "+-+-+"
+            }
+        })
+        .unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            "maze!: line 3, column 4: expected ' ', '-' or '?', found 'x'"
+        );
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(32))]
+        // The simulator refuses moves through present walls
+        #[test]
+        fn navigate_respects_walls(actual in proptest::prelude::any::<maze::Maze>()) {
+            let goal = actual.get_goal();
+            let (width, height) = (actual.get_width(), actual.get_height());
+            let mut sim = simulator::Simulator::new(actual);
+            let mut solver = adachi::Adachi::new(maze::Maze::new(width, height));
+            proptest::prop_assert!(sim.run(&mut solver, goal, 10000).is_ok());
+        }
+
+        // Quarter turns keep a location inside the turned maze and four of
+        // them bring it back
+        #[test]
+        fn location_quarter_turns((maze, location) in arbitrary::maze_and_location()) {
+            let turn = symmetry::Symmetry {
+                mirrored: false,
+                quarter_turns: 1,
+            };
+            let (mut width, mut height) = (maze.get_width(), maze.get_height());
+            let mut moved = location;
+            for _ in 0..4 {
+                moved = turn.location(moved, width, height);
+                (width, height) = turn.size(width, height);
+                proptest::prop_assert!(moved.pos.x < width && moved.pos.y < height);
+            }
+            proptest::prop_assert_eq!(moved, location);
+        }
+    }
+
+    #[test]
+    fn flat_wall_storage_serde() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let json: serde_json::Value = serde_json::to_value(&maze).unwrap();
+        // Still nested arrays: 5 rows of 4 horizontal walls, 4 rows of 5 vertical ones
+        let rows = json["horizontal_walls"].as_array().unwrap();
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| row.as_array().unwrap().len() == 4));
+        assert_eq!(json["vertical_walls"][0].as_array().unwrap().len(), 5);
+        let back: maze::Maze = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(back, maze);
+
+        // Grids that do not match the size are rejected
+        let mut empty = json.clone();
+        empty["horizontal_walls"] = serde_json::json!([]);
+        empty["vertical_walls"] = serde_json::json!([]);
+        assert!(serde_json::from_value::<maze::Maze>(empty).is_err());
+        let mut ragged = json.clone();
+        ragged["vertical_walls"][2] = serde_json::json!(["Present"]);
+        assert!(serde_json::from_value::<maze::Maze>(ragged).is_err());
+        let mut wider = json;
+        wider["width"] = serde_json::json!(5);
+        assert!(serde_json::from_value::<maze::Maze>(wider).is_err());
+    }
+
+    #[test]
+    fn step_map_early_exit() {
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let mut routes = vec![];
+        for early_exit in [None, Some(0)] {
+            let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+            solver.set_early_exit(early_exit);
+            let route: Vec<_> = solver
+                .solve_iter(|location| {
+                    let mut sim = simulator::Simulator::new(actual.clone());
+                    sim.set_location(location);
+                    sim.sense()
+                })
+                .map(|step| step.unwrap().0)
+                .collect();
+            routes.push(route);
+        }
+        assert_eq!(routes[0], routes[1]);
+
+        let mut full = adachi::Adachi::with_known_maze(actual.clone(), actual.get_start());
+        full.calc_step_map(goal);
+        let mut partial = adachi::Adachi::with_known_maze(actual.clone(), actual.get_start());
+        partial.set_early_exit(Some(2));
+        // Only navigate stops early, other consumers get the whole map
+        partial.calc_step_map(goal);
+        assert_eq!(partial.step_map(), full.step_map());
+        let seen = simulator::Simulator::new(actual.clone()).sense();
+        partial
+            .navigate_step(seen.front, seen.left, seen.right, goal)
+            .unwrap();
+        let start = actual.get_start().pos;
+        let robot_step = full.get_step(start.x, start.y);
+        assert_eq!(partial.get_step(start.x, start.y), robot_step);
+        for y in 0..16 {
+            for x in 0..16 {
+                let (f, p) = (full.get_step(x, y), partial.get_step(x, y));
+                if f <= robot_step + 2 {
+                    assert_eq!(p, f);
+                } else {
+                    assert_eq!(p, adachi::Adachi::NONE);
+                }
+            }
+        }
+        // The fast run plan still needs (and gets) the whole map
+        assert!(partial.plan_fast_run(start, goal).is_ok());
+    }
+
+    #[test]
+    fn step_map_access() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        assert_eq!(solver.try_get_step(0, 0), None);
+        assert!(solver.step_map().is_empty());
+        solver.calc_step_map(maze.get_goal());
+        assert_eq!(solver.try_get_step(0, 0), Some(13));
+        assert_eq!(solver.try_get_step(4, 0), None);
+        assert_eq!(solver.step_map().len(), 4);
+        assert_eq!(solver.step_map()[2][1], 0);
+    }
+
+    #[test]
+    fn step_map_export() {
+        use adachi::StepMapFormat;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        let mut out = vec![];
+        assert!(solver
+            .export_step_map(StepMapFormat::Csv, &mut out)
+            .is_err());
+        solver.calc_step_map(maze.get_goal());
+
+        solver
+            .export_step_map(StepMapFormat::Csv, &mut out)
+            .unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines, vec!["10,9,8,7", "11,0,1,6", "12,1,2,5", "13,4,3,4"]);
+
+        let mut out = vec![];
+        solver
+            .export_step_map(StepMapFormat::Json, &mut out)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["width"], 4);
+        assert_eq!(json["goal"]["y"], 2);
+        assert_eq!(json["steps"][0][0], 13);
+        assert_eq!(json["steps"][2][1], 0);
+    }
+
+    #[test]
+    fn heading_cost_map() {
+        use heading_map::{HeadingCostMap, MoveCosts};
+        use maze::{Compass, Location, Position};
+        let maze = open_field(5, 5);
+        let goal = Position::new(4, 4);
+        let mode = adachi::StepMapMode::UnexploredAsPresent;
+        let map = HeadingCostMap::compute(&maze, goal, mode, MoveCosts::default());
+        let start = Location::new(Position::new(0, 0), Compass::North);
+        // 4 straight north, turn, 3 straight east
+        assert_eq!(map.get(start), Some(10));
+        // Facing south: turn east first and once more north, never a U-turn
+        assert_eq!(map.get(Location::new(start.pos, Compass::South)), Some(12));
+        let route = map.route(&maze, start).unwrap();
+        assert_eq!(route.len(), 9);
+        assert_eq!(route[4], Position::new(0, 4));
+        assert!(maze.validate_path(&route, mode).is_ok());
+
+        // A zero penalty costs the same as an open wall
+        let blank = maze::Maze::new(5, 5);
+        let penalty = adachi::StepMapMode::UnexploredWithPenalty(0);
+        let map = HeadingCostMap::compute(&blank, goal, penalty, MoveCosts::default());
+        assert_eq!(map.get(start), Some(10));
+        assert_eq!(map.route(&blank, start).unwrap().len(), 9);
+
+        let real: maze::Maze =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let map = HeadingCostMap::compute(&real, real.get_goal(), mode, MoveCosts::default());
+        let route = map.route(&real, real.get_start()).unwrap();
+        assert!(real.validate_path(&route, mode).is_ok());
+    }
+
+    #[test]
+    fn cell_penalty_overlay() {
+        use maze::Position;
+        // Open 3x3 field
+        let maze = open_field(3, 3);
+        let (start, goal) = (Position::new(0, 0), Position::new(2, 2));
+        let mut solver = adachi::Adachi::new(maze);
+        let plain = solver.plan_fast_run(start, goal).unwrap();
+        assert!(plain.contains(&Position::new(0, 1)));
+
+        // Avoid the west column
+        solver.set_cell_penalty(Position::new(0, 1), 5);
+        solver.set_cell_penalty(Position::new(0, 2), 5);
+        assert_eq!(solver.cell_penalty(Position::new(0, 1)), 5);
+        let detour = solver.plan_fast_run(start, goal).unwrap();
+        assert_eq!(detour.len(), plain.len());
+        assert!(!detour.contains(&Position::new(0, 1)));
+        solver.calc_step_map(goal);
+        assert_eq!(solver.get_step(0, 1), 3 + 5);
+        // Dijkstra on the solver's graph sees the same costs
+        let mode = adachi::StepMapMode::UnexploredAsPresent;
+        let distance = |graph: &graph::MazeGraph, from: Position| {
+            graph.distances_from(graph.node(from))[graph.node(goal)]
+        };
+        let graph = solver.to_graph(mode);
+        assert_eq!(distance(&graph, Position::new(0, 1)), Some(3 + 5));
+        assert_eq!(distance(&graph, start), Some(4));
+        let plain_graph = solver.get_maze().to_graph(mode);
+        assert_eq!(distance(&plain_graph, Position::new(0, 1)), Some(3));
+
+        solver.clear_cell_penalties();
+        assert_eq!(solver.plan_fast_run(start, goal).unwrap(), plain);
+    }
+
+    #[test]
+    fn banned_cells() {
+        use maze::{Position, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let (start, goal) = (maze.get_start().pos, maze.get_goal());
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        let plain = solver.plan_fast_run(start, goal).unwrap();
+
+        // (2,1) leads into the goal area through (1,1) or (2,2)
+        solver.ban_cell(Position::new(1, 1));
+        let other = solver.plan_fast_run(start, goal).unwrap();
+        assert!(!other.contains(&Position::new(1, 1)));
+        assert_eq!(other.len(), plain.len());
+        assert_eq!(solver.get_maze(), &maze);
+
+        solver.set_early_exit(Some(0));
+        solver.calc_step_map(goal);
+        assert_eq!(solver.get_step(1, 1), adachi::Adachi::NONE);
+
+        solver.ban_cell(Position::new(2, 1));
+        assert!(solver.plan_fast_run(start, goal).is_err());
+        solver.ban_cell(goal);
+        assert_eq!(
+            solver.navigate_step(Wall::Absent, Wall::Present, Wall::Present, goal),
+            Err(path_finder::NavigationError::TargetBanned)
+        );
+        solver.clear_banned_cells();
+        assert_eq!(solver.plan_fast_run(start, goal).unwrap(), plain);
+    }
+
+    #[test]
+    fn cells_on_any_shortest_path() {
+        use maze::Position;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let (start, goal) = (maze.get_start().pos, maze.get_goal());
+        let mode = adachi::StepMapMode::UnexploredAsPresent;
+        let cells = shortest_paths::cells_on_shortest_paths(&maze, start, goal, mode);
+        // 14 route cells plus the second way into the goal
+        assert_eq!(cells.len(), 15);
+        assert!(cells.contains(&Position::new(1, 1)) && cells.contains(&Position::new(2, 2)));
+        assert!(!cells.contains(&Position::new(1, 0)));
+        for path in shortest_paths::enumerate_shortest_paths(&maze, start, goal, mode, 10) {
+            assert!(path.iter().all(|p| cells.contains(p)));
+        }
+        let unexplored = maze::Maze::new(4, 4);
+        assert!(shortest_paths::cells_on_shortest_paths(&unexplored, start, goal, mode).is_empty());
+    }
+
+    #[test]
+    fn bounded_flood_fill() {
+        use maze::Position;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        solver.calc_step_map_within(maze.get_goal(), 2);
+        let reached: Vec<u16> = solver
+            .step_map()
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|s| *s != adachi::Adachi::NONE)
+            .collect();
+        // Goal, (1,1), (2,2), (2,1)
+        assert_eq!(reached.len(), 4);
+        assert_eq!(solver.get_step(2, 1), 2);
+        assert_eq!(solver.get_step(0, 0), adachi::Adachi::NONE);
+
+        // Fresh map: the robot's own cell is the nearest frontier
+        let mut explorer = adachi::Adachi::new(maze::Maze::new(4, 4));
+        assert_eq!(
+            explorer.nearest_frontier_within(6),
+            Some(Position::new(0, 0))
+        );
+        assert_eq!(solver.nearest_frontier_within(6), None);
+    }
+
+    #[test]
+    fn maze_symmetry() {
+        use symmetry::Symmetry;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let key = symmetry::canonical_fingerprint(&maze);
+        for s in Symmetry::all() {
+            let turned = symmetry::transform(&maze, s);
+            assert_eq!(symmetry::canonical_fingerprint(&turned), key);
+        }
+        let quarter = Symmetry {
+            mirrored: false,
+            quarter_turns: 1,
+        };
+        let turned = symmetry::transform(&maze, quarter);
+        assert_eq!(
+            turned.get_start(),
+            maze::Location::new(maze::Position::new(0, 3), maze::Compass::East)
+        );
+        let mut back = maze.clone();
+        for _ in 0..4 {
+            back = symmetry::transform(&back, quarter);
+        }
+        assert_eq!(back, maze);
+        assert!(!symmetry::is_symmetric(&maze));
+
+        let options = maze::InitOptions {
+            start_wall: maze::StartWall::None,
+        };
+        let empty = maze::Maze::with_options(4, 4, options);
+        assert_eq!(symmetry::symmetries(&empty).len(), 8);
+        let (canonical, s) = symmetry::canonical_form(&maze);
+        assert_eq!(symmetry::transform(&maze, s), canonical);
+    }
+
+    #[test]
+    fn crop_and_embed() {
+        use maze::{Compass, Position, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let part = maze.crop(1, 1, 2, 2).unwrap();
+        assert_eq!((part.get_width(), part.get_height()), (2, 2));
+        // Goal (1,2) moves to (0,1), the start is outside
+        assert_eq!(part.get_goal(), Position::new(0, 1));
+        assert_eq!(part.get(0, 0, Compass::South), Wall::Present);
+        assert_eq!(
+            part.get(0, 0, Compass::North),
+            maze.get(1, 1, Compass::North)
+        );
+        assert!(maze.crop(3, 3, 2, 1).is_err());
+
+        // Putting a fragment back where it came from changes nothing
+        let mut copy = maze.clone();
+        copy.embed(&part, Position::new(1, 1)).unwrap();
+        assert_eq!(copy, maze);
+        assert_eq!(maze.crop(0, 0, 4, 4).unwrap(), maze);
+
+        let mut big = maze::Maze::new(8, 8);
+        big.embed(&maze, Position::new(4, 4)).unwrap();
+        assert_eq!(
+            big.crop(4, 4, 4, 4).unwrap().get(1, 1, Compass::North),
+            maze.get(1, 1, Compass::North)
+        );
+        assert_eq!(big.get(4, 4, Compass::South), Wall::Unexplored);
+        assert!(big.embed(&maze, Position::new(5, 0)).is_err());
+    }
+
+    #[test]
+    fn perturbed_variants() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let area = maze.center_goal_area();
+        let variants = generator::variants(&maze, 5, 3, 7);
+        assert_eq!(variants.len(), 5);
+        for variant in &variants {
+            assert_ne!(*variant, maze);
+            assert!(similarity::shortest_path_length(variant).is_some());
+            let agreement = similarity::wall_agreement(&maze, variant).unwrap();
+            assert!(agreement < 1.0);
+            // Start side wall and goal area untouched
+            assert_eq!(variant.get(0, 0, maze::Compass::East), maze::Wall::Present);
+            for pos in &area {
+                for compass in maze::Compass::iter() {
+                    let inside = maze
+                        .get_neighbor_cell(pos.y, pos.x, compass)
+                        .is_some_and(|(y, x)| area.contains(&maze::Position::new(x, y)));
+                    if inside {
+                        assert_eq!(
+                            variant.get(pos.y, pos.x, compass),
+                            maze.get(pos.y, pos.x, compass)
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(generator::variants(&maze, 5, 3, 7), variants);
+    }
+
+    #[test]
+    fn wall_change_events() {
+        use maze::{Compass, Wall, WallChange};
+        let mut maze = maze::Maze::new(4, 4);
+        let events = maze.subscribe();
+        maze.set(1, 2, Compass::North, Wall::Present);
+        // Outer walls cannot be removed, no event
+        maze.set(0, 0, Compass::South, Wall::Absent);
+        maze.set(1, 2, Compass::North, Wall::Absent);
+        let changes: Vec<WallChange> = events.try_iter().collect();
+        assert_eq!(
+            changes,
+            vec![
+                WallChange {
+                    y: 1,
+                    x: 2,
+                    compass: Compass::North,
+                    old: Wall::Unexplored,
+                    new: Wall::Present,
+                },
+                WallChange {
+                    y: 1,
+                    x: 2,
+                    compass: Compass::North,
+                    old: Wall::Present,
+                    new: Wall::Absent,
+                },
+            ]
+        );
+        // Clones do not notify the original subscribers
+        let mut copy = maze.clone();
+        copy.set(2, 2, Compass::East, Wall::Present);
+        assert!(events.try_recv().is_err());
+        assert_eq!(copy.get(2, 2, Compass::East), Wall::Present);
+
+        // merge and init report the walls they change
+        copy.set(0, 1, Compass::North, Wall::Absent);
+        assert_eq!(maze.merge(&copy).unwrap(), 2);
+        assert_eq!(events.try_iter().count(), 2);
+        maze.init();
+        let reset: Vec<WallChange> = events.try_iter().collect();
+        assert_eq!(reset.len(), 3);
+        assert!(reset.iter().all(|c| c.new == Wall::Unexplored));
+
+        // A receiver that does not read keeps the newest changes out
+        for _ in 0..maze::SUBSCRIBER_CAPACITY + 10 {
+            maze.set(2, 2, Compass::East, Wall::Present);
+        }
+        assert_eq!(events.try_iter().count(), maze::SUBSCRIBER_CAPACITY);
+
+        drop(events);
+        assert_eq!(maze.subscriber_count(), 1);
+        maze.set(2, 2, Compass::East, Wall::Present);
+        assert_eq!(maze.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn shared_maze_across_threads() {
+        use maze::{Compass, Wall};
+        let shared = shared::SharedMaze::new(maze::Maze::new(4, 4));
+        let writer = shared.clone();
+        std::thread::spawn(move || {
+            for x in 0..3 {
+                writer.set(1, x, Compass::East, Wall::Present);
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(shared.get(1, 2, Compass::East), Wall::Present);
+        assert_eq!(shared.get(1, 3, Compass::West), Wall::Present);
+        let snapshot = shared.snapshot();
+        shared.set(2, 0, Compass::North, Wall::Absent);
+        assert_eq!(snapshot.get(2, 0, Compass::North), Wall::Unexplored);
+        assert_eq!(shared.read().get(2, 0, Compass::North), Wall::Absent);
+    }
+
+    #[test]
+    fn protocol_round_trip() {
+        use maze::{Compass, Location, Position, Wall};
+        use protocol::{Message, Mirror};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut messages = Message::cell_walls(&maze, Position::new(1, 1)).unwrap();
+        messages
+            .push(Message::position(Location::new(Position::new(1, 1), Compass::East)).unwrap());
+        messages.push(Message::goal_reached(maze.get_goal(), 9).unwrap());
+        let snapshot = Message::Snapshot(protocol::Snapshot { maze: maze.clone() });
+        assert_eq!(snapshot.encode().unwrap().len(), 8 + 8 + 3);
+        messages.push(snapshot);
+        // Zones and init options travel with the snapshot
+        let mut zoned = maze.clone();
+        zoned
+            .add_zone(maze::Zone {
+                min: Position::new(1, 0),
+                max: Position::new(2, 3),
+                weight: 300,
+            })
+            .unwrap();
+        zoned.set_init_options(maze::InitOptions {
+            start_wall: maze::StartWall::None,
+        });
+        let bytes = Message::Snapshot(protocol::Snapshot {
+            maze: zoned.clone(),
+        })
+        .encode()
+        .unwrap();
+        assert_eq!(bytes.len(), 8 + 8 + 3 + 6);
+        assert_eq!(
+            Message::decode(&bytes).unwrap(),
+            Message::Snapshot(protocol::Snapshot { maze: zoned })
+        );
+        assert!(Message::decode(&bytes[..bytes.len() - 1]).is_err());
+        // Goal or start outside the maze
+        let mut bad = bytes.clone();
+        bad[3] = 4;
+        assert!(Message::decode(&bad).unwrap_err().contains("outside"));
+        let mut bad = bytes;
+        bad[6] = 9;
+        assert!(Message::decode(&bad).unwrap_err().contains("outside"));
+
+        let mut mirror = Mirror::new(maze::Maze::new(4, 4));
+        for message in &messages {
+            let bytes = message.encode().unwrap();
+            assert_eq!(&Message::decode(&bytes).unwrap(), message);
+            mirror.apply_bytes(&bytes).unwrap();
+        }
+        assert_eq!(mirror.maze, maze);
+        assert_eq!(mirror.location.dir, Compass::East);
+        assert_eq!(mirror.goal_reached, Some((maze.get_goal(), 9)));
+
+        assert_eq!(
+            Message::wall(Position::new(2, 3), Compass::West, Wall::Present)
+                .unwrap()
+                .encode()
+                .unwrap(),
+            vec![1, 2, 3, 0b1101]
+        );
+        assert!(Message::decode(&[1, 2, 3]).is_err());
+        assert!(Message::decode(&[9]).is_err());
+        assert!(mirror.apply_bytes(&[2, 7, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn framed_stream_resyncs() {
+        use maze::{Compass, Position, Wall};
+        use protocol::Message;
+        assert_eq!(framing::crc16(b"123456789"), 0x29B1);
+        let long: Vec<u8> = (0..600).map(|i| (i % 7) as u8).collect();
+        assert_eq!(
+            framing::cobs_decode(&framing::cobs_encode(&long)).unwrap(),
+            long
+        );
+        assert!(!framing::cobs_encode(&long).contains(&0));
+
+        let first = Message::wall(Position::new(0, 0), Compass::North, Wall::Absent).unwrap();
+        let second = Message::goal_reached(Position::new(0, 0), 0).unwrap();
+        let third = Message::Snapshot(protocol::Snapshot {
+            maze: SMALL_MAZE.parse().unwrap(),
+        });
+        let mut stream = vec![0x55, 0x13];
+        stream.extend(framing::encode_frame(&first).unwrap());
+        let mut corrupted = framing::encode_frame(&second).unwrap();
+        corrupted[2] ^= 0x40;
+        stream.extend(corrupted);
+        stream.extend(framing::encode_frame(&second).unwrap());
+        stream.extend(framing::encode_frame(&third).unwrap());
+
+        let mut decoder = framing::FrameDecoder::default();
+        // Arbitrary chunks, as they come off a serial port
+        let mut received = vec![];
+        for chunk in stream.chunks(5) {
+            received.extend(decoder.feed(chunk));
+        }
+        // The leading garbage merges into the first frame and breaks it
+        assert_eq!(received, vec![second, third]);
+        assert_eq!(decoder.dropped(), 2);
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn postcard_round_trip() {
+        use maze::{Compass, Location, Position};
+        let maze =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let bytes = compact::to_postcard(&maze).unwrap();
+        assert!(bytes.len() * 4 < serde_json::to_vec(&maze).unwrap().len());
+        assert_eq!(compact::from_postcard::<maze::Maze>(&bytes).unwrap(), maze);
+
+        let location = Location::new(Position::new(3, 4), Compass::West);
+        let bytes = compact::to_postcard(&location).unwrap();
+        assert_eq!(
+            compact::from_postcard::<Location>(&bytes).unwrap(),
+            location
+        );
+
+        let message = protocol::Message::position(location).unwrap();
+        let bytes = compact::to_postcard(&message).unwrap();
+        assert_eq!(
+            compact::from_postcard::<protocol::Message>(&bytes).unwrap(),
+            message
+        );
+        assert!(compact::from_postcard::<maze::Maze>(&bytes).is_err());
+    }
+
+    #[test]
+    fn mms_session() {
+        use maze::Position;
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut finder = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        // wasReset, wallFront, wallLeft, wallRight, moveForward
+        let answers = "false\nfalse\ntrue\ntrue\nack\n";
+        let mut output = vec![];
+        let mut session = mms::Mms::new(answers.as_bytes(), &mut output);
+        let steps = mms::run(&mut finder, &mut session, Position::new(0, 1), 10).unwrap();
+        assert_eq!(steps, 1);
+        assert_eq!(finder.get_location().pos, Position::new(0, 1));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "wasReset\nwallFront\nwallLeft\nwallRight\n\
+             setWall 0 0 e\nsetWall 0 0 s\nsetWall 0 0 w\nmoveForward\n"
+        );
+
+        let mut session = mms::Mms::new("16\nmaybe\n".as_bytes(), std::io::sink());
+        assert_eq!(session.maze_width().unwrap(), 16);
+        assert!(session.wall_front().is_err());
+        assert!(session.wall_left().is_err());
+    }
+
+    #[cfg(feature = "serial")]
+    #[test]
+    fn serial_bridge_feed() {
+        use bridge::{Bridge, BridgeFormat};
+        use maze::{Compass, Position, Wall};
+        use std::cell::Cell;
+        use std::rc::Rc;
+        let mut bridge = Bridge::new(maze::Maze::new(4, 4), BridgeFormat::WallLog);
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        bridge.set_callback(Some(Box::new(move |_, _| counter.set(counter.get() + 1))));
+        let log = "boot ok\n[INFO] Y: 0, X: 0, Dir:N, Wall:| |, Go:F^\nY: 1, X: 0, Dir:N, Wa";
+        assert_eq!(bridge.feed(log.as_bytes()), 1);
+        assert_eq!(bridge.feed(b"ll:|-|, Go:Bv\n"), 1);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(bridge.location().pos, Position::new(0, 1));
+        assert_eq!(bridge.maze().get(0, 0, Compass::North), Wall::Absent);
+        assert_eq!(bridge.maze().get(1, 0, Compass::North), Wall::Present);
+        assert_eq!(bridge.maze().get(1, 0, Compass::East), Wall::Present);
+
+        let mut bridge = Bridge::new(maze::Maze::new(4, 4), BridgeFormat::Binary);
+        let message =
+            protocol::Message::wall(Position::new(2, 2), Compass::South, Wall::Present).unwrap();
+        let frame = framing::encode_frame(&message).unwrap();
+        assert_eq!(bridge.feed(&frame), 1);
+        assert_eq!(bridge.maze().get(1, 2, Compass::North), Wall::Present);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn websocket_telemetry() {
+        use maze::{Compass, Location, Position, Wall};
+        use telemetry::{TelemetryEvent, TelemetryServer};
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let mut server = TelemetryServer::bind("127.0.0.1:0", solver.subscribe()).unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        // A client that never completes its handshake does not block publish
+        let silent = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            server.publish(solver.get_maze(), solver.get_location(), None);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(started.elapsed() < std::time::Duration::from_millis(500));
+        assert_eq!(server.client_count(), 0);
+        let client = std::thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(url).unwrap();
+            (0..3)
+                .map(|_| {
+                    let text = socket.read().unwrap().into_text().unwrap();
+                    serde_json::from_str::<TelemetryEvent>(&text).unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+        let start = solver.get_location();
+        while server.client_count() == 0 {
+            server.publish(solver.get_maze(), start, None);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        solver.observe(Wall::Absent, Wall::Present, Wall::Present);
+        let moved = Location::new(Position::new(0, 1), Compass::North);
+        server.publish(solver.get_maze(), moved, None);
+
+        let events = client.join().unwrap();
+        assert!(matches!(events[0], TelemetryEvent::Snapshot { pose, .. } if pose == start));
+        // Only the front wall changed, the side walls were already known
+        assert_eq!(
+            events[1],
+            TelemetryEvent::Wall {
+                x: 0,
+                y: 0,
+                compass: Compass::North,
+                wall: Wall::Absent
+            }
+        );
+        assert_eq!(events[2], TelemetryEvent::Pose { pose: moved });
+        drop(silent);
+    }
+
+    #[test]
+    fn known_walls_exchange() {
+        use maze::{Compass, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let list = maze.to_present_wall_list();
+        assert!(list.iter().all(|r| r.state == Wall::Present));
+        let mut csv = vec![];
+        maze.write_present_wall_list_csv(&mut csv).unwrap();
+        let imported = maze::Maze::read_present_wall_list_csv(csv.as_slice(), 4, 4).unwrap();
+        assert_eq!(imported.to_present_wall_list(), list);
+        assert!(imported.is_consistent_with(&maze).is_ok());
+        // An open wall of the original is unknown after the round trip
+        assert_eq!(maze.get(0, 0, Compass::North), Wall::Absent);
+        assert_eq!(imported.get(0, 0, Compass::North), Wall::Unexplored);
+
+        let text: maze::Maze = maze.to_maze_string().parse().unwrap();
+        let relaxed = text.absent_as_unexplored();
+        assert_eq!(relaxed.get(0, 0, Compass::North), Wall::Unexplored);
+        assert_eq!(relaxed.get(0, 0, Compass::South), Wall::Present);
+        assert_eq!(relaxed.to_present_wall_list(), list);
+    }
+
+    #[test]
+    fn blocked_cells() {
+        use maze::{Compass, Position, Wall};
+        // L-shaped arena: the north-east 2x2 block does not exist
+        let mut maze = maze::Maze::new(4, 4);
+        for (x, y) in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+            maze.set_cell_exists(y, x, false);
+        }
+        maze.set_goal(Position::new(0, 3));
+        assert!(!maze.cell_exists(3, 3));
+        assert_eq!(maze.get_neighbor_cell(2, 1, Compass::East), None);
+        assert_eq!(maze.get(2, 1, Compass::East), Wall::Present);
+        maze.set(2, 1, Compass::East, Wall::Absent);
+        assert_eq!(maze.get(2, 1, Compass::East), Wall::Present);
+
+        let mut solver = adachi::Adachi::new(maze.clone());
+        solver.set_mode(adachi::StepMapMode::UnexploredAsAbsent);
+        solver.calc_step_map(maze.get_goal());
+        assert_eq!(solver.get_step(3, 3), adachi::Adachi::NONE);
+        assert_eq!(solver.get_step(1, 1), 3);
+
+        let text = maze.to_maze_string();
+        assert!(text.contains('X'));
+        let parsed: maze::Maze = text.parse().unwrap();
+        assert_eq!(parsed.blocked_cells(), maze.blocked_cells());
+        let rendered = render::render_maze(&maze, &render::RenderOptions { color: false });
+        assert_eq!(rendered.matches("###").count(), 4);
+
+        // Every exchange format keeps the blocked cells
+        let mut csv = vec![];
+        maze.write_wall_list_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv.clone())
+            .unwrap()
+            .contains("3,3,Blocked,Present"));
+        let mut imported = maze::Maze::read_wall_list_csv(csv.as_slice(), 4, 4).unwrap();
+        imported.set_goal(maze.get_goal());
+        assert_eq!(imported, maze);
+        let snapshot = protocol::Message::Snapshot(protocol::Snapshot { maze: maze.clone() });
+        assert_eq!(
+            protocol::Message::decode(&snapshot.encode().unwrap()).unwrap(),
+            snapshot
+        );
+        let open = {
+            let mut open = maze.clone();
+            for pos in maze.blocked_cells() {
+                open.set_cell_exists(pos.y, pos.x, true);
+            }
+            open
+        };
+        assert_ne!(symmetry::fingerprint(&open), symmetry::fingerprint(&maze));
+
+        // A blocked list that does not match the size is rejected
+        let mut json = serde_json::to_value(&maze).unwrap();
+        json["blocked"] = serde_json::json!([true, false]);
+        assert!(serde_json::from_value::<maze::Maze>(json).is_err());
+
+        maze.set_cell_exists(3, 3, true);
+        assert_eq!(maze.blocked_cells().len(), 3);
+    }
+
+    #[test]
+    fn weighted_zones() {
+        use adachi::StepMapMode;
+        use maze::{Compass, Location, Position, Zone};
+        let mut maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let rough = Zone {
+            min: Position::new(1, 1),
+            max: Position::new(2, 2),
+            weight: 5,
+        };
+        maze.add_zone(rough).unwrap();
+        assert_eq!(maze.zone_cost(1, 1), 5);
+        assert_eq!(maze.zone_cost(0, 0), 0);
+        assert!(maze
+            .add_zone(Zone {
+                min: Position::new(3, 3),
+                max: Position::new(4, 4),
+                weight: 1
+            })
+            .is_err());
+
+        let goal = maze.get_goal();
+        let start = Position::new(0, 0);
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        solver.calc_step_map(goal);
+        let step = solver.get_step(start.x, start.y);
+        assert!(step > 13);
+        // Step map, graph and heading map agree
+        assert_eq!(
+            maze.distance(start, goal, StepMapMode::UnexploredAsPresent),
+            Some(step)
+        );
+        let costs = heading_map::MoveCosts {
+            straight: 1,
+            turn: 1,
+            u_turn: 1,
+        };
+        let map = heading_map::HeadingCostMap::compute(
+            &maze,
+            goal,
+            StepMapMode::UnexploredAsPresent,
+            costs,
+        );
+        assert_eq!(
+            map.get(Location::new(start, Compass::North)),
+            Some(step as u32)
+        );
+
+        let json = serde_json::to_string(&maze).unwrap();
+        let restored: maze::Maze = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.zones(), &[rough]);
+    }
+
+    #[test]
+    fn pessimistic_reachability() {
+        use maze::{Position, Wall};
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        let goal = solver.get_maze().get_goal();
+        let report = solver.reachability(goal);
+        assert_eq!(report.cells, vec![Position::new(0, 0)]);
+        assert!(!report.goal_reachable);
+        assert_eq!(report.frontier, vec![Position::new(0, 0)]);
+
+        // Open a corridor north from the start
+        solver.observe(Wall::Absent, Wall::Present, Wall::Present);
+        let mut location = solver.get_location();
+        location.forward();
+        solver.set_location(location);
+        solver.observe(Wall::Present, Wall::Present, Wall::Present);
+        let report = solver.reachability(goal);
+        assert_eq!(report.cells.len(), 2);
+        // Both cells are fully known and walled in: nothing left to explore
+        assert!(report.frontier.is_empty());
+        assert!(!report.goal_reachable);
+    }
+
+    #[test]
+    fn frontier_distance() {
+        use maze::{Position, Wall};
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        // Start cell fully known, open to the north only
+        solver.observe(Wall::Absent, Wall::Present, Wall::Present);
+        let map = solver.frontier_distance_map();
+        assert_eq!(map[0][0], 1);
+        assert_eq!(map[1][0], 0);
+        assert_eq!(map[3][3], 0);
+        let plain = render::RenderOptions { color: false };
+        let text = render::render_frontier_distance(&solver, &plain);
+        assert!(text.contains("|  1|"));
+
+        // Exploration still reaches the goal with frontier tie breaking
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(4, 4));
+        solver.set_tie_break(adachi::TieBreak::PreferFrontier);
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let goal = actual.get_goal();
+        sim.run(&mut solver, goal, 100).unwrap();
+        assert_eq!(solver.get_location().pos, Position::new(goal.x, goal.y));
+    }
+
+    #[test]
+    fn mouse_facade() {
+        use maze::Wall;
+        use path_finder::{MoveFailure, NavigationError, Observation};
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut sim = simulator::Simulator::new(actual.clone());
+        let mut mouse = mouse::Mouse::new(maze::Maze::new(4, 4));
+        mouse.set_goal(actual.get_goal());
+        let mut steps = 0;
+        loop {
+            mouse.sense(&sim.sense());
+            let direction = match mouse.decide() {
+                Err(NavigationError::GoalReached) => break,
+                result => result.unwrap(),
+            };
+            assert_eq!(mouse.pending(), Some(direction));
+            sim.step(direction).unwrap();
+            mouse.moved();
+            assert_eq!(mouse.where_am_i(), sim.get_location());
+            steps += 1;
+            assert!(steps < 100);
+        }
+        assert!(mouse.at_goal());
+
+        // A failed move keeps the location
+        mouse.reset(true);
+        let start = mouse.where_am_i();
+        mouse.sense(&Observation::new(
+            Wall::Absent,
+            Wall::Present,
+            Wall::Present,
+        ));
+        mouse.decide().unwrap();
+        mouse.move_failed(MoveFailure::Aborted);
+        assert_eq!(mouse.pending(), None);
+        mouse.moved();
+        assert_eq!(mouse.where_am_i(), start);
+    }
+
+    #[test]
+    fn explain_decisions() {
+        use explain::Verdict;
+        use maze::{Compass, Position, Wall};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::with_known_maze(maze.clone(), maze.get_start());
+        let goal = maze.get_goal();
+        solver
+            .navigate(Wall::Absent, Wall::Present, Wall::Present, goal)
+            .unwrap();
+        assert!(solver.last_decision().is_none());
+
+        solver.set_explain(true);
+        solver.reset(true);
+        let direction = solver
+            .navigate(Wall::Absent, Wall::Present, Wall::Present, goal)
+            .unwrap();
+        let decision = solver.last_decision().unwrap().clone();
+        assert_eq!(decision.direction, direction);
+        assert_eq!(decision.chosen, Compass::North);
+        assert_eq!(decision.location.pos, Position::new(0, 0));
+        assert_eq!(decision.step, Some(13));
+        let verdict = |c| decision.candidate(c).unwrap().verdict;
+        assert_eq!(verdict(Compass::North), Verdict::Chosen);
+        assert_eq!(verdict(Compass::East), Verdict::Wall(Wall::Present));
+        assert_eq!(verdict(Compass::South), Verdict::OuterWall);
+        assert_eq!(verdict(Compass::West), Verdict::OuterWall);
+        let text = decision.to_string();
+        assert!(text.contains("N 12 chosen"), "{}", text);
+        assert!(text.contains("S outer wall"), "{}", text);
+
+        // The search mode floods through the unexplored wall ahead, the
+        // robot does not drive through it and the explanation agrees
+        let mut field = open_field(3, 3);
+        field.set(0, 0, Compass::North, Wall::Unexplored);
+        let mut solver = adachi::Adachi::with_known_maze(field, maze.get_start());
+        solver.set_explain(true);
+        solver
+            .navigate(
+                Wall::Unexplored,
+                Wall::Present,
+                Wall::Absent,
+                Position::new(1, 1),
+            )
+            .unwrap();
+        let decision = solver.last_decision().unwrap();
+        assert_eq!(decision.chosen, Compass::East);
+        let north = decision.candidate(Compass::North).unwrap();
+        assert_eq!(north.step, Some(1));
+        assert_eq!(north.verdict, Verdict::Wall(Wall::Unexplored));
+    }
+
+    #[test]
+    fn fastest_run() {
+        use adachi::StepMapMode;
+        use fast_run::{Motion, TimeModel};
+        use maze::{Compass, Location, Position};
+        // Open 4x4 field: a diagonal beats the L-shaped route
+        let open = open_field(4, 4);
+        let model = TimeModel::default();
+        let start = Location::new(Position::new(0, 0), Compass::North);
+        let goal = Position::new(3, 3);
+        let run = fast_run::fastest_run(&open, start, goal, &model).unwrap();
+        let l_shape = model.straight_time(3.0, false) * 2.0 + model.turn90;
+        assert!(run.time < l_shape, "{:?}", run);
+        assert!(run
+            .motions
+            .iter()
+            .any(|m| matches!(m, Motion::Straight { diagonal: true, .. })));
+        assert_eq!(run.cells.first(), Some(&start.pos));
+        assert_eq!(run.cells.last(), Some(&goal));
+        open.validate_path(&run.cells, StepMapMode::UnexploredAsPresent)
+            .unwrap();
+
+        // Real maze: the run follows known open walls only
+        let maze =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let run = fast_run::fastest_run(&maze, maze.get_start(), maze.get_goal(), &model).unwrap();
+        maze.validate_path(&run.cells, StepMapMode::UnexploredAsPresent)
+            .unwrap();
+        assert_eq!(run.cells.last(), Some(&maze.get_goal()));
+
+        // Unreachable goal
+        let closed = maze::Maze::new(4, 4);
+        assert!(fast_run::fastest_run(&closed, start, goal, &model).is_none());
+    }
+
+    #[test]
+    fn run_profiles() {
+        use profiles::{Profile, Profiles};
+        let mut set = Profiles::builtin();
+        assert_eq!(set.names(), vec!["search", "fast_v1", "fast_v2"]);
+        assert_eq!(
+            set.get("fast_v1").unwrap().time_model(),
+            fast_run::TimeModel::default()
+        );
+
+        let mut tuned = Profile::fast_v2();
+        tuned.max_speed = 45.0;
+        set.insert(tuned.clone());
+        assert_eq!(set.names().len(), 3);
+        let json = set.to_json().unwrap();
+        let restored = Profiles::from_json(&json).unwrap();
+        assert_eq!(restored, set);
+        assert_eq!(restored.get("fast_v2"), Some(&tuned));
+
+        // Offsets are driven at turn speed instead of accelerating on the
+        // straights: 0.1 cell takes 0.1 / 8 s instead of the time to
+        // accelerate over it from 8 cells/s at 70 cells/s^2
+        let model = tuned.time_model();
+        let accelerating = ((8.0f32 * 8.0 + 2.0 * 70.0 * 0.1).sqrt() - 8.0) / 70.0;
+        let turn90 = tuned.turn90.time + 2.0 * (0.1 / 8.0 - accelerating);
+        assert!((model.turn90 - turn90).abs() < 1e-6);
+        assert!(model.turn90 > tuned.turn90.time);
+        assert!(model.turn90 < tuned.turn90.total_time(tuned.turn_speed));
+
+        let duplicated = format!("[{0},{0}]", serde_json::to_string(&tuned).unwrap());
+        assert!(Profiles::from_json(&duplicated).is_err());
+        assert!(set.remove("search").is_some());
+        assert!(set.get("search").is_none());
+    }
+
+    #[test]
+    fn compare_candidate_paths() {
+        use maze::Position;
+        let p = |x, y| Position::new(x, y);
+        // Same number of cells: a staircase and an L
+        let stairs = [
+            p(0, 0),
+            p(0, 1),
+            p(1, 1),
+            p(1, 2),
+            p(2, 2),
+            p(2, 3),
+            p(3, 3),
+        ];
+        let l_shape = [
+            p(0, 0),
+            p(0, 1),
+            p(0, 2),
+            p(0, 3),
+            p(1, 3),
+            p(2, 3),
+            p(3, 3),
+        ];
+        let profile = profiles::Profile::fast_v1();
+        let result = fast_run::compare_paths(&stairs, &l_shape, &profile).unwrap();
+        assert_eq!(result.a.length, 6);
+        assert_eq!(result.b.length, 6);
+        assert_eq!(result.b.turns, 1);
+        assert_eq!(result.b.diagonal_segments, 0);
+        assert_eq!(result.a.diagonal_segments, 1);
+        assert_eq!(result.a.run.cells, stairs.to_vec());
+        assert!(result.a.time > 0.0 && result.b.time > 0.0);
+        assert_eq!(result.a_is_faster(), result.time_saved_by_a() > 0.0);
+
+        let broken = [p(0, 0), p(2, 0)];
+        assert!(fast_run::compare_paths(&broken, &l_shape, &profile).is_err());
+        let single = fast_run::estimate_path(&[p(1, 1)], &profile.time_model()).unwrap();
+        assert_eq!(single.time, 0.0);
+        let looped = [p(0, 0), p(1, 0), p(1, 1), p(0, 1), p(0, 0)];
+        assert_eq!(
+            fast_run::estimate_path(&looped, &profile.time_model()).unwrap_err(),
+            path::PathError::Revisited {
+                index: 4,
+                pos: p(0, 0)
+            }
+        );
+    }
+
+    #[test]
+    fn gym_environment() {
+        use maze::{Compass, Direction};
+        let actual: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut env = gym::Env::new(actual.clone());
+        let observation = env.reset();
+        assert_eq!(observation.location, actual.get_start());
+        assert_eq!(observation.features()[..6], [0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+
+        // The start cell is walled on the east
+        let (observation, reward, done) = env.step(Direction::Right);
+        assert!(env.last_info().unwrap().crashed);
+        assert_eq!(reward, -5.0);
+        assert!(!done);
+        assert_eq!(observation.location.pos, actual.get_start().pos);
+        assert_eq!(observation.location.dir, Compass::East);
+
+        // An agent reaching the goal ends the episode
+        let mut observation = env.reset();
+        let mut mouse = mouse::Mouse::new(maze::Maze::new(4, 4));
+        mouse.set_goal(actual.get_goal());
+        let mut total = 0.0;
+        loop {
+            mouse.sense(&observation.walls);
+            let direction = mouse.decide().unwrap();
+            mouse.moved();
+            let (next, reward, done) = env.step(direction);
+            assert_eq!(next.location, mouse.where_am_i());
+            total += reward;
+            observation = next;
+            if done {
+                break;
+            }
+        }
+        assert!(env.last_info().unwrap().reached_goal);
+        assert!(total >= 100.0);
+        assert_eq!(env.step(Direction::Forward).1, 0.0);
+
+        // Custom reward and step limit
+        env.set_reward(Box::new(|_| 1.0));
+        env.set_max_steps(2);
+        env.reset();
+        let backward = gym::action(3).unwrap();
+        assert_eq!(env.step(backward).1, 1.0);
+        let (_, reward, done) = env.step(backward);
+        assert_eq!(reward, 1.0);
+        assert!(done);
+        assert!(env.last_info().unwrap().timed_out);
+    }
+
+    #[test]
+    fn monte_carlo_evaluation() {
+        use evaluate::{EvaluationConfig, FinderFactory};
+        let mazes = evaluate::generated_mazes(6, 8, 8, 42);
+        let factory = |actual: &maze::Maze| -> Box<dyn PathFinder> {
+            let maze = maze::Maze::new(actual.get_width(), actual.get_height());
+            Box::new(adachi::Adachi::new(maze))
+        };
+        let make_finder: &FinderFactory = &factory;
+        let config = EvaluationConfig {
+            threads: 1,
+            ..Default::default()
+        };
+        let serial = evaluate::evaluate(&mazes, make_finder, &config);
+        assert_eq!(serial.episodes.len(), 6);
+        assert_eq!(serial.success_rate.mean, 1.0);
+        assert!(serial.success_rate.ci_low > 0.5 && serial.success_rate.ci_high > 0.999);
+        let steps = serial.search_steps.unwrap();
+        assert!(steps.ci_low <= steps.mean && steps.mean <= steps.ci_high);
+        let quality = serial.fast_run_quality.unwrap();
+        assert!(quality.mean > 0.0 && quality.mean <= 1.0 + 1e-6);
+
+        let parallel = EvaluationConfig {
+            threads: 3,
+            ..config
+        };
+        assert_eq!(evaluate::evaluate(&mazes, make_finder, &parallel), serial);
+        let all_cores = EvaluationConfig::default();
+        assert_eq!(evaluate::evaluate(&mazes, make_finder, &all_cores), serial);
+
+        // A step limit too small to reach any goal
+        let hopeless = EvaluationConfig {
+            max_steps: 1,
+            ..config
+        };
+        let failed = evaluate::evaluate(&mazes, make_finder, &hopeless);
+        assert_eq!(failed.success_rate.mean, 0.0);
+        assert!(failed.search_steps.is_none());
+    }
+
+    #[test]
+    fn batch_simulation() {
+        use batch::{BatchConfig, Strategy};
+        use evaluate::FinderFactory;
+        let mazes = evaluate::generated_mazes(3, 8, 8, 7);
+        let fixed = |actual: &maze::Maze| -> Box<dyn PathFinder> {
+            let maze = maze::Maze::new(actual.get_width(), actual.get_height());
+            Box::new(adachi::Adachi::new(maze))
+        };
+        let straight = |actual: &maze::Maze| -> Box<dyn PathFinder> {
+            let maze = maze::Maze::new(actual.get_width(), actual.get_height());
+            let mut solver = adachi::Adachi::new(maze);
+            solver.set_tie_break(adachi::TieBreak::PreferStraight);
+            Box::new(solver)
+        };
+        let (fixed, straight): (&FinderFactory, &FinderFactory) = (&fixed, &straight);
+        let strategies = [
+            Strategy {
+                name: "fixed",
+                make_finder: fixed,
+            },
+            Strategy {
+                name: "straight",
+                make_finder: straight,
+            },
+        ];
+
+        let table = batch::simulate_all(&mazes, &strategies, &BatchConfig::default());
+        assert_eq!(table.rows.len(), 6);
+        let order: Vec<(usize, usize)> = table
+            .rows
+            .iter()
+            .map(|row| (row.episode.maze, row.strategy))
+            .collect();
+        assert_eq!(order, vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)]);
+        let serial = evaluate::evaluate(&mazes, fixed, &evaluate::EvaluationConfig::default());
+        assert_eq!(table.summary(0), serial);
+
+        // Seeds only matter with faults
+        let noisy = BatchConfig {
+            seeds: 2,
+            faults: Some(simulator::FaultConfig {
+                missed_move: 0.1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let table = batch::simulate_all(&mazes, &strategies, &noisy);
+        assert_eq!(table.rows.len(), 12);
+        assert_eq!(batch::simulate_all(&mazes, &strategies, &noisy), table);
+        let mut csv = vec![];
+        table.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("maze,strategy,seed,success,search_steps,fast_run_quality\n"));
+        assert_eq!(csv.lines().count(), 13);
+        assert!(csv.lines().nth(2).unwrap().starts_with("0,fixed,1,"));
+
+        let all = batch::analyze_all(&mazes);
+        assert_eq!(all[1], stats::stats(&mazes[1]));
+    }
+
+    #[test]
+    fn replay_roundtrip() {
+        use replay::{Replay, ReplayError, ReplayEvent};
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let mut solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        solver.set_tie_break(adachi::TieBreak::PreferStraight);
+        let faults = simulator::FaultConfig {
+            missed_move: 0.05,
+            spurious_turn: 0.02,
+            restart: 0.002,
+            seed: 3,
+            ..Default::default()
+        };
+        let goal = actual.get_goal();
+        let (recorded, after) = Replay::record(&actual, solver, goal, Some(faults), 5000).unwrap();
+        assert!(recorded.result.is_ok());
+        assert!(recorded
+            .events
+            .iter()
+            .any(|e| matches!(e, ReplayEvent::MoveFailed(_))));
+
+        let mut bytes = vec![];
+        recorded.write(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"mm_maze replay 1\n"));
+        let loaded = Replay::read(&bytes[..]).unwrap();
+        assert_eq!(loaded, recorded);
+        let replayed = loaded.play().unwrap();
+        assert_eq!(replayed.get_maze(), after.get_maze());
+        assert_eq!(replayed.get_location(), after.get_location());
+
+        // Writing again gives the same bytes
+        let mut again = vec![];
+        loaded.write(&mut again).unwrap();
+        assert_eq!(again, bytes);
+
+        let mut tampered = loaded.clone();
+        tampered.events[3] = ReplayEvent::Reset;
+        assert!(matches!(
+            tampered.play(),
+            Err(ReplayError::Diverged { index: 3, .. })
+        ));
+
+        let mut future = b"mm_maze replay 2\n".to_vec();
+        future.extend_from_slice(&bytes[bytes.iter().position(|b| *b == b'\n').unwrap() + 1..]);
+        assert_eq!(Replay::read(&future[..]), Err(ReplayError::Version(2)));
+    }
+
+    #[test]
+    fn checkpoint_resume() {
+        use checkpoint::Simulation;
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let start = || {
+            let mut sim = simulator::Simulator::new(actual.clone());
+            sim.set_faults(Some(simulator::FaultConfig {
+                missed_move: 0.05,
+                spurious_turn: 0.02,
+                seed: 11,
+                ..Default::default()
+            }));
+            let solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+            Simulation::new(sim, solver, goal)
+        };
+
+        let mut straight = start();
+        assert!(straight.run_for(5000).unwrap());
+
+        let mut interrupted = start();
+        assert!(!interrupted.run_for(40).unwrap());
+        assert_eq!(interrupted.steps(), 40);
+        let file = std::env::temp_dir().join(format!("mm_maze_{}.ckpt", std::process::id()));
+        let file = file.to_str().unwrap();
+        interrupted.checkpoint(file).unwrap();
+        drop(interrupted);
+        let mut resumed = Simulation::resume(file).unwrap();
+        std::fs::remove_file(file).unwrap();
+        assert_eq!(resumed.steps(), 40);
+        assert!(resumed.run_for(5000).unwrap());
+
+        assert_eq!(resumed.steps(), straight.steps());
+        assert_eq!(resumed.solver().get_maze(), straight.solver().get_maze());
+        assert_eq!(
+            resumed.simulator().fault_log(),
+            straight.simulator().fault_log()
+        );
+
+        let mut bytes = straight.to_bytes().unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        bytes = text
+            .replacen("\"version\":1", "\"version\":9", 1)
+            .into_bytes();
+        assert!(Simulation::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn contest_score() {
+        use scoring::{score, simulate_contest, RunRecord, ScoringRules};
+        let rules = ScoringRules::classic();
+        let runs = [
+            RunRecord {
+                start_time: 0.0,
+                run_time: Some(60.0),
+                touches: 0,
+            },
+            RunRecord {
+                start_time: 120.0,
+                run_time: Some(10.0),
+                touches: 1,
+            },
+            RunRecord {
+                start_time: 150.0,
+                run_time: None,
+                touches: 0,
+            },
+            RunRecord {
+                start_time: 700.0,
+                run_time: Some(1.0),
+                touches: 0,
+            },
+        ];
+        let result = score(&runs, &rules);
+        // 10 + 120 / 30 + 3
+        assert_eq!(result.best_run, Some(1));
+        assert!((result.score.unwrap() - 17.0).abs() < 1e-4);
+        assert_eq!(result.run_scores[2], None);
+        assert_eq!(result.run_scores[3], None);
+        assert_eq!(score(&[], &rules).score, None);
+
+        let actual =
+            maze::Maze::from_file("maze_data/AllJapan_032_2011_classic_exp_fin_16x16.txt").unwrap();
+        let goal = actual.get_goal();
+        let solver = adachi::Adachi::new(maze::Maze::new(16, 16));
+        let contest = simulate_contest(
+            &actual,
+            solver,
+            &rules,
+            &profiles::Profile::search(),
+            &profiles::Profile::fast_v1(),
+            3,
+        )
+        .unwrap();
+        assert_eq!(contest.runs.len(), 4);
+        let search = contest.runs[0].run_time.unwrap();
+        let fast = contest.runs[1].run_time.unwrap();
+        assert!(fast < search);
+        assert!(contest.runs[2].start_time > contest.runs[1].start_time);
+        // The first fast run wins: later ones pay more for the elapsed time
+        assert_eq!(contest.score.best_run, Some(1));
+        assert!(contest.score.score.unwrap() > fast);
+
+        // With the whole maze known the return is the best run from the
+        // goal in any heading
+        let contest = simulate_contest(
+            &actual,
+            adachi::Adachi::new(actual.clone()),
+            &rules,
+            &profiles::Profile::search(),
+            &profiles::Profile::fast_v1(),
+            2,
+        )
+        .unwrap();
+        let model = profiles::Profile::fast_v1().time_model();
+        let start = actual.get_start();
+        let best_return = maze::Compass::iter()
+            .filter_map(|heading| {
+                fast_run::fastest_run(
+                    &actual,
+                    maze::Location::new(goal, heading),
+                    start.pos,
+                    &model,
+                )
+            })
+            .map(|run| run.time)
+            .min_by(f32::total_cmp)
+            .unwrap();
+        let fast = contest.runs[1].run_time.unwrap();
+        let gap = contest.runs[2].start_time - contest.runs[1].start_time;
+        assert!((gap - fast - best_return).abs() < 1e-3);
+    }
+
+    #[test]
+    fn render_colors() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut solver = adachi::Adachi::new(maze);
+        solver.calc_step_map(solver.get_goal());
+
+        let options = render::RenderOptions { color: false };
+        let no_markers = render::Markers {
+            robot: false,
+            goal: false,
+            visited: &[],
+        };
+        let plain = render::render_step_map(&solver, &options, &no_markers);
+        assert_eq!(solver.display_step_map(), plain);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain.lines().count(), 4 * 2 + 2);
+        assert_eq!(plain.lines().nth(5).unwrap(), "| 12|  1   2|  5| 1");
+
+        let colored = render::render_step_map(
+            &solver,
+            &render::RenderOptions::default(),
+            &render::Markers::default(),
+        );
+        assert!(colored.contains("\x1b[44m"));
+        assert!(colored.contains("\x1b[45m"));
+
+        let visited = [maze::Position::new(0, 1)];
+        let marked = solver.display_step_map_with(&visited);
+        assert_eq!(marked.lines().nth(3).unwrap(), "| 11|G 0   1|  6| 2");
+        assert_eq!(marked.lines().nth(7).unwrap(), "|^13|  4   3   4| 0");
+        assert_eq!(marked.lines().nth(5).unwrap(), "|.12|  1   2|  5| 1");
+    }
+
+    #[test]
+    fn render_trace() {
+        use maze::{Compass, Location, Position};
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let trace = [
+            Location::new(Position::new(0, 0), Compass::North),
+            Location::new(Position::new(0, 1), Compass::North),
+            Location::new(Position::new(0, 2), Compass::North),
+            Location::new(Position::new(0, 3), Compass::East),
+            Location::new(Position::new(1, 3), Compass::East),
+        ];
+        let options = render::RenderOptions { color: false };
+        let text = render::render_trace(&maze, &trace, &options);
+        assert_eq!(text.lines().nth(1).unwrap(), "| >   >         | 3");
+        assert_eq!(text.lines().nth(3).unwrap(), "| ^ | G     |   | 2");
+        assert_eq!(text.lines().nth(7).unwrap(), "| ^ |           | 0");
+    }
+
+    #[test]
+    fn read_write_buffer() {
+        let maze: maze::Maze = SMALL_MAZE.parse().unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        maze.write_maze(&mut buffer).unwrap();
+        assert_eq!(maze::Maze::read_maze(buffer.as_slice()).unwrap(), maze);
+        assert_eq!(maze::Maze::from_bytes(&buffer).unwrap(), maze);
+    }
 }
@@ -0,0 +1,82 @@
+use crate::maze::{Compass, Direction, Location, Maze, Position, Wall};
+use crate::path_finder::Observation;
+
+// What the mouse sensed at one cell and how it moved on from there.
+// `moved` is None for the current (last) cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub observation: Observation,
+    pub moved: Option<Direction>,
+}
+
+// Unexplored on either side matches anything
+fn wall_matches(known: Wall, observed: Wall) -> bool {
+    known == Wall::Unexplored || observed == Wall::Unexplored || known == observed
+}
+
+fn observation_matches(maze: &Maze, location: Location, observation: &Observation) -> bool {
+    let (y, x) = (location.pos.y, location.pos.x);
+    let sides = [
+        (Direction::Forward, observation.front),
+        (Direction::Left, observation.left),
+        (Direction::Right, observation.right),
+    ];
+    if !sides
+        .iter()
+        .all(|(d, wall)| wall_matches(maze.get(y, x, location.dir.turn(*d)), *wall))
+    {
+        return false;
+    }
+    let Some(reading) = observation.ahead else {
+        return true;
+    };
+    let mut pos = location.pos;
+    for _ in 0..reading.open_cells {
+        if !wall_matches(maze.get(pos.y, pos.x, location.dir), Wall::Absent) {
+            return false;
+        }
+        match maze.get_neighbor_cell(pos.y, pos.x, location.dir) {
+            Some((y, x)) => pos = Position::new(x, y),
+            None => return false,
+        }
+    }
+    !reading.wall || wall_matches(maze.get(pos.y, pos.x, location.dir), Wall::Present)
+}
+
+// Follow the history from one starting hypothesis; the final location if
+// every observation and move fits the maze
+fn replay(maze: &Maze, start: Location, history: &[Sample]) -> Option<Location> {
+    let mut location = start;
+    for sample in history {
+        if !observation_matches(maze, location, &sample.observation) {
+            return None;
+        }
+        if let Some(direction) = sample.moved {
+            let compass = location.dir.turn(direction);
+            if maze.get(location.pos.y, location.pos.x, compass) == Wall::Present {
+                return None;
+            }
+            let (y, x) = maze.get_neighbor_cell(location.pos.y, location.pos.x, compass)?;
+            location = Location::new(Position::new(x, y), compass);
+        }
+    }
+    Some(location)
+}
+
+// Every current location (position and heading) consistent with the
+// history in the known maze. One candidate means the mouse is localized;
+// keep adding samples while there are several.
+pub fn localize(maze: &Maze, history: &[Sample]) -> Vec<Location> {
+    let mut candidates = vec![];
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            for dir in Compass::iter() {
+                let start = Location::new(Position::new(x, y), dir);
+                if let Some(location) = replay(maze, start, history) {
+                    candidates.push(location);
+                }
+            }
+        }
+    }
+    candidates
+}
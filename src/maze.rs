@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::mpsc;
 
 /*
     Coordinate system:
     (0,0) is the bottom left corner
     x increases to the right (east)
     y increases upwards (north)
-    The robot starts at (0,0) facing north
+    The robot starts at (0,0) facing north by default (see Maze::set_start)
 
     Horizontal walls are blocks between (x,y) and (x,y+1)
     Vertical walls are blocks between (x,y) and (x+1,y)
@@ -25,6 +27,37 @@ West 2 +   +   +   +   + East
              South >X
 */
 
+// Step value of a cell the step map cannot reach (Adachi::NONE)
+pub const NO_STEP: u16 = u16::MAX - 1;
+
+// How the step map and the graph helpers treat walls
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum StepMapMode {
+    UnexploredAsAbsent,  // Search
+    UnexploredAsPresent, // Shortest path
+    // Cautious search: crossing an unexplored wall costs 1 + penalty steps
+    UnexploredWithPenalty(u16),
+    // Fast run on verified corridors: like UnexploredAsPresent, and leaving
+    // a cell within `margin` cells of an unexplored wall costs extra,
+    // penalty * (margin + 1 - distance). Only the Adachi step map applies
+    // the cell costs; graph helpers see it as UnexploredAsPresent.
+    SafetyMargin { margin: u8, penalty: u16 },
+}
+
+impl StepMapMode {
+    // Cost of moving through the wall, or None if the wall blocks the move
+    pub fn cost(&self, wall: Wall) -> Option<u16> {
+        match (self, wall) {
+            (_, Wall::Absent) => Some(1),
+            (_, Wall::Present) => None,
+            (StepMapMode::UnexploredAsAbsent, Wall::Unexplored) => Some(1),
+            (StepMapMode::UnexploredAsPresent, Wall::Unexplored) => None,
+            (StepMapMode::UnexploredWithPenalty(p), Wall::Unexplored) => Some(p.saturating_add(1)),
+            (StepMapMode::SafetyMargin { .. }, Wall::Unexplored) => None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum Wall {
     Absent,
@@ -94,6 +127,52 @@ impl Direction {
         .iter()
         .copied()
     }
+
+    pub fn opposite(&self) -> Direction {
+        self.rotate(2)
+    }
+
+    // Rotate by quarter turns, positive is clockwise (Forward -> Right)
+    pub fn rotate(&self, quarter_turns: i32) -> Direction {
+        const CLOCKWISE: [Direction; 4] = [
+            Direction::Forward,
+            Direction::Right,
+            Direction::Backward,
+            Direction::Left,
+        ];
+        let index = CLOCKWISE.iter().position(|d| d == self).unwrap() as i32;
+        CLOCKWISE[(index + quarter_turns).rem_euclid(4) as usize]
+    }
+}
+
+// 'F', 'L', 'R', 'B' (either case)
+impl TryFrom<char> for Direction {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_uppercase() {
+            'F' => Ok(Direction::Forward),
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            'B' => Ok(Direction::Backward),
+            _ => Err(format!("Invalid direction '{}'", c)),
+        }
+    }
+}
+
+// Single letter or full name: "F", "forward", "Forward", ...
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Direction::try_from(c),
+            _ => Direction::iter()
+                .find(|d| format!("{:?}", d).eq_ignore_ascii_case(s))
+                .ok_or(format!("Invalid direction \"{}\"", s)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
@@ -157,11 +236,83 @@ impl Compass {
         }
     }
 
+    // Heading marker used for the start cell in maze text
+    pub fn to_arrow(&self) -> char {
+        match self {
+            Compass::North => '^',
+            Compass::East => '>',
+            Compass::South => 'v',
+            Compass::West => '<',
+        }
+    }
+
+    pub fn from_arrow(c: char) -> Option<Compass> {
+        match c {
+            '^' => Some(Compass::North),
+            '>' => Some(Compass::East),
+            'v' => Some(Compass::South),
+            '<' => Some(Compass::West),
+            _ => None,
+        }
+    }
+
     pub fn iter() -> impl Iterator<Item = Compass> {
         [Compass::North, Compass::East, Compass::South, Compass::West]
             .iter()
             .copied()
     }
+
+    pub fn opposite(&self) -> Compass {
+        self.rotate(2)
+    }
+
+    // Rotate by quarter turns, positive is clockwise (North -> East)
+    pub fn rotate(&self, quarter_turns: i32) -> Compass {
+        let index = Compass::iter().position(|c| c == *self).unwrap() as i32;
+        Compass::iter()
+            .nth((index + quarter_turns).rem_euclid(4) as usize)
+            .unwrap()
+    }
+
+    // (dx, dy) of one step in this direction
+    pub fn to_delta(&self) -> (i8, i8) {
+        match self {
+            Compass::North => (0, 1),
+            Compass::East => (1, 0),
+            Compass::South => (0, -1),
+            Compass::West => (-1, 0),
+        }
+    }
+}
+
+// 'N', 'E', 'S', 'W' (either case)
+impl TryFrom<char> for Compass {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_uppercase() {
+            'N' => Ok(Compass::North),
+            'E' => Ok(Compass::East),
+            'S' => Ok(Compass::South),
+            'W' => Ok(Compass::West),
+            _ => Err(format!("Invalid compass '{}'", c)),
+        }
+    }
+}
+
+// Single letter or full name: "N", "north", "North", ...
+impl FromStr for Compass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Compass::try_from(c),
+            _ => Compass::iter()
+                .find(|c| format!("{:?}", c).eq_ignore_ascii_case(s))
+                .ok_or(format!("Invalid compass \"{}\"", s)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
@@ -184,10 +335,7 @@ pub struct Location {
 
 impl Location {
     pub fn new(pos: Position, dir: Compass) -> Self {
-        Location {
-            pos: pos,
-            dir: dir,
-        }
+        Location { pos, dir }
     }
 
     pub fn turn(&mut self, dir: Direction) {
@@ -225,13 +373,278 @@ impl std::fmt::Display for Location {
     }
 }
 
+// The four walls around one cell
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CellWalls {
+    pub north: Wall,
+    pub east: Wall,
+    pub south: Wall,
+    pub west: Wall,
+}
+
+impl CellWalls {
+    pub fn get(&self, compass: Compass) -> Wall {
+        match compass {
+            Compass::North => self.north,
+            Compass::East => self.east,
+            Compass::South => self.south,
+            Compass::West => self.west,
+        }
+    }
+
+    pub fn set(&mut self, compass: Compass, wall: Wall) {
+        match compass {
+            Compass::North => self.north = wall,
+            Compass::East => self.east = wall,
+            Compass::South => self.south = wall,
+            Compass::West => self.west = wall,
+        }
+    }
+}
+
+// Which side of the start cell gets the mandatory wall, seen from the start heading
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum StartWall {
+    #[default]
+    Right,
+    Left,
+    // No mandatory wall (practice mazes)
+    None,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct InitOptions {
+    pub start_wall: StartWall,
+}
+
+/*
+    Walls of one orientation in a single allocation, row-major. Indexing
+    with a row gives a slice, so grid[y][x] reads like the nested Vec it
+    replaces. Serialized as nested arrays, same as before.
+*/
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "Vec<Vec<Wall>>", into = "Vec<Vec<Wall>>")]
+struct WallGrid {
+    columns: usize,
+    walls: Vec<Wall>,
+}
+
+impl WallGrid {
+    fn new(rows: usize, columns: usize) -> Self {
+        WallGrid {
+            columns,
+            walls: vec![Wall::Unexplored; rows * columns],
+        }
+    }
+
+    // Caller guarantees row < rows and column < columns
+    #[inline]
+    unsafe fn get_unchecked(&self, row: usize, column: usize) -> Wall {
+        debug_assert!(column < self.columns && row * self.columns + column < self.walls.len());
+        *self.walls.get_unchecked(row * self.columns + column)
+    }
+}
+
+impl std::ops::Index<usize> for WallGrid {
+    type Output = [Wall];
+
+    #[inline]
+    fn index(&self, row: usize) -> &[Wall] {
+        &self.walls[row * self.columns..(row + 1) * self.columns]
+    }
+}
+
+impl std::ops::IndexMut<usize> for WallGrid {
+    #[inline]
+    fn index_mut(&mut self, row: usize) -> &mut [Wall] {
+        &mut self.walls[row * self.columns..(row + 1) * self.columns]
+    }
+}
+
+impl TryFrom<Vec<Vec<Wall>>> for WallGrid {
+    type Error = String;
+
+    fn try_from(rows: Vec<Vec<Wall>>) -> Result<Self, String> {
+        let columns = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != columns) {
+            return Err("Wall rows differ in length".to_string());
+        }
+        Ok(WallGrid {
+            columns,
+            walls: rows.into_iter().flatten().collect(),
+        })
+    }
+}
+
+impl From<WallGrid> for Vec<Vec<Wall>> {
+    fn from(grid: WallGrid) -> Self {
+        if grid.columns == 0 {
+            return vec![];
+        }
+        grid.walls
+            .chunks(grid.columns)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+}
+
+// Rectangular area (corners inclusive) that costs `weight` extra per cell
+// moved through, e.g. rough floor the robot has to cross slowly
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Zone {
+    pub min: Position,
+    pub max: Position,
+    pub weight: u16,
+}
+
+impl Zone {
+    pub fn contains(&self, pos: Position) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+}
+
+// One Maze::set call, sent to every subscriber (see Maze::subscribe)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallChange {
+    pub y: usize,
+    pub x: usize,
+    pub compass: Compass,
+    pub old: Wall,
+    pub new: Wall,
+}
+
+// Changes buffered per subscriber; later ones are dropped until it reads
+pub const SUBSCRIBER_CAPACITY: usize = 4096;
+
+/*
+    Senders of Maze::subscribe. Not part of the maze itself: a clone starts
+    without subscribers, they are never serialized and never compared.
+*/
+#[derive(Default)]
+struct Subscribers(Vec<mpsc::SyncSender<WallChange>>);
+
+impl Subscribers {
+    fn notify(&mut self, change: WallChange) {
+        // Receivers that were dropped are forgotten, full ones miss the change
+        self.0.retain(|sender| match sender.try_send(change) {
+            Ok(_) => true,
+            Err(mpsc::TrySendError::Full(_)) => {
+                log::warn!("Wall change subscriber is full, change dropped");
+                true
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+impl Clone for Subscribers {
+    fn clone(&self) -> Self {
+        Subscribers::default()
+    }
+}
+
+impl PartialEq for Subscribers {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl std::fmt::Debug for Subscribers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} subscribers", self.0.len())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "MazeData")]
 pub struct Maze {
     width: usize,
     height: usize,
-    horizontal_walls: Vec<Vec<Wall>>,
-    vertical_walls: Vec<Vec<Wall>>,
+    horizontal_walls: WallGrid,
+    vertical_walls: WallGrid,
     goal: Position,
+    #[serde(default)]
+    start: Location,
+    #[serde(default)]
+    init_options: InitOptions,
+    // Row-major, true for void cells (non-rectangular arenas). Empty when
+    // every cell exists.
+    #[serde(default)]
+    blocked: Vec<bool>,
+    #[serde(default)]
+    zones: Vec<Zone>,
+    #[serde(skip)]
+    subscribers: Subscribers,
+}
+
+// Maze as read by serde, checked before it becomes a Maze
+#[derive(Deserialize)]
+struct MazeData {
+    width: usize,
+    height: usize,
+    horizontal_walls: WallGrid,
+    vertical_walls: WallGrid,
+    goal: Position,
+    #[serde(default)]
+    start: Location,
+    #[serde(default)]
+    init_options: InitOptions,
+    #[serde(default)]
+    blocked: Vec<bool>,
+    #[serde(default)]
+    zones: Vec<Zone>,
+}
+
+impl TryFrom<MazeData> for Maze {
+    type Error = String;
+
+    // The wall grids must match the size: get_unchecked relies on it
+    fn try_from(data: MazeData) -> Result<Self, String> {
+        let check = |name: &str, grid: &WallGrid, rows: usize, columns: usize| {
+            if grid.columns != columns || grid.walls.len() != rows * columns {
+                Err(format!(
+                    "{} of a {}x{} maze must be {} rows of {} walls",
+                    name, data.width, data.height, rows, columns
+                ))
+            } else {
+                Ok(())
+            }
+        };
+        check(
+            "horizontal_walls",
+            &data.horizontal_walls,
+            data.height + 1,
+            data.width,
+        )?;
+        check(
+            "vertical_walls",
+            &data.vertical_walls,
+            data.height,
+            data.width + 1,
+        )?;
+        // is_blocked indexes it without checks
+        if !data.blocked.is_empty() && data.blocked.len() != data.width * data.height {
+            return Err(format!(
+                "blocked of a {}x{} maze must have {} cells, got {}",
+                data.width,
+                data.height,
+                data.width * data.height,
+                data.blocked.len()
+            ));
+        }
+        Ok(Maze {
+            width: data.width,
+            height: data.height,
+            horizontal_walls: data.horizontal_walls,
+            vertical_walls: data.vertical_walls,
+            goal: data.goal,
+            start: data.start,
+            init_options: data.init_options,
+            blocked: data.blocked,
+            zones: data.zones,
+            subscribers: Subscribers::default(),
+        })
+    }
 }
 
 impl Maze {
@@ -239,15 +652,80 @@ impl Maze {
         let mut maze = Maze {
             width,
             height,
-            horizontal_walls: vec![vec![Wall::Unexplored; width]; height + 1],
-            vertical_walls: vec![vec![Wall::Unexplored; width + 1]; height],
+            horizontal_walls: WallGrid::new(height + 1, width),
+            vertical_walls: WallGrid::new(height, width + 1),
             goal: Position { x: 0, y: 0 },
+            start: Location::default(),
+            init_options: InitOptions::default(),
+            blocked: Vec::new(),
+            zones: Vec::new(),
+            subscribers: Subscribers::default(),
         };
         maze.init();
         maze
     }
 
+    pub fn with_options(width: usize, height: usize, options: InitOptions) -> Self {
+        let mut maze = Maze::new(width, height);
+        maze.init_with(options);
+        maze
+    }
+
+    // Same as init, but the options are kept for later calls to init
+    pub fn init_with(&mut self, options: InitOptions) {
+        self.init_options = options;
+        self.init();
+    }
+
+    pub fn get_init_options(&self) -> InitOptions {
+        self.init_options
+    }
+
+    // Options for later calls to init; the walls stay as they are
+    pub fn set_init_options(&mut self, options: InitOptions) {
+        self.init_options = options;
+    }
+
     pub fn init(&mut self) {
+        if self.subscribers.0.is_empty() {
+            self.init_walls();
+        } else {
+            // One change per wall that differs afterwards, none from set
+            let mut subscribers = std::mem::take(&mut self.subscribers);
+            let before = self.clone();
+            self.init_walls();
+            self.notify_changes(&before, &mut subscribers);
+            self.subscribers = subscribers;
+        }
+    }
+
+    // Send a WallChange for every wall that differs from `before`
+    fn notify_changes(&self, before: &Maze, subscribers: &mut Subscribers) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for compass in Compass::iter() {
+                    // Shared walls once, from the cell south or west of them
+                    let shared = match compass {
+                        Compass::South => y > 0,
+                        Compass::West => x > 0,
+                        Compass::North | Compass::East => false,
+                    };
+                    let (old, new) = (before.get(y, x, compass), self.get(y, x, compass));
+                    if !shared && old != new {
+                        subscribers.notify(WallChange {
+                            y,
+                            x,
+                            compass,
+                            old,
+                            new,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn init_walls(&mut self) {
         // Set all walls to unexplored
         for y in 0..self.height + 1 {
             for x in 0..self.width {
@@ -270,8 +748,15 @@ impl Maze {
             self.vertical_walls[y][self.width] = Wall::Present;
         }
 
-        // Set the right wall of the start cell to present
-        self.set(0, 0, Compass::North.turn(Direction::Right), Wall::Present);
+        // Blocked cells are walled in
+        for pos in self.blocked_cells() {
+            self.wall_in(pos);
+        }
+
+        // Set the side wall of the start cell to present
+        if let Some(side) = self.start_side_wall() {
+            self.set(self.start.pos.y, self.start.pos.x, side, Wall::Present);
+        }
 
         // Set the goal
         self.goal = Position {
@@ -289,12 +774,36 @@ impl Maze {
         }
     }
 
+    // Same as get without bounds checks, for hot loops
+    //
+    // # Safety
+    // y < height and x < width
+    #[inline]
+    pub(crate) unsafe fn get_unchecked(&self, y: usize, x: usize, compass: Compass) -> Wall {
+        match compass {
+            Compass::North => self.horizontal_walls.get_unchecked(y + 1, x),
+            Compass::East => self.vertical_walls.get_unchecked(y, x + 1),
+            Compass::South => self.horizontal_walls.get_unchecked(y, x),
+            Compass::West => self.vertical_walls.get_unchecked(y, x),
+        }
+    }
+
     pub fn set(&mut self, y: usize, x: usize, compass: Compass, wall: Wall) {
+        if wall != Wall::Present && self.borders_blocked_cell(y, x, compass) {
+            log::warn!(
+                "Cannot open a wall of a blocked cell. Operation is ignored. Y: {}, X: {}, compass: {:?}",
+                y,
+                x,
+                compass
+            );
+            return;
+        }
+
         // Check outer walls
         if (y == 0 && compass == Compass::South && wall != Wall::Present)
-            || (y == self.height && compass == Compass::North && wall != Wall::Present)
+            || (y + 1 == self.height && compass == Compass::North && wall != Wall::Present)
             || (x == 0 && compass == Compass::West && wall != Wall::Present)
-            || (x == self.width && compass == Compass::East && wall != Wall::Present)
+            || (x + 1 == self.width && compass == Compass::East && wall != Wall::Present)
         {
             // Cannot remove the outer wall
             log::warn!(
@@ -306,14 +815,369 @@ impl Maze {
             return;
         }
 
-        match compass {
-            Compass::North => self.horizontal_walls[y + 1][x] = wall,
-            Compass::East => self.vertical_walls[y][x + 1] = wall,
-            Compass::South => self.horizontal_walls[y][x] = wall,
-            Compass::West => self.vertical_walls[y][x] = wall,
+        let slot = match compass {
+            Compass::North => &mut self.horizontal_walls[y + 1][x],
+            Compass::East => &mut self.vertical_walls[y][x + 1],
+            Compass::South => &mut self.horizontal_walls[y][x],
+            Compass::West => &mut self.vertical_walls[y][x],
+        };
+        let old = *slot;
+        *slot = wall;
+        if !self.subscribers.0.is_empty() {
+            self.subscribers.notify(WallChange {
+                y,
+                x,
+                compass,
+                old,
+                new: wall,
+            });
         }
     }
 
+    /*
+        Event stream of wall updates: every later call to set (and so
+        set_cell_walls, embed, ...) sends a WallChange, also when the value
+        does not change. init and merge send one per wall they change;
+        rejected outer wall removals send none. At most SUBSCRIBER_CAPACITY
+        unread changes are kept, a receiver that falls further behind misses
+        the rest. Dropping the receiver unsubscribes.
+    */
+    pub fn subscribe(&mut self) -> mpsc::Receiver<WallChange> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+        self.subscribers.0.push(sender);
+        receiver
+    }
+
+    // Receivers still attached (dropped ones go on the next change)
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.0.len()
+    }
+
+    // True if the cell is inside the maze, the neighbor towards compass exists
+    // and the wall between them is passable under the given unexplored policy
+    pub fn can_move(&self, y: usize, x: usize, compass: Compass, mode: StepMapMode) -> bool {
+        y < self.height
+            && x < self.width
+            && self.get_neighbor_cell(y, x, compass).is_some()
+            && mode.cost(self.get(y, x, compass)).is_some()
+    }
+
+    pub fn cell_walls(&self, y: usize, x: usize) -> CellWalls {
+        CellWalls {
+            north: self.get(y, x, Compass::North),
+            east: self.get(y, x, Compass::East),
+            south: self.get(y, x, Compass::South),
+            west: self.get(y, x, Compass::West),
+        }
+    }
+
+    // Same rules as set: outer walls cannot be removed, the shared walls of
+    // the neighbor cells change as well
+    pub fn set_cell_walls(&mut self, y: usize, x: usize, walls: CellWalls) {
+        for compass in Compass::iter() {
+            self.set(y, x, compass, walls.get(compass));
+        }
+    }
+
+    // Copy the walls known in `other` into walls still unexplored here.
+    // Walls already known here are kept. Returns the number of walls learned.
+    pub fn merge(&mut self, other: &Maze) -> Result<usize, String> {
+        if self.width != other.width || self.height != other.height {
+            return Err(format!(
+                "Cannot merge a {}x{} maze into a {}x{} maze",
+                other.width, other.height, self.width, self.height
+            ));
+        }
+        let before = (!self.subscribers.0.is_empty()).then(|| self.clone());
+        let mut learned = 0;
+        let pairs = self
+            .horizontal_walls
+            .walls
+            .iter_mut()
+            .zip(other.horizontal_walls.walls.iter())
+            .chain(
+                self.vertical_walls
+                    .walls
+                    .iter_mut()
+                    .zip(other.vertical_walls.walls.iter()),
+            );
+        for (m, t) in pairs {
+            if *m == Wall::Unexplored && *t != Wall::Unexplored {
+                *m = *t;
+                learned += 1;
+            }
+        }
+        if let Some(before) = before {
+            let mut subscribers = std::mem::take(&mut self.subscribers);
+            self.notify_changes(&before, &mut subscribers);
+            self.subscribers = subscribers;
+        }
+        Ok(learned)
+    }
+
+    /*
+        The w x h block whose south-west cell is (x0, y0), as a maze of its
+        own. Walls on the edge of the block become outer walls (present).
+        Start and goal are kept if they lie inside the block, otherwise
+        they get the defaults of a new maze.
+    */
+    pub fn crop(&self, x0: usize, y0: usize, w: usize, h: usize) -> Result<Maze, String> {
+        if w == 0 || h == 0 || x0 + w > self.width || y0 + h > self.height {
+            return Err(format!(
+                "Cannot crop {}x{} at ({}, {}) from a {}x{} maze",
+                w, h, x0, y0, self.width, self.height
+            ));
+        }
+        let mut cropped = Maze::with_options(w, h, self.init_options);
+        for y in 0..h {
+            for x in 0..w {
+                for compass in Compass::iter() {
+                    if cropped.get_neighbor_cell(y, x, compass).is_some() {
+                        cropped.set(y, x, compass, self.get(y0 + y, x0 + x, compass));
+                    }
+                }
+            }
+        }
+        let inside = |pos: Position| (x0..x0 + w).contains(&pos.x) && (y0..y0 + h).contains(&pos.y);
+        for pos in self.blocked_cells().into_iter().filter(|p| inside(*p)) {
+            cropped.set_cell_exists(pos.y - y0, pos.x - x0, false);
+        }
+        if inside(self.start.pos) {
+            let pos = Position::new(self.start.pos.x - x0, self.start.pos.y - y0);
+            cropped.set_start_location(Location::new(pos, self.start.dir));
+        }
+        if inside(self.goal) {
+            cropped.set_goal(Position::new(self.goal.x - x0, self.goal.y - y0));
+        }
+        Ok(cropped)
+    }
+
+    /*
+        Copy the inner walls of `small` into this maze with its south-west
+        cell at `at`. The walls on the edge of the block (the outer walls
+        of `small`) are left as they are here, so set the seams explicitly
+        where the fragment should connect. Start and goal are not changed.
+    */
+    pub fn embed(&mut self, small: &Maze, at: Position) -> Result<(), String> {
+        if at.x + small.width > self.width || at.y + small.height > self.height {
+            return Err(format!(
+                "A {}x{} maze does not fit at ({}, {}) in a {}x{} maze",
+                small.width, small.height, at.x, at.y, self.width, self.height
+            ));
+        }
+        for y in 0..small.height {
+            for x in 0..small.width {
+                for compass in Compass::iter() {
+                    if small.get_neighbor_cell(y, x, compass).is_some() {
+                        self.set(at.y + y, at.x + x, compass, small.get(y, x, compass));
+                    }
+                }
+            }
+        }
+        for pos in small.blocked_cells() {
+            self.set_cell_exists(at.y + pos.y, at.x + pos.x, false);
+        }
+        Ok(())
+    }
+
+    /*
+        North and east wall of every cell, row by row from the south-west
+        corner, two bits per wall (0 absent, 1 present, 2 unexplored),
+        first wall in the high bits. Used by fingerprints and snapshots.
+    */
+    pub(crate) fn packed_walls(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity((self.width * self.height).div_ceil(2));
+        let mut byte = 0u8;
+        let mut bits = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for compass in [Compass::North, Compass::East] {
+                    let code = match self.get(y, x, compass) {
+                        Wall::Absent => 0,
+                        Wall::Present => 1,
+                        Wall::Unexplored => 2,
+                    };
+                    byte = (byte << 2) | code;
+                    bits += 2;
+                    if bits == 8 {
+                        bytes.push(byte);
+                        byte = 0;
+                        bits = 0;
+                    }
+                }
+            }
+        }
+        if bits > 0 {
+            bytes.push(byte << (8 - bits));
+        }
+        bytes
+    }
+
+    // Inverse of packed_walls
+    pub(crate) fn set_packed_walls(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let needed = (self.width * self.height).div_ceil(2);
+        if bytes.len() != needed {
+            return Err(format!(
+                "Expected {} bytes of walls, got {}",
+                needed,
+                bytes.len()
+            ));
+        }
+        let mut index = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for compass in [Compass::North, Compass::East] {
+                    let wall = match (bytes[index / 4] >> (6 - 2 * (index % 4))) & 3 {
+                        0 => Wall::Absent,
+                        1 => Wall::Present,
+                        2 => Wall::Unexplored,
+                        code => return Err(format!("Invalid wall code {}", code)),
+                    };
+                    if self.get_neighbor_cell(y, x, compass).is_some() {
+                        self.set(y, x, compass, wall);
+                    }
+                    index += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /*
+        Blocked (void) cells for non-rectangular arenas, e.g. an L-shaped
+        practice field. A blocked cell is walled in and its walls cannot be
+        opened, so flood fills never enter it; get_neighbor_cell treats it
+        as outside the maze. Unblocking leaves the walls present.
+    */
+    pub fn cell_exists(&self, y: usize, x: usize) -> bool {
+        y < self.height && x < self.width && !self.is_blocked(y, x)
+    }
+
+    pub fn set_cell_exists(&mut self, y: usize, x: usize, exists: bool) {
+        if y >= self.height || x >= self.width {
+            log::warn!("Cell out of the maze. Y: {}, X: {}", y, x);
+            return;
+        }
+        if !exists {
+            self.wall_in(Position::new(x, y));
+            if self.blocked.is_empty() {
+                self.blocked = vec![false; self.width * self.height];
+            }
+        }
+        if !self.blocked.is_empty() {
+            self.blocked[y * self.width + x] = !exists;
+            if self.blocked.iter().all(|b| !b) {
+                self.blocked.clear();
+            }
+        }
+    }
+
+    pub fn blocked_cells(&self) -> Vec<Position> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Position::new(x, y)))
+            .filter(|p| self.is_blocked(p.y, p.x))
+            .collect()
+    }
+
+    // Blocked cells as a row-major bitmap, first cell in the highest bit.
+    // Empty when every cell exists.
+    pub(crate) fn packed_blocked(&self) -> Vec<u8> {
+        if self.blocked.is_empty() {
+            return vec![];
+        }
+        self.blocked
+            .chunks(8)
+            .map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, b)| byte | (u8::from(*b) << (7 - i)))
+            })
+            .collect()
+    }
+
+    // Inverse of packed_blocked
+    pub(crate) fn set_packed_blocked(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let needed = (self.width * self.height).div_ceil(8);
+        if !bytes.is_empty() && bytes.len() != needed {
+            return Err(format!(
+                "Expected {} bytes of blocked cells, got {}",
+                needed,
+                bytes.len()
+            ));
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = y * self.width + x;
+                let blocked = bytes.get(i / 8).is_some_and(|b| b >> (7 - i % 8) & 1 == 1);
+                self.set_cell_exists(y, x, !blocked);
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn is_blocked(&self, y: usize, x: usize) -> bool {
+        !self.blocked.is_empty() && self.blocked[y * self.width + x]
+    }
+
+    // True if the wall separates a blocked cell from anything
+    fn borders_blocked_cell(&self, y: usize, x: usize, compass: Compass) -> bool {
+        if self.blocked.is_empty() {
+            return false;
+        }
+        let (dx, dy) = compass.to_delta();
+        let (nx, ny) = (x as isize + dx as isize, y as isize + dy as isize);
+        let neighbor_blocked = nx >= 0
+            && ny >= 0
+            && (nx as usize) < self.width
+            && (ny as usize) < self.height
+            && self.is_blocked(ny as usize, nx as usize);
+        self.is_blocked(y, x) || neighbor_blocked
+    }
+
+    fn wall_in(&mut self, pos: Position) {
+        for compass in Compass::iter() {
+            self.set(pos.y, pos.x, compass, Wall::Present);
+        }
+    }
+
+    /*
+        Weighted zones are part of the maze (and its serialized form), so
+        every planner sees the same costs: the Adachi step map, the heading
+        cost map and to_graph add zone_cost for each cell moved out of.
+    */
+    pub fn add_zone(&mut self, zone: Zone) -> Result<(), String> {
+        if zone.min.x > zone.max.x
+            || zone.min.y > zone.max.y
+            || zone.max.x >= self.width
+            || zone.max.y >= self.height
+        {
+            return Err(format!(
+                "Invalid zone {:?} in a {}x{} maze",
+                zone, self.width, self.height
+            ));
+        }
+        self.zones.push(zone);
+        Ok(())
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    pub fn clear_zones(&mut self) {
+        self.zones.clear();
+    }
+
+    // Extra cost of moving through the cell, overlapping zones add up
+    pub fn zone_cost(&self, y: usize, x: usize) -> u16 {
+        let pos = Position::new(x, y);
+        self.zones
+            .iter()
+            .filter(|zone| zone.contains(pos))
+            .fold(0u16, |cost, zone| cost.saturating_add(zone.weight))
+    }
+
     pub fn get_goal(&self) -> Position {
         self.goal
     }
@@ -322,6 +1186,63 @@ impl Maze {
         self.goal = pos;
     }
 
+    // The 2x2 goal area of a classic maze: the default goal (see init) and
+    // the three cells south and west of it. Just the center cell on odd sizes.
+    pub fn center_goal_area(&self) -> Vec<Position> {
+        let (cx, cy) = (self.width / 2, self.height / 2);
+        if self.width % 2 == 1 || self.height % 2 == 1 || cx == 0 || cy == 0 {
+            return vec![Position::new(cx, cy)];
+        }
+        vec![
+            Position::new(cx - 1, cy - 1),
+            Position::new(cx, cy - 1),
+            Position::new(cx - 1, cy),
+            Position::new(cx, cy),
+        ]
+    }
+
+    pub fn get_start(&self) -> Location {
+        self.start
+    }
+
+    // Compass of the mandatory wall of the start cell, if the rule has one
+    fn start_side_wall(&self) -> Option<Compass> {
+        match self.init_options.start_wall {
+            StartWall::Right => Some(self.start.dir.turn(Direction::Right)),
+            StartWall::Left => Some(self.start.dir.turn(Direction::Left)),
+            StartWall::None => None,
+        }
+    }
+
+    // Change the start cell and heading, before the search. The mandatory
+    // side wall moves along: the old one becomes unexplored again (unless
+    // it is an outer wall), the new one is set to present.
+    pub fn set_start(&mut self, start: Location) -> Result<(), String> {
+        if start.pos.x >= self.width || start.pos.y >= self.height {
+            return Err(format!(
+                "Start {:?} is outside the {}x{} maze",
+                start.pos, self.width, self.height
+            ));
+        }
+        if let Some(side) = self.start_side_wall() {
+            let old = self.start.pos;
+            if self.get_neighbor_cell(old.y, old.x, side).is_some() {
+                self.set(old.y, old.x, side, Wall::Unexplored);
+            }
+        }
+        self.start = start;
+        if let Some(side) = self.start_side_wall() {
+            self.set(start.pos.y, start.pos.x, side, Wall::Present);
+        }
+        Ok(())
+    }
+
+    // Start as read from a file or a snapshot whose walls are already
+    // complete; nothing else changes
+    pub(crate) fn set_start_location(&mut self, start: Location) {
+        self.start = start;
+    }
+
     pub fn get_width(&self) -> usize {
         self.width
     }
@@ -368,6 +1289,9 @@ impl Maze {
     "-" and "|" measn wall is present
     " "  means wall is absent
     "G" means goal
+    "X" marks a blocked (void) cell
+    "^", ">", "v" or "<" marks the start cell and heading
+      (only written when the start is not (0,0) facing north)
       + means pillar
     */
     pub fn read_maze_file(
@@ -380,50 +1304,193 @@ impl Maze {
             Ok(c) => c,
             Err(e) => return Err(e.to_string()),
         };
-        // Split the contents into lines and store them in Vec<String>
-        let lines: Vec<&str> = contents.lines().collect();
-        // Reverse the lines
-        let lines: Vec<&str> = lines.iter().rev().map(|l| *l).collect();
-        // Remove "+"
-        let lines: Vec<String> = lines.iter().map(|l| l.replace("+", "")).collect();
-        // Convert " " to Wall::Absent and "-" to Wall::Present
-        for y in 0..height {
-            // Horizontal walls
+        self.read_maze_text(&contents, width, height)
+    }
+
+    // Parse the maze text (same format as read_maze_file) into this maze.
+    // Blank lines and indentation around each line are ignored.
+    // Errors point at the offending character, e.g.
+    // "line 12, column 7: expected ' ', '-' or '?', found 'x'"
+    pub fn read_maze_text(
+        &mut self,
+        text: &str,
+        width: usize,
+        height: usize,
+    ) -> Result<(), String> {
+        // (line number, indentation, characters) of each non-blank line
+        let lines: Vec<(usize, usize, Vec<char>)> = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                let indent = l.chars().count() - l.trim_start().chars().count();
+                (i + 1, indent, l.trim().chars().collect())
+            })
+            .collect();
+        if lines.len() != height * 2 + 1 {
+            return Err(format!(
+                "Expected {} lines for a {}x{} maze, found {}",
+                height * 2 + 1,
+                width,
+                height,
+                lines.len()
+            ));
+        }
+        if self.width != width || self.height != height {
+            return Err(format!(
+                "Cannot read a {}x{} maze into a {}x{} maze",
+                width, height, self.width, self.height
+            ));
+        }
+
+        // Return the character at index i, or an error describing what was expected
+        let expect = |row: usize, i: usize, allowed: &str, what: &str| -> Result<char, String> {
+            let (number, indent, chars) = &lines[lines.len() - 1 - row];
+            match chars.get(i) {
+                Some(c) if allowed.contains(*c) => Ok(*c),
+                Some(c) => Err(format!(
+                    "line {}, column {}: expected {}, found '{}'",
+                    number,
+                    indent + i + 1,
+                    what,
+                    c
+                )),
+                None => Err(format!(
+                    "line {}, column {}: expected {}, found end of line",
+                    number,
+                    indent + i + 1,
+                    what
+                )),
+            }
+        };
+        let to_wall = |c: char| match c {
+            ' ' => Wall::Absent,
+            '-' | '|' => Wall::Present,
+            _ => Wall::Unexplored,
+        };
+
+        // Rows are numbered from the bottom line so that y matches the maze
+        self.blocked.clear();
+        let mut blocked = vec![];
+        for y in 0..height + 1 {
+            // Pillars and horizontal walls
             for x in 0..width {
-                let c = lines[y * 2].chars().nth(x).unwrap();
-                self.horizontal_walls[y][x] = match c {
-                    ' ' => Wall::Absent,
-                    '-' => Wall::Present,
-                    _ => Wall::Unexplored,
-                };
+                expect(y * 2, x * 2, "+", "'+'")?;
+                let c = expect(y * 2, x * 2 + 1, " -?.", "' ', '-' or '?'")?;
+                self.horizontal_walls[y][x] = to_wall(c);
             }
+            expect(y * 2, width * 2, "+", "'+'")?;
+            if let Some(c) = lines[lines.len() - 1 - y * 2].2.get(width * 2 + 1) {
+                return Err(format!(
+                    "line {}, column {}: unexpected '{}' after the last pillar",
+                    lines[lines.len() - 1 - y * 2].0,
+                    lines[lines.len() - 1 - y * 2].1 + width * 2 + 2,
+                    c
+                ));
+            }
+            if y == height {
+                break;
+            }
+
             // Vertical walls (two characters per wall)
-            for x in 0..width {
-                let c = lines[y * 2 + 1].chars().nth(x * 2).unwrap();
-                self.vertical_walls[y][x] = match c {
-                    ' ' => Wall::Absent,
-                    '|' => Wall::Present,
-                    _ => Wall::Unexplored,
-                };
+            for x in 0..width + 1 {
+                let c = expect(y * 2 + 1, x * 2, " |?.", "' ', '|' or '?'")?;
+                self.vertical_walls[y][x] = to_wall(c);
+                if x == width {
+                    break;
+                }
 
-                // Goal location
-                let c = lines[y * 2 + 1].chars().nth(x * 2 + 1).unwrap();
-                if c == 'G' {
+                // Goal, start location and blocked cells
+                let c = expect(
+                    y * 2 + 1,
+                    x * 2 + 1,
+                    " G^>v<X",
+                    "' ', 'G', 'X' or a start marker",
+                )?;
+                if c == 'X' {
+                    blocked.push(Position { x, y });
+                } else if c == 'G' {
                     self.goal = Position { x, y };
+                } else if let Some(dir) = Compass::from_arrow(c) {
+                    self.start = Location::new(Position { x, y }, dir);
                 }
             }
         }
+        for pos in blocked {
+            self.set_cell_exists(pos.y, pos.x, false);
+        }
         Ok(())
     }
 
-    pub fn write_maze_file(&self, filename: &str) -> Result<(), String> {
-        let contents = self.to_text_data(" ", "-", " ", " ", "|", " ", "+", "G");
-        match std::fs::write(filename, contents) {
+    // Build a new maze of the given size from maze text (see read_maze_file)
+    pub fn from_ascii(text: &str, width: usize, height: usize) -> Result<Maze, String> {
+        let mut maze = Maze::new(width, height);
+        maze.read_maze_text(text, width, height)?;
+        Ok(maze)
+    }
+
+    // Read a maze file, inferring the width and height from its contents
+    pub fn from_file(filename: &str) -> Result<Maze, String> {
+        match std::fs::File::open(filename) {
+            Ok(f) => Maze::read_maze(f),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    // Read maze text from any reader (socket, flash blob, in-memory buffer...)
+    pub fn read_maze(mut reader: impl std::io::Read) -> Result<Maze, String> {
+        let mut contents = String::new();
+        if let Err(e) = reader.read_to_string(&mut contents) {
+            return Err(e.to_string());
+        }
+        contents.parse()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Maze, String> {
+        Maze::read_maze(bytes)
+    }
+
+    // Write the maze in the same format as write_maze_file
+    pub fn write_maze(&self, mut writer: impl std::io::Write) -> Result<(), String> {
+        match writer.write_all(self.to_maze_string().as_bytes()) {
             Ok(_) => Ok(()),
             Err(e) => Err(e.to_string()),
         }
     }
 
+    pub fn to_maze_string(&self) -> String {
+        self.to_text_data(" ", "-", " ", " ", "|", " ", "+", "G")
+    }
+
+    // Infer (width, height) from maze text: 2 * height + 1 lines,
+    // and 2 * width + 1 characters in the top pillar line.
+    pub fn detect_size(text: &str) -> Result<(usize, usize), String> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect();
+        if lines.len() < 3 || lines.len().is_multiple_of(2) {
+            return Err(format!("Cannot infer maze size from {} lines", lines.len()));
+        }
+        let columns = lines[0].chars().count();
+        if columns < 3 || columns.is_multiple_of(2) {
+            return Err(format!(
+                "Cannot infer maze size from a {} character wide line",
+                columns
+            ));
+        }
+        Ok(((columns - 1) / 2, (lines.len() - 1) / 2))
+    }
+
+    pub fn write_maze_file(&self, filename: &str) -> Result<(), String> {
+        match std::fs::File::create(filename) {
+            Ok(f) => self.write_maze(f),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn to_text_data(
         &self,
         horizontal_wall_absent: &str,
@@ -441,24 +1508,34 @@ impl Maze {
             // y
             for j in 0..self.width {
                 // x
-                line += &pillar;
+                line += pillar;
                 line += match self.horizontal_walls[i][j] {
-                    Wall::Absent => &horizontal_wall_absent,
-                    Wall::Present => &horizontal_wall_present,
-                    Wall::Unexplored => &horizontal_wall_unexplored,
+                    Wall::Absent => horizontal_wall_absent,
+                    Wall::Present => horizontal_wall_present,
+                    Wall::Unexplored => horizontal_wall_unexplored,
                 };
             }
-            line += "+";
+            line += pillar;
             lines.push(line);
             line = "".to_string();
             for j in 0..self.width + 1 {
                 line += match self.vertical_walls[i][j] {
-                    Wall::Absent => &vertical_wall_absent,
-                    Wall::Present => &vertical_wall_present,
-                    Wall::Unexplored => &vertical_wall_unexplored,
+                    Wall::Absent => vertical_wall_absent,
+                    Wall::Present => vertical_wall_present,
+                    Wall::Unexplored => vertical_wall_unexplored,
                 };
                 if j == self.goal.x && i == self.goal.y {
-                    line += &goal;
+                    line += goal;
+                } else if j < self.width && self.is_blocked(i, j) && !goal.is_empty() {
+                    line.push('X');
+                    line += " ".repeat(goal.len() - 1).as_str();
+                } else if self.start != Location::default()
+                    && j == self.start.pos.x
+                    && i == self.start.pos.y
+                    && !goal.is_empty()
+                {
+                    line.push(self.start.dir.to_arrow());
+                    line += " ".repeat(goal.len() - 1).as_str();
                 } else {
                     // goalと同じ長さになるように空白を追加
                     line += " ".repeat(goal.len()).as_str();
@@ -468,14 +1545,14 @@ impl Maze {
             line = "".to_string();
         }
         for j in 0..self.width {
-            line += &pillar;
+            line += pillar;
             line += match self.horizontal_walls[self.height][j] {
-                Wall::Absent => &horizontal_wall_absent,
-                Wall::Present => &horizontal_wall_present,
-                Wall::Unexplored => &horizontal_wall_unexplored,
+                Wall::Absent => horizontal_wall_absent,
+                Wall::Present => horizontal_wall_present,
+                Wall::Unexplored => horizontal_wall_unexplored,
             };
         }
-        line += &pillar;
+        line += pillar;
         lines.push(line);
         // join reversed lines
         lines
@@ -489,6 +1566,7 @@ impl Maze {
     /*
        This function returns the coordinates of the cell that is adjacent to the cell at (x, y)
        When the the cell is at the edge of the maze, None is returned
+       (also when either cell is blocked, see cell_exists)
     */
     pub fn get_neighbor_cell(
         &self,
@@ -496,36 +1574,36 @@ impl Maze {
         x: usize,
         compass: Compass,
     ) -> Option<(usize, usize)> {
-        match compass {
+        let (ny, nx) = match compass {
             Compass::North => {
                 if y == self.height - 1 {
-                    None
-                } else {
-                    Some((y + 1, x))
+                    return None;
                 }
+                (y + 1, x)
             }
             Compass::East => {
                 if x == self.width - 1 {
-                    None
-                } else {
-                    Some((y, x + 1))
+                    return None;
                 }
+                (y, x + 1)
             }
             Compass::South => {
                 if y == 0 {
-                    None
-                } else {
-                    Some((y - 1, x))
+                    return None;
                 }
+                (y - 1, x)
             }
             Compass::West => {
                 if x == 0 {
-                    None
-                } else {
-                    Some((y, x - 1))
+                    return None;
                 }
+                (y, x - 1)
             }
+        };
+        if self.is_blocked(y, x) || self.is_blocked(ny, nx) {
+            return None;
         }
+        Some((ny, nx))
     }
 }
 
@@ -540,18 +1618,17 @@ impl std::fmt::Display for Maze {
     }
 }
 
-impl Default for Maze{
+impl std::str::FromStr for Maze {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = Maze::detect_size(s)?;
+        Maze::from_ascii(s, width, height)
+    }
+}
+
+impl Default for Maze {
     fn default() -> Self {
-        let width = 16;
-        let height = 16;
-        let mut maze = Maze {
-        width,
-        height,
-        horizontal_walls: vec![vec![Wall::Unexplored; width]; height + 1],
-        vertical_walls: vec![vec![Wall::Unexplored; width + 1]; height],
-        goal: Position { x: 0, y: 0 },
-    };
-    maze.init();
-    maze
+        Maze::new(16, 16)
     }
 }
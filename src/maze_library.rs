@@ -0,0 +1,82 @@
+use crate::maze::Maze;
+
+/*
+    Mazes of the maze_data directory, embedded at build time
+    (feature "maze_library").
+
+    Names are the file names without ".txt", e.g.
+    "AllJapan_032_2011_classic_exp_fin_16x16". Most follow
+    Competition_Number_Year_Class_Category_Stage_Size, the metadata is taken
+    from there where possible and the size from the maze itself.
+*/
+include!(concat!(env!("OUT_DIR"), "/maze_library.rs"));
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeInfo {
+    pub name: &'static str,
+    pub competition: String,
+    pub year: Option<u16>,
+    pub width: usize,
+    pub height: usize,
+}
+
+// First run of exactly four digits that looks like a year
+fn year_of(name: &str) -> Option<u16> {
+    name.split(|c: char| !c.is_ascii_digit())
+        .filter(|digits| digits.len() == 4)
+        .filter_map(|digits| digits.parse().ok())
+        .find(|year| (1970..=2100).contains(year))
+}
+
+fn competition_of(name: &str) -> String {
+    match name.split_once('_') {
+        Some((competition, _)) => competition.to_string(),
+        None => name.chars().take_while(|c| !c.is_ascii_digit()).collect(),
+    }
+}
+
+// None if the size cannot be read from the text
+fn info_of(name: &'static str, text: &str) -> Option<MazeInfo> {
+    let (width, height) = Maze::detect_size(text).ok()?;
+    Some(MazeInfo {
+        name,
+        competition: competition_of(name),
+        year: year_of(name),
+        width,
+        height,
+    })
+}
+
+// Mazes whose size cannot be read are left out with a warning
+pub fn list() -> Vec<MazeInfo> {
+    MAZES
+        .iter()
+        .filter_map(|(name, text)| {
+            let info = info_of(name, text);
+            if info.is_none() {
+                log::warn!("Cannot read the size of maze \"{}\"", name);
+            }
+            info
+        })
+        .collect()
+}
+
+pub fn info(name: &str) -> Option<MazeInfo> {
+    MAZES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .and_then(|(name, text)| info_of(name, text))
+}
+
+// Raw maze text
+pub fn text(name: &str) -> Option<&'static str> {
+    MAZES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, text)| *text)
+}
+
+pub fn load(name: &str) -> Result<Maze, String> {
+    let text = text(name).ok_or(format!("No maze named \"{}\" in the library", name))?;
+    text.parse()
+}
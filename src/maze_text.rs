@@ -0,0 +1,245 @@
+use crate::maze::{Compass, Location, Maze, Position, Wall};
+
+/*
+    Text dialects found in contest maze archives.
+
+    Standard (maze_data format):     Wide cells, "o" pillars:
+    +-+-+                            o---o---o
+    |G  |                            | G     |
+    + +-+                            o   o---o
+    |   |                            | 12  3 |
+    +-+-+                            o---o---o
+
+    Auto takes the pillar character and the cell width from the top line;
+    the other dialects expect their pillar there.
+    Partial is the standard layout with "?" for unexplored walls, so that
+    a search snapshot survives a round trip. Custom writes "?" for
+    unexplored walls as well. "?" and "." are read back as unexplored
+    walls in every dialect.
+
+    Any character inside a cell other than "G"/"g", "X" (blocked cell) and
+    the start heading markers "^", ">", "v", "<" (step numbers, "S", ...)
+    is ignored. Without a goal marker the goal stays at the maze center.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    // Detect the pillar character and cell width from the top line
+    Auto,
+    // "+" pillars, one character per horizontal wall
+    Standard,
+    // Standard with "?" for unexplored walls
+    Partial,
+    Custom { pillar: char, cell_width: usize },
+}
+
+impl Dialect {
+    // Resolve Auto into a concrete (pillar, cell_width) pair
+    fn resolve(&self, top_line: &str) -> Result<(char, usize), String> {
+        match self {
+            Dialect::Standard | Dialect::Partial => Ok(('+', 1)),
+            Dialect::Custom { pillar, cell_width } => {
+                if *cell_width == 0 {
+                    return Err("Cell width must be at least 1".to_string());
+                }
+                Ok((*pillar, *cell_width))
+            }
+            Dialect::Auto => {
+                let mut chars = top_line.chars();
+                let pillar = match chars.next() {
+                    Some(c) => c,
+                    None => return Err("Empty maze text".to_string()),
+                };
+                match chars.position(|c| c == pillar) {
+                    Some(0) | None => Err(format!(
+                        "Cannot detect the cell width from the top line \"{}\"",
+                        top_line
+                    )),
+                    Some(n) => Ok((pillar, n)),
+                }
+            }
+        }
+    }
+}
+
+fn is_horizontal_wall(c: char) -> bool {
+    matches!(c, '-' | '_' | '=' | '#')
+}
+
+fn is_vertical_wall(c: char) -> bool {
+    matches!(c, '|' | 'I' | '#')
+}
+
+fn is_unexplored(c: char) -> bool {
+    matches!(c, '?' | '.')
+}
+
+fn horizontal_wall(segment: &[char]) -> Wall {
+    if segment.iter().any(|c| is_horizontal_wall(*c)) {
+        Wall::Present
+    } else if segment.iter().any(|c| is_unexplored(*c)) {
+        Wall::Unexplored
+    } else {
+        Wall::Absent
+    }
+}
+
+fn vertical_wall(c: char) -> Wall {
+    if is_vertical_wall(c) {
+        Wall::Present
+    } else if is_unexplored(c) {
+        Wall::Unexplored
+    } else {
+        Wall::Absent
+    }
+}
+
+pub fn parse(text: &str, dialect: Dialect) -> Result<Maze, String> {
+    let lines: Vec<Vec<char>> = text
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().collect())
+        .collect();
+    if lines.len() < 3 || lines.len().is_multiple_of(2) {
+        return Err(format!("Cannot infer maze size from {} lines", lines.len()));
+    }
+    let top_line: String = lines[0].iter().collect();
+    let (pillar, cell_width) = dialect.resolve(&top_line)?;
+    let pitch = cell_width + 1;
+    if lines[0].len() < pitch + 1 {
+        return Err(format!("Top line \"{}\" is too short", top_line));
+    }
+    let width = (lines[0].len() - 1) / pitch;
+    let height = (lines.len() - 1) / 2;
+    // The top line fixes the grid: a pillar every `pitch` characters
+    if let Some(x) = (0..=width).find(|x| lines[0][x * pitch] != pillar) {
+        return Err(format!(
+            "Top line \"{}\": expected pillar '{}' at column {}",
+            top_line,
+            pillar,
+            x * pitch + 1
+        ));
+    }
+
+    let mut maze = Maze::new(width, height);
+    // Read from the bottom line so that the row index matches y
+    let lines: Vec<&Vec<char>> = lines.iter().rev().collect();
+    let at = |line: &Vec<char>, i: usize| line.get(i).copied().unwrap_or(' ');
+    let mut blocked = vec![];
+
+    for y in 0..height {
+        // Horizontal walls below the row y (and above it for the top row)
+        for (line, compass) in [
+            (lines[y * 2], Compass::South),
+            (lines[y * 2 + 2], Compass::North),
+        ] {
+            if compass == Compass::North && y + 1 != height {
+                continue;
+            }
+            for x in 0..width {
+                let segment: Vec<char> = (x * pitch + 1..x * pitch + pitch)
+                    .map(|i| at(line, i))
+                    .collect();
+                maze.set(y, x, compass, horizontal_wall(&segment));
+            }
+        }
+
+        // Vertical walls and cell contents
+        let line = lines[y * 2 + 1];
+        for x in 0..width {
+            maze.set(y, x, Compass::West, vertical_wall(at(line, x * pitch)));
+            for i in x * pitch + 1..x * pitch + pitch {
+                let c = at(line, i);
+                if matches!(c, 'G' | 'g') {
+                    maze.set_goal(Position { x, y });
+                } else if c == 'X' {
+                    blocked.push(Position { x, y });
+                } else if let Some(dir) = Compass::from_arrow(c) {
+                    maze.set_start_location(Location::new(Position { x, y }, dir));
+                }
+            }
+        }
+        maze.set(
+            y,
+            width - 1,
+            Compass::East,
+            vertical_wall(at(line, width * pitch)),
+        );
+    }
+    for pos in blocked {
+        maze.set_cell_exists(pos.y, pos.x, false);
+    }
+    Ok(maze)
+}
+
+pub fn format(maze: &Maze, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Auto | Dialect::Standard => maze.to_maze_string(),
+        Dialect::Partial => maze.to_text_data(" ", "-", "?", " ", "|", "?", "+", "G"),
+        Dialect::Custom { pillar, cell_width } => {
+            let absent = " ".repeat(cell_width);
+            let present = "-".repeat(cell_width);
+            let unexplored = "?".repeat(cell_width);
+            let goal = format!("{:<1$}", "G", cell_width);
+            maze.to_text_data(
+                &absent,
+                &present,
+                &unexplored,
+                " ",
+                "|",
+                "?",
+                &pillar.to_string(),
+                &goal,
+            )
+        }
+    }
+}
+
+impl Maze {
+    pub fn to_text_with(&self, dialect: Dialect) -> String {
+        format(self, dialect)
+    }
+
+    pub fn write_maze_file_with(&self, filename: &str, dialect: Dialect) -> Result<(), String> {
+        match std::fs::write(filename, self.to_text_with(dialect)) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn from_text_with(text: &str, dialect: Dialect) -> Result<Maze, String> {
+        parse(text, dialect)
+    }
+
+    pub fn from_file_with(filename: &str, dialect: Dialect) -> Result<Maze, String> {
+        match std::fs::read_to_string(filename) {
+            Ok(contents) => parse(&contents, dialect),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/*
+    Inline maze for tests, one string literal per line:
+
+    let maze = maze! {
+        "+-+-+"
+        "|G  |"
+        "+ +-+"
+        "|   |"
+        "+-+-+"
+    };
+
+    The text is joined at compile time and parsed like Maze::from_str when
+    the expression runs; a bad character panics with its line (counted in
+    literals) and column.
+*/
+#[macro_export]
+macro_rules! maze {
+    ($($line:literal)+) => {
+        match <$crate::maze::Maze as ::core::str::FromStr>::from_str(concat!($($line, "\n"),+)) {
+            Ok(maze) => maze,
+            Err(e) => panic!("maze!: {}", e),
+        }
+    };
+}
@@ -0,0 +1,267 @@
+use crate::adachi::{Adachi, StepMapMode};
+use crate::maze::{Direction, Location, Position, Wall};
+use crate::path::compass_between;
+use crate::path_finder::PathFinder;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MissionPhase {
+    // Start -> goal, exploring
+    Search,
+    // Goal -> start, exploring on the way back
+    Return,
+    // Start -> goal on the planned route through known walls
+    FastRun,
+    Finished,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MissionEvent {
+    PhaseChanged {
+        from: MissionPhase,
+        to: MissionPhase,
+    },
+}
+
+// Step map mode and speed (whatever unit the motor control uses) per phase
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MissionConfig {
+    pub search_mode: StepMapMode,
+    pub return_mode: StepMapMode,
+    pub search_speed: f32,
+    pub return_speed: f32,
+    pub fast_speed: f32,
+    pub budget: Option<Budget>,
+}
+
+/*
+    Time limit for the search and return. Once the steps left are no more
+    than the way back to the start through known walls plus `margin`, the
+    mission stops exploring and heads home; the fast run is skipped then.
+    max_seconds is converted with seconds_per_step, the tighter limit wins.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Budget {
+    pub max_steps: Option<usize>,
+    pub max_seconds: Option<f32>,
+    pub seconds_per_step: f32,
+    pub margin: usize,
+}
+
+impl Budget {
+    // Budget in steps, usize::MAX if unlimited
+    pub fn steps(&self) -> usize {
+        let by_time = self
+            .max_seconds
+            .map(|seconds| (seconds / self.seconds_per_step).max(0.0) as usize);
+        match (self.max_steps, by_time) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) | (None, Some(a)) => a,
+            (None, None) => usize::MAX,
+        }
+    }
+}
+
+impl Default for MissionConfig {
+    fn default() -> Self {
+        MissionConfig {
+            search_mode: StepMapMode::UnexploredAsAbsent,
+            return_mode: StepMapMode::UnexploredAsAbsent,
+            search_speed: 0.3,
+            return_speed: 0.5,
+            fast_speed: 1.0,
+            budget: None,
+        }
+    }
+}
+
+/*
+    The standard micromouse run: search to the goal, come back to the start,
+    then fast run. Call navigate after every move (and set_location with the
+    new location) like with a plain PathFinder; phase changes happen when
+    the target of the current phase is reached and are reported as events.
+*/
+pub struct Mission {
+    solver: Adachi,
+    config: MissionConfig,
+    phase: MissionPhase,
+    start: Position,
+    goal: Position,
+    fast_route: Vec<Position>,
+    events: Vec<MissionEvent>,
+    // Moves made in the search and return phases
+    steps: usize,
+    out_of_budget: bool,
+}
+
+impl Mission {
+    pub fn new(mut solver: Adachi, config: MissionConfig) -> Self {
+        let start = solver.get_maze().get_start().pos;
+        let goal = solver.get_goal();
+        solver.set_mode(config.search_mode);
+        solver.set_target(goal);
+        Mission {
+            solver,
+            config,
+            phase: MissionPhase::Search,
+            start,
+            goal,
+            fast_route: vec![],
+            events: vec![],
+            steps: 0,
+            out_of_budget: false,
+        }
+    }
+
+    pub fn phase(&self) -> MissionPhase {
+        self.phase
+    }
+
+    // Speed for the current phase
+    pub fn speed(&self) -> f32 {
+        match self.phase {
+            MissionPhase::Search => self.config.search_speed,
+            MissionPhase::Return => self.config.return_speed,
+            MissionPhase::FastRun | MissionPhase::Finished => self.config.fast_speed,
+        }
+    }
+
+    pub fn solver(&self) -> &Adachi {
+        &self.solver
+    }
+
+    pub fn get_location(&self) -> Location {
+        self.solver.get_location()
+    }
+
+    pub fn set_location(&mut self, location: Location) {
+        self.solver.set_location(location);
+    }
+
+    pub fn steps_taken(&self) -> usize {
+        self.steps
+    }
+
+    // Steps left of the budget, None without a budget
+    pub fn remaining_steps(&self) -> Option<usize> {
+        self.config
+            .budget
+            .map(|budget| budget.steps().saturating_sub(self.steps))
+    }
+
+    // True once the budget forced the mission to head home
+    pub fn out_of_budget(&self) -> bool {
+        self.out_of_budget
+    }
+
+    fn check_budget(&mut self) -> anyhow::Result<()> {
+        let (Some(budget), Some(remaining)) = (self.config.budget, self.remaining_steps()) else {
+            return Ok(());
+        };
+        if self.out_of_budget {
+            return Ok(());
+        }
+        let maze = self.solver.get_maze();
+        let pos = self.solver.get_location().pos;
+        // Without a known way home there is no telling how long it takes,
+        // so turn back right away
+        let home = maze.distance(pos, self.start, StepMapMode::UnexploredAsPresent);
+        if home.is_some_and(|home| remaining > home as usize + budget.margin) {
+            return Ok(());
+        }
+        log::info!(
+            "Budget nearly exhausted ({} steps left, {:?} home), returning",
+            remaining,
+            home
+        );
+        self.out_of_budget = true;
+        if self.phase == MissionPhase::Search {
+            self.enter(MissionPhase::Return)?;
+        }
+        // Only take known ways home, if there is one
+        if home.is_some() {
+            self.solver.set_mode(StepMapMode::UnexploredAsPresent);
+        }
+        Ok(())
+    }
+
+    // Route of the fast run, empty before the FastRun phase
+    pub fn fast_route(&self) -> &[Position] {
+        &self.fast_route
+    }
+
+    // Events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<MissionEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn enter(&mut self, to: MissionPhase) -> anyhow::Result<()> {
+        log::info!("Mission phase {:?} -> {:?}", self.phase, to);
+        match to {
+            MissionPhase::Return => {
+                self.solver.set_mode(self.config.return_mode);
+                self.solver.set_target(self.start);
+            }
+            MissionPhase::FastRun => {
+                self.fast_route = self.solver.plan_fast_run(self.start, self.goal)?;
+                self.solver.set_target(self.goal);
+            }
+            MissionPhase::Search | MissionPhase::Finished => (),
+        }
+        self.events.push(MissionEvent::PhaseChanged {
+            from: self.phase,
+            to,
+        });
+        self.phase = to;
+        Ok(())
+    }
+
+    // Next direction, None once the fast run has reached the goal
+    pub fn navigate(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+    ) -> anyhow::Result<Option<Direction>> {
+        let pos = self.solver.get_location().pos;
+        if self.phase == MissionPhase::Search && pos == self.goal {
+            self.enter(MissionPhase::Return)?;
+        }
+        if self.phase == MissionPhase::Return && pos == self.start {
+            if self.out_of_budget {
+                self.enter(MissionPhase::Finished)?;
+            } else {
+                self.enter(MissionPhase::FastRun)?;
+            }
+        }
+        if self.phase == MissionPhase::FastRun && pos == self.goal {
+            self.enter(MissionPhase::Finished)?;
+        }
+
+        match self.phase {
+            MissionPhase::Search | MissionPhase::Return => {
+                self.check_budget()?;
+                if self.phase == MissionPhase::Return && pos == self.start {
+                    // The budget ran out right at the start
+                    self.enter(MissionPhase::Finished)?;
+                    return Ok(None);
+                }
+                let direction = self.solver.navigate_to(front, left, right)?;
+                self.steps += 1;
+                Ok(Some(direction))
+            }
+            MissionPhase::FastRun => {
+                let index = self
+                    .fast_route
+                    .iter()
+                    .position(|p| *p == pos)
+                    .ok_or(anyhow::anyhow!("Left the fast run route at {:?}", pos))?;
+                let compass = compass_between(pos, self.fast_route[index + 1])
+                    .ok_or(anyhow::anyhow!("Fast run route is broken at {:?}", pos))?;
+                Ok(Some(
+                    self.solver.get_location().dir.get_direction_to(compass),
+                ))
+            }
+            MissionPhase::Finished => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::maze::{Compass, Direction, Position, Wall};
+use crate::path_finder::{MoveFailure, Observation, PathFinder};
+use std::io::{BufRead, Write};
+
+/*
+    Adapter for the mms micromouse simulator (github.com/mackorone/mms).
+    mms starts the program and talks to it over stdin / stdout, one command
+    per line, e.g. "wallFront" answered with "true" or "false". Its
+    coordinates match this crate: (0, 0) south-west, y growing north.
+
+    A binary that runs any PathFinder in mms:
+
+        let mut mms = Mms::stdio();
+        let maze = Maze::new(mms.maze_width()?, mms.maze_height()?);
+        let mut finder = Adachi::new(maze);
+        run(&mut finder, &mut mms, goal, 10_000)?;
+
+    Logging has to go to stderr, stdout belongs to mms.
+*/
+pub struct Mms<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+}
+
+impl Mms<std::io::StdinLock<'static>, std::io::Stdout> {
+    pub fn stdio() -> Self {
+        Mms::new(std::io::stdin().lock(), std::io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> Mms<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Mms { input, output }
+    }
+
+    // A command without an answer (drawing)
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.output, "{}", command)
+            .and_then(|_| self.output.flush())
+            .map_err(|e| format!("mms: cannot send \"{}\": {}", command, e))
+    }
+
+    fn query(&mut self, command: &str) -> Result<String, String> {
+        self.send(command)?;
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => Err(format!("mms: no answer to \"{}\"", command)),
+            Ok(_) => Ok(line.trim().to_string()),
+            Err(e) => Err(format!("mms: cannot read answer to \"{}\": {}", command, e)),
+        }
+    }
+
+    fn query_bool(&mut self, command: &str) -> Result<bool, String> {
+        match self.query(command)?.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => Err(format!(
+                "mms: unexpected answer \"{}\" to \"{}\"",
+                other, command
+            )),
+        }
+    }
+
+    fn query_ack(&mut self, command: &str) -> Result<bool, String> {
+        match self.query(command)?.as_str() {
+            "ack" => Ok(true),
+            "crash" => Ok(false),
+            other => Err(format!(
+                "mms: unexpected answer \"{}\" to \"{}\"",
+                other, command
+            )),
+        }
+    }
+
+    fn query_usize(&mut self, command: &str) -> Result<usize, String> {
+        let answer = self.query(command)?;
+        answer
+            .parse()
+            .map_err(|_| format!("mms: unexpected answer \"{}\" to \"{}\"", answer, command))
+    }
+
+    pub fn maze_width(&mut self) -> Result<usize, String> {
+        self.query_usize("mazeWidth")
+    }
+
+    pub fn maze_height(&mut self) -> Result<usize, String> {
+        self.query_usize("mazeHeight")
+    }
+
+    pub fn wall_front(&mut self) -> Result<bool, String> {
+        self.query_bool("wallFront")
+    }
+
+    pub fn wall_left(&mut self) -> Result<bool, String> {
+        self.query_bool("wallLeft")
+    }
+
+    pub fn wall_right(&mut self) -> Result<bool, String> {
+        self.query_bool("wallRight")
+    }
+
+    // False if the mouse crashed into a wall
+    pub fn move_forward(&mut self) -> Result<bool, String> {
+        self.query_ack("moveForward")
+    }
+
+    pub fn turn_left(&mut self) -> Result<(), String> {
+        self.query_ack("turnLeft").map(|_| ())
+    }
+
+    pub fn turn_right(&mut self) -> Result<(), String> {
+        self.query_ack("turnRight").map(|_| ())
+    }
+
+    // True once the reset button of the GUI was pressed; confirm with ack_reset
+    pub fn was_reset(&mut self) -> Result<bool, String> {
+        self.query_bool("wasReset")
+    }
+
+    pub fn ack_reset(&mut self) -> Result<(), String> {
+        self.query_ack("ackReset").map(|_| ())
+    }
+
+    pub fn set_wall(&mut self, pos: Position, compass: Compass) -> Result<(), String> {
+        let side = compass.to_log().to_ascii_lowercase();
+        self.send(&format!("setWall {} {} {}", pos.x, pos.y, side))
+    }
+
+    // Color is one of the mms color letters, e.g. 'G' green, 'R' red
+    pub fn set_color(&mut self, pos: Position, color: char) -> Result<(), String> {
+        self.send(&format!("setColor {} {} {}", pos.x, pos.y, color))
+    }
+
+    pub fn set_text(&mut self, pos: Position, text: &str) -> Result<(), String> {
+        self.send(&format!("setText {} {} {}", pos.x, pos.y, text))
+    }
+
+    pub fn observe(&mut self) -> Result<Observation, String> {
+        Ok(Observation::new(
+            Wall::from_bool(self.wall_front()?),
+            Wall::from_bool(self.wall_left()?),
+            Wall::from_bool(self.wall_right()?),
+        ))
+    }
+
+    // Turn towards `direction` and move one cell; false on a crash
+    pub fn step(&mut self, direction: Direction) -> Result<bool, String> {
+        match direction {
+            Direction::Forward => (),
+            Direction::Left => self.turn_left()?,
+            Direction::Right => self.turn_right()?,
+            Direction::Backward => {
+                self.turn_right()?;
+                self.turn_right()?;
+            }
+        }
+        self.move_forward()
+    }
+}
+
+/*
+    Run the finder in mms until it reaches `goal`. The walls the finder
+    knows around each visited cell are drawn in the GUI. The GUI reset
+    button puts the finder back on the start with its map kept. Returns
+    the number of moves, like Simulator::run.
+*/
+pub fn run<R: BufRead, W: Write>(
+    finder: &mut dyn PathFinder,
+    mms: &mut Mms<R, W>,
+    goal: Position,
+    max_steps: usize,
+) -> Result<usize, String> {
+    let mut steps = 0;
+    while finder.get_location().pos != goal {
+        if steps >= max_steps {
+            return Err(format!("Step limit {} reached", max_steps));
+        }
+        if mms.was_reset()? {
+            finder.reset(true);
+            mms.ack_reset()?;
+        }
+        steps += 1;
+        let observation = mms.observe()?;
+        let direction = finder
+            .navigate_observed(&observation, goal)
+            .map_err(|e| e.to_string())?;
+
+        let here = finder.get_location().pos;
+        for compass in Compass::iter() {
+            if finder.get_maze().get(here.y, here.x, compass) == Wall::Present {
+                mms.set_wall(here, compass)?;
+            }
+        }
+
+        let mut next = finder.get_location();
+        next.turn(direction);
+        if mms.step(direction)? {
+            next.forward();
+            finder.set_location(next);
+        } else {
+            finder.report_move_failure(MoveFailure::Displaced(next));
+        }
+    }
+    Ok(steps)
+}
@@ -0,0 +1,130 @@
+use crate::adachi::Adachi;
+use crate::maze::{Direction, Location, Maze, Position, Wall};
+use crate::path_finder::{MoveFailure, Observation, PathFinder, Result};
+
+/*
+    High-level wrapper around Adachi for a control loop:
+
+        mouse.sense(&observation);
+        let direction = mouse.decide()?;
+        // turn towards direction, drive one cell
+        mouse.moved();
+
+    The mouse keeps its own location consistent: decide() remembers the
+    direction and moved() turns and advances the location, so the caller
+    never calls set_location. If the move did not happen as commanded,
+    call move_failed() instead of moved().
+*/
+pub struct Mouse {
+    solver: Adachi,
+    goal: Position,
+    observation: Option<Observation>,
+    // Direction returned by the last decide, until moved / move_failed
+    pending: Option<Direction>,
+}
+
+impl Mouse {
+    // Explores `maze` (usually empty) towards its goal
+    pub fn new(maze: Maze) -> Self {
+        Mouse::with_solver(Adachi::new(maze))
+    }
+
+    // Any configured solver; the goal is the one of its maze
+    pub fn with_solver(solver: Adachi) -> Self {
+        Mouse {
+            goal: solver.get_maze().get_goal(),
+            solver,
+            observation: None,
+            pending: None,
+        }
+    }
+
+    pub fn set_goal(&mut self, goal: Position) {
+        self.goal = goal;
+    }
+
+    pub fn goal(&self) -> Position {
+        self.goal
+    }
+
+    // Walls seen from the current location, used by the next decide
+    pub fn sense(&mut self, observation: &Observation) {
+        self.observation = Some(*observation);
+    }
+
+    /*
+        Next move from the current location. Without a sense() since the
+        last move every wall counts as unexplored. Goal reached is an
+        Err(NavigationError::GoalReached) like PathFinder::navigate.
+        Deciding again before moved() replaces the pending move.
+    */
+    pub fn decide(&mut self) -> Result<Direction> {
+        let unknown = Observation::new(Wall::Unexplored, Wall::Unexplored, Wall::Unexplored);
+        let observation = self.observation.take().unwrap_or(unknown);
+        self.pending = None;
+        let direction = self
+            .solver
+            .navigate_step_observed(&observation, self.goal)?
+            .into_direction()?;
+        self.pending = Some(direction);
+        Ok(direction)
+    }
+
+    // The move returned by decide is done: the mouse faces its direction
+    // one cell further
+    pub fn moved(&mut self) {
+        let Some(direction) = self.pending.take() else {
+            log::warn!("Mouse::moved without a decided move");
+            return;
+        };
+        let mut location = self.solver.get_location();
+        location.turn(direction);
+        location.forward();
+        self.solver.set_location(location);
+    }
+
+    // The move returned by decide did not happen as commanded
+    pub fn move_failed(&mut self, failure: MoveFailure) {
+        self.pending = None;
+        self.solver.report_move_failure(failure);
+    }
+
+    pub fn where_am_i(&self) -> Location {
+        self.solver.get_location()
+    }
+
+    pub fn at_goal(&self) -> bool {
+        self.where_am_i().pos == self.goal
+    }
+
+    // Move decided but not yet reported
+    pub fn pending(&self) -> Option<Direction> {
+        self.pending
+    }
+
+    pub fn maze(&self) -> &Maze {
+        self.solver.get_maze()
+    }
+
+    pub fn solver(&self) -> &Adachi {
+        &self.solver
+    }
+
+    // For settings (tie break, step map mode, ...)
+    pub fn solver_mut(&mut self) -> &mut Adachi {
+        &mut self.solver
+    }
+
+    // Back to the start, keeping the map if keep_map
+    pub fn reset(&mut self, keep_map: bool) {
+        self.observation = None;
+        self.pending = None;
+        self.solver.reset(keep_map);
+    }
+}
+
+impl From<Adachi> for Mouse {
+    fn from(solver: Adachi) -> Self {
+        Mouse::with_solver(solver)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::maze::{Compass, Maze, Wall};
+
+/*
+    Occupancy grid in the ROS map_server convention: one byte per pixel,
+    0 = occupied, 254 = free, 205 = unknown (unexplored walls).
+
+    Each maze cell is `cell_pixels` wide; walls are `wall_pixels` thick and
+    drawn on the lower/left edge of the cell, plus one extra wall strip on
+    the top and right edges of the maze, so the image is
+    width * cell_pixels + wall_pixels pixels wide. Pillars are always
+    occupied. `resolution` is meters per pixel (for the YAML file).
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OccupancyConfig {
+    cell_pixels: usize,
+    wall_pixels: usize,
+    resolution: f64,
+}
+
+impl OccupancyConfig {
+    // The wall has to leave some free pixels in the cell
+    pub fn new(cell_pixels: usize, wall_pixels: usize, resolution: f64) -> Result<Self, String> {
+        if wall_pixels == 0 || wall_pixels >= cell_pixels {
+            return Err(format!(
+                "Walls of {} pixels do not fit in cells of {} pixels",
+                wall_pixels, cell_pixels
+            ));
+        }
+        Ok(OccupancyConfig {
+            cell_pixels,
+            wall_pixels,
+            resolution,
+        })
+    }
+
+    pub fn cell_pixels(&self) -> usize {
+        self.cell_pixels
+    }
+
+    pub fn wall_pixels(&self) -> usize {
+        self.wall_pixels
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+}
+
+impl Default for OccupancyConfig {
+    // 18cm classic cells at 1cm per pixel
+    fn default() -> Self {
+        OccupancyConfig {
+            cell_pixels: 18,
+            wall_pixels: 1,
+            resolution: 0.01,
+        }
+    }
+}
+
+pub const OCCUPIED: u8 = 0;
+pub const FREE: u8 = 254;
+pub const UNKNOWN: u8 = 205;
+
+// Rows are stored top (north) first, like the image
+#[derive(Clone, Debug, PartialEq)]
+pub struct OccupancyGrid {
+    pub width: usize,
+    pub height: usize,
+    pub resolution: f64,
+    pub data: Vec<u8>,
+}
+
+impl OccupancyGrid {
+    // Pixel at column x, row y (row 0 = top of the image)
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x]
+    }
+
+    // Binary PGM (P5)
+    pub fn write_pgm(&self, mut writer: impl std::io::Write) -> Result<(), String> {
+        let header = format!("P5\n{} {}\n255\n", self.width, self.height);
+        writer
+            .write_all(header.as_bytes())
+            .and_then(|_| writer.write_all(&self.data))
+            .map_err(|e| e.to_string())
+    }
+
+    // map_server metadata for the PGM written to `image`. The origin is the
+    // bottom-left corner of the maze (the start corner).
+    pub fn write_yaml(&self, mut writer: impl std::io::Write, image: &str) -> Result<(), String> {
+        let yaml = format!(
+            "image: {}\nresolution: {}\norigin: [0.0, 0.0, 0.0]\nnegate: 0\noccupied_thresh: 0.65\nfree_thresh: 0.196\n",
+            image, self.resolution
+        );
+        writer.write_all(yaml.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    // Write `<stem>.pgm` and `<stem>.yaml`
+    pub fn save(&self, stem: &str) -> Result<(), String> {
+        let image = format!("{}.pgm", stem);
+        let pgm = std::fs::File::create(&image).map_err(|e| e.to_string())?;
+        self.write_pgm(std::io::BufWriter::new(pgm))?;
+        let yaml = std::fs::File::create(format!("{}.yaml", stem)).map_err(|e| e.to_string())?;
+        // map_server resolves the image relative to the YAML file
+        let name = std::path::Path::new(&image)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or(image.clone());
+        self.write_yaml(yaml, &name)
+    }
+}
+
+impl Maze {
+    pub fn to_occupancy_grid(&self, config: &OccupancyConfig) -> OccupancyGrid {
+        let (cell, wall) = (config.cell_pixels, config.wall_pixels);
+        let width = self.get_width() * cell + wall;
+        let height = self.get_height() * cell + wall;
+        let mut data = vec![FREE; width * height];
+        let value = |w: Wall| match w {
+            Wall::Absent => FREE,
+            Wall::Present => OCCUPIED,
+            Wall::Unexplored => UNKNOWN,
+        };
+        // Fill a rectangle given in maze pixels (origin bottom-left)
+        let mut fill = |x0: usize, y0: usize, w: usize, h: usize, v: u8| {
+            for y in y0..y0 + h {
+                let row = height - 1 - y;
+                data[row * width + x0..row * width + x0 + w].fill(v);
+            }
+        };
+        for y in 0..self.get_height() {
+            for x in 0..self.get_width() {
+                let (px, py) = (x * cell, y * cell);
+                let south = value(self.get(y, x, Compass::South));
+                let west = value(self.get(y, x, Compass::West));
+                fill(px + wall, py, cell - wall, wall, south);
+                fill(px, py + wall, wall, cell - wall, west);
+                if y == self.get_height() - 1 {
+                    let north = value(self.get(y, x, Compass::North));
+                    fill(px + wall, py + cell, cell - wall, wall, north);
+                }
+                if x == self.get_width() - 1 {
+                    let east = value(self.get(y, x, Compass::East));
+                    fill(px + cell, py + wall, wall, cell - wall, east);
+                }
+            }
+        }
+        // Pillars
+        for y in 0..=self.get_height() {
+            for x in 0..=self.get_width() {
+                fill(x * cell, y * cell, wall, wall, OCCUPIED);
+            }
+        }
+        OccupancyGrid {
+            width,
+            height,
+            resolution: config.resolution,
+            data,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::maze::StepMapMode;
+use crate::maze::{Compass, Maze, Position, Wall};
+
+// Why a path was rejected. `index` is the position in the path of the
+// offending cell (for moves, the cell being entered).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathError {
+    OutOfBounds {
+        index: usize,
+        pos: Position,
+    },
+    NotAdjacent {
+        index: usize,
+        from: Position,
+        to: Position,
+    },
+    Blocked {
+        index: usize,
+        from: Position,
+        to: Position,
+        wall: Wall,
+    },
+    // The path enters a cell it has already been through
+    Revisited {
+        index: usize,
+        pos: Position,
+    },
+    // No fast run drives the path from its first to its last cell
+    NoRun {
+        from: Position,
+        to: Position,
+    },
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PathError::OutOfBounds { index, pos } => {
+                write!(
+                    f,
+                    "step {}: ({}, {}) is outside the maze",
+                    index, pos.x, pos.y
+                )
+            }
+            PathError::NotAdjacent { index, from, to } => write!(
+                f,
+                "step {}: ({}, {}) and ({}, {}) are not adjacent",
+                index, from.x, from.y, to.x, to.y
+            ),
+            PathError::Blocked {
+                index,
+                from,
+                to,
+                wall,
+            } => write!(
+                f,
+                "step {}: wall between ({}, {}) and ({}, {}) is {:?}",
+                index, from.x, from.y, to.x, to.y, wall
+            ),
+            PathError::Revisited { index, pos } => {
+                write!(f, "step {}: ({}, {}) is visited twice", index, pos.x, pos.y)
+            }
+            PathError::NoRun { from, to } => write!(
+                f,
+                "no fast run along the path from ({}, {}) to ({}, {})",
+                from.x, from.y, to.x, to.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+// Compass from `from` to an adjacent cell, None if not adjacent
+pub fn compass_between(from: Position, to: Position) -> Option<Compass> {
+    Compass::iter().find(|compass| {
+        let (dx, dy) = compass.to_delta();
+        from.x as isize + dx as isize == to.x as isize
+            && from.y as isize + dy as isize == to.y as isize
+    })
+}
+
+impl Maze {
+    // Check that every cell is inside the maze, consecutive cells are
+    // adjacent and no wall is crossed. `mode` decides whether unexplored
+    // walls may be crossed.
+    pub fn validate_path(&self, path: &[Position], mode: StepMapMode) -> Result<(), PathError> {
+        for (index, &pos) in path.iter().enumerate() {
+            if pos.x >= self.get_width() || pos.y >= self.get_height() {
+                return Err(PathError::OutOfBounds { index, pos });
+            }
+        }
+        for (index, pair) in path.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let index = index + 1;
+            let compass =
+                compass_between(from, to).ok_or(PathError::NotAdjacent { index, from, to })?;
+            if !self.can_move(from.y, from.x, compass, mode) {
+                let wall = self.get(from.y, from.x, compass);
+                return Err(PathError::Blocked {
+                    index,
+                    from,
+                    to,
+                    wall,
+                });
+            }
+        }
+        Ok(())
+    }
+}
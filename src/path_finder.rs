@@ -1,15 +1,282 @@
 use crate::maze;
-use anyhow::Result;
+
+// Why a PathFinder could not give a direction. Plain enum (no strings or
+// allocation) so firmware can match on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NavigationError {
+    // Only from navigate / navigate_observed; navigate_step reports it
+    // as NavigationStep::goal_reached instead
+    GoalReached,
+    // Every way out of the current cell is blocked
+    NoPath,
+    // The observation contradicts the map (e.g. an opening in an outer
+    // wall), usually because the location estimate is wrong
+    InconsistentMap,
+    // Location or goal outside the maze
+    OutOfBounds,
+    // The target is a banned cell (see Adachi::ban_cell)
+    TargetBanned,
+    // Moving back and forth between `cells` cells for `steps` steps
+    // without discovering a wall (see Adachi::set_stall_detection)
+    Stuck { steps: u32, cells: u32 },
+}
+
+impl core::fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            NavigationError::GoalReached => write!(f, "Goal reached"),
+            NavigationError::NoPath => write!(f, "No path to go"),
+            NavigationError::InconsistentMap => write!(f, "Observation contradicts the map"),
+            NavigationError::OutOfBounds => write!(f, "Position outside the maze"),
+            NavigationError::TargetBanned => write!(f, "Target cell is banned"),
+            NavigationError::Stuck { steps, cells } => write!(
+                f,
+                "Stuck: {} steps in {} cells without a new wall",
+                steps, cells
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NavigationError {}
+
+pub type Result<T> = core::result::Result<T, NavigationError>;
+
+// Long-range reading along the heading (ToF style front sensor).
+// The next `open_cells` walls ahead are absent; if `wall` is true the wall
+// right after them is present, otherwise the sensor range ended there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RangeReading {
+    pub open_cells: usize,
+    pub wall: bool,
+}
+
+// Everything sensed from the current location
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    pub front: maze::Wall,
+    pub left: maze::Wall,
+    pub right: maze::Wall,
+    pub ahead: Option<RangeReading>,
+}
+
+impl Observation {
+    pub fn new(front: maze::Wall, left: maze::Wall, right: maze::Wall) -> Self {
+        Observation {
+            front,
+            left,
+            right,
+            ahead: None,
+        }
+    }
+}
+
+// Reported when the move returned by navigate did not happen as commanded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveFailure {
+    // Stopped before leaving the cell (emergency stop, wheel slip)
+    Aborted,
+    // A wall the map said was open stopped the mouse
+    Blocked,
+    // The mouse ended up somewhere else
+    Displaced(maze::Location),
+}
+
+// Result of one navigation decision
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavigationStep {
+    // None once the goal is reached
+    pub direction: Option<maze::Direction>,
+    // Steps left to the goal from the current cell, if the finder knows
+    pub expected_remaining_steps: Option<u16>,
+    pub goal_reached: bool,
+}
+
+impl NavigationStep {
+    pub fn go(direction: maze::Direction, expected_remaining_steps: Option<u16>) -> Self {
+        NavigationStep {
+            direction: Some(direction),
+            expected_remaining_steps,
+            goal_reached: false,
+        }
+    }
+
+    pub fn goal_reached() -> Self {
+        NavigationStep {
+            direction: None,
+            expected_remaining_steps: Some(0),
+            goal_reached: true,
+        }
+    }
+
+    // The direction, or the "Goal reached" error used by navigate
+    pub fn into_direction(self) -> Result<maze::Direction> {
+        match self.direction {
+            Some(direction) if !self.goal_reached => Ok(direction),
+            _ => Err(NavigationError::GoalReached),
+        }
+    }
+}
 
 pub trait PathFinder {
-    fn navigate(
+    // Decide the next move. Reaching the goal is a normal outcome
+    // (goal_reached), errors are reserved for real failures.
+    fn navigate_step(
         &mut self,
         front: maze::Wall,
         left: maze::Wall,
         right: maze::Wall,
         goal: maze::Position,
-    ) -> Result<maze::Direction>;
+    ) -> Result<NavigationStep>;
     fn get_location(&self) -> maze::Location;
     fn set_location(&mut self, location: maze::Location);
     fn get_maze(&self) -> &maze::Maze;
+    // Put the robot back on the start cell.
+    // The learned walls are cleared unless keep_map is true.
+    fn reset(&mut self, keep_map: bool);
+
+    // The last move failed. The location stays where it was before the
+    // move unless the mouse was displaced.
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        if let MoveFailure::Displaced(location) = failure {
+            self.set_location(location);
+        }
+    }
+
+    // Same as navigate_step with a full observation. Finders that cannot
+    // use the long-range reading just ignore it.
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<NavigationStep> {
+        self.navigate_step(observation.front, observation.left, observation.right, goal)
+    }
+
+    // Next direction only; reaching the goal is reported as an error
+    fn navigate(
+        &mut self,
+        front: maze::Wall,
+        left: maze::Wall,
+        right: maze::Wall,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        self.navigate_step(front, left, right, goal)?
+            .into_direction()
+    }
+
+    fn navigate_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        self.navigate_step_observed(observation, goal)?
+            .into_direction()
+    }
 }
+
+// Forwarding impls so strategies can be picked at run time:
+// Box<dyn PathFinder> and &mut dyn PathFinder are PathFinders themselves.
+// Every method is forwarded to keep the overrides of the inner finder.
+impl<P: PathFinder + ?Sized> PathFinder for Box<P> {
+    fn navigate_step(
+        &mut self,
+        front: maze::Wall,
+        left: maze::Wall,
+        right: maze::Wall,
+        goal: maze::Position,
+    ) -> Result<NavigationStep> {
+        (**self).navigate_step(front, left, right, goal)
+    }
+    fn get_location(&self) -> maze::Location {
+        (**self).get_location()
+    }
+    fn set_location(&mut self, location: maze::Location) {
+        (**self).set_location(location)
+    }
+    fn get_maze(&self) -> &maze::Maze {
+        (**self).get_maze()
+    }
+    fn reset(&mut self, keep_map: bool) {
+        (**self).reset(keep_map)
+    }
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        (**self).report_move_failure(failure)
+    }
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<NavigationStep> {
+        (**self).navigate_step_observed(observation, goal)
+    }
+    fn navigate(
+        &mut self,
+        front: maze::Wall,
+        left: maze::Wall,
+        right: maze::Wall,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        (**self).navigate(front, left, right, goal)
+    }
+    fn navigate_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        (**self).navigate_observed(observation, goal)
+    }
+}
+
+impl<P: PathFinder + ?Sized> PathFinder for &mut P {
+    fn navigate_step(
+        &mut self,
+        front: maze::Wall,
+        left: maze::Wall,
+        right: maze::Wall,
+        goal: maze::Position,
+    ) -> Result<NavigationStep> {
+        (**self).navigate_step(front, left, right, goal)
+    }
+    fn get_location(&self) -> maze::Location {
+        (**self).get_location()
+    }
+    fn set_location(&mut self, location: maze::Location) {
+        (**self).set_location(location)
+    }
+    fn get_maze(&self) -> &maze::Maze {
+        (**self).get_maze()
+    }
+    fn reset(&mut self, keep_map: bool) {
+        (**self).reset(keep_map)
+    }
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        (**self).report_move_failure(failure)
+    }
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<NavigationStep> {
+        (**self).navigate_step_observed(observation, goal)
+    }
+    fn navigate(
+        &mut self,
+        front: maze::Wall,
+        left: maze::Wall,
+        right: maze::Wall,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        (**self).navigate(front, left, right, goal)
+    }
+    fn navigate_observed(
+        &mut self,
+        observation: &Observation,
+        goal: maze::Position,
+    ) -> Result<maze::Direction> {
+        (**self).navigate_observed(observation, goal)
+    }
+}
+
+// PathFinder must stay object safe
+const _: Option<&dyn PathFinder> = None;
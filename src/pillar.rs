@@ -0,0 +1,81 @@
+use crate::maze::{Compass, Maze, Position, Wall};
+
+/*
+    Pillar addressing: pillar (x, y) is the south-west corner of cell (x, y),
+    so pillars run from (0, 0) to (width, height).
+
+    A wall is addressed by a pillar and the compass direction towards the
+    neighbor pillar it connects to.
+
+    (0,2)   (1,2)   (2,2)
+        +-------+-------+
+        |       |       |
+    (0,1)   (1,1)   (2,1)
+        +       +-------+
+        |               |
+        +-------+-------+
+    (0,0)   (1,0)   (2,0)
+
+    Pillar (1, 1) North is the west wall of cell (1, 1),
+    pillar (1, 1) East is the south wall of cell (1, 1).
+*/
+
+// Cell + compass of the wall between `pillar` and its neighbor towards
+// `compass`. None if the wall is outside a maze of the given size.
+pub fn pillar_to_cell(
+    pillar: Position,
+    compass: Compass,
+    width: usize,
+    height: usize,
+) -> Option<(Position, Compass)> {
+    let (x, y) = (pillar.x, pillar.y);
+    match compass {
+        Compass::East if x < width && y < height => Some((pillar, Compass::South)),
+        Compass::East if x < width && y == height => {
+            Some((Position::new(x, y - 1), Compass::North))
+        }
+        Compass::North if y < height && x < width => Some((pillar, Compass::West)),
+        Compass::North if y < height && x == width => {
+            Some((Position::new(x - 1, y), Compass::East))
+        }
+        Compass::West if x > 0 => {
+            pillar_to_cell(Position::new(x - 1, y), Compass::East, width, height)
+        }
+        Compass::South if y > 0 => {
+            pillar_to_cell(Position::new(x, y - 1), Compass::North, width, height)
+        }
+        _ => None,
+    }
+}
+
+// Pillar + compass (always East or North) of a cell wall
+pub fn cell_to_pillar(pos: Position, compass: Compass) -> (Position, Compass) {
+    match compass {
+        Compass::North => (Position::new(pos.x, pos.y + 1), Compass::East),
+        Compass::East => (Position::new(pos.x + 1, pos.y), Compass::North),
+        Compass::South => (pos, Compass::East),
+        Compass::West => (pos, Compass::North),
+    }
+}
+
+impl Maze {
+    pub fn get_pillar_wall(&self, pillar: Position, compass: Compass) -> Option<Wall> {
+        let (pos, compass) = pillar_to_cell(pillar, compass, self.get_width(), self.get_height())?;
+        Some(self.get(pos.y, pos.x, compass))
+    }
+
+    pub fn set_pillar_wall(
+        &mut self,
+        pillar: Position,
+        compass: Compass,
+        wall: Wall,
+    ) -> Result<(), String> {
+        let (pos, compass) = pillar_to_cell(pillar, compass, self.get_width(), self.get_height())
+            .ok_or(format!(
+            "No wall from pillar ({}, {}) towards {:?}",
+            pillar.x, pillar.y, compass
+        ))?;
+        self.set(pos.y, pos.x, compass, wall);
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use crate::fast_run::TimeModel;
+use serde::{Deserialize, Serialize};
+
+// One slalom turn: time spent in the curve and the straight offsets
+// (cells) driven at turn speed before and after it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Slalom {
+    pub time: f32,
+    pub offset_before: f32,
+    pub offset_after: f32,
+}
+
+impl Slalom {
+    pub fn new(time: f32, offset_before: f32, offset_after: f32) -> Self {
+        Slalom {
+            time,
+            offset_before,
+            offset_after,
+        }
+    }
+
+    // Curve plus offsets
+    pub fn total_time(&self, turn_speed: f32) -> f32 {
+        self.time + (self.offset_before + self.offset_after) / turn_speed
+    }
+
+    /*
+        Time the turn adds to a run on top of its straights. fast_run
+        straights already cover the offsets, accelerating from (or braking
+        to) turn_speed at `accel`, so each offset costs its time at turn
+        speed minus the time the straight would have spent on it. Exact as
+        long as the straights are at least twice the offsets long.
+    */
+    pub fn run_time(&self, turn_speed: f32, accel: f32) -> f32 {
+        let ramp = |offset: f32| {
+            if accel <= 0.0 {
+                offset / turn_speed
+            } else {
+                ((turn_speed * turn_speed + 2.0 * accel * offset).sqrt() - turn_speed) / accel
+            }
+        };
+        self.total_time(turn_speed) - ramp(self.offset_before) - ramp(self.offset_after)
+    }
+}
+
+/*
+    Named run tuning shared by the host and the robot: velocities in
+    cells/s, acceleration in cells/s^2, one slalom per turn type. Stored as
+    JSON, e.g.
+
+    [{"name":"search","accel":20.0,"max_speed":5.0,...,
+      "turn90":{"time":0.3,"offset_before":0.0,"offset_after":0.0},...}]
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub accel: f32,
+    pub max_speed: f32,
+    pub diagonal_max_speed: f32,
+    pub turn_speed: f32,
+    pub turn45: Slalom,
+    pub turn90: Slalom,
+    pub turn135: Slalom,
+    pub turn_v90: Slalom,
+}
+
+impl Profile {
+    // Timing model of fast_run for this profile, see Slalom::run_time for
+    // the offsets
+    pub fn time_model(&self) -> TimeModel {
+        // Straights no faster than the turns are driven at constant speed
+        let accel = if self.max_speed > self.turn_speed {
+            self.accel
+        } else {
+            0.0
+        };
+        let turn = |slalom: &Slalom| slalom.run_time(self.turn_speed, accel);
+        TimeModel {
+            accel: self.accel,
+            max_speed: self.max_speed,
+            diagonal_max_speed: self.diagonal_max_speed,
+            turn_speed: self.turn_speed,
+            turn45: turn(&self.turn45),
+            turn90: turn(&self.turn90),
+            turn135: turn(&self.turn135),
+            turn_v90: turn(&self.turn_v90),
+        }
+    }
+
+    // Slow and safe, for exploration
+    pub fn search() -> Self {
+        Profile {
+            name: "search".to_string(),
+            accel: 20.0,
+            max_speed: 5.0,
+            diagonal_max_speed: 5.0,
+            turn_speed: 3.0,
+            turn45: Slalom::new(0.15, 0.0, 0.0),
+            turn90: Slalom::new(0.3, 0.0, 0.0),
+            turn135: Slalom::new(0.3, 0.0, 0.0),
+            turn_v90: Slalom::new(0.25, 0.0, 0.0),
+        }
+    }
+
+    // First fast run: same numbers as TimeModel::default
+    pub fn fast_v1() -> Self {
+        let model = TimeModel::default();
+        Profile {
+            name: "fast_v1".to_string(),
+            accel: model.accel,
+            max_speed: model.max_speed,
+            diagonal_max_speed: model.diagonal_max_speed,
+            turn_speed: model.turn_speed,
+            turn45: Slalom::new(model.turn45, 0.0, 0.0),
+            turn90: Slalom::new(model.turn90, 0.0, 0.0),
+            turn135: Slalom::new(model.turn135, 0.0, 0.0),
+            turn_v90: Slalom::new(model.turn_v90, 0.0, 0.0),
+        }
+    }
+
+    // Faster turns with offsets to line up the exit
+    pub fn fast_v2() -> Self {
+        Profile {
+            name: "fast_v2".to_string(),
+            accel: 70.0,
+            max_speed: 40.0,
+            diagonal_max_speed: 28.0,
+            turn_speed: 8.0,
+            turn45: Slalom::new(0.08, 0.1, 0.2),
+            turn90: Slalom::new(0.13, 0.1, 0.1),
+            turn135: Slalom::new(0.15, 0.2, 0.1),
+            turn_v90: Slalom::new(0.1, 0.1, 0.1),
+        }
+    }
+}
+
+// A set of profiles looked up by name
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(transparent)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    // search, fast_v1 and fast_v2
+    pub fn builtin() -> Self {
+        Profiles {
+            profiles: vec![Profile::search(), Profile::fast_v1(), Profile::fast_v2()],
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    // Replaces a profile with the same name
+    pub fn insert(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Profile> {
+        let index = self.profiles.iter().position(|p| p.name == name)?;
+        Some(self.profiles.remove(index))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.profiles.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.profiles.iter()
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    // Names must be unique
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let profiles: Profiles = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for (i, profile) in profiles.profiles.iter().enumerate() {
+            if profiles.profiles[..i]
+                .iter()
+                .any(|p| p.name == profile.name)
+            {
+                return Err(format!("Duplicate profile \"{}\"", profile.name));
+            }
+        }
+        Ok(profiles)
+    }
+
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(filename)
+            .map_err(|e| format!("Cannot read {}: {}", filename, e))?;
+        Profiles::from_json(&json)
+    }
+
+    pub fn write_file(&self, filename: &str) -> Result<(), String> {
+        std::fs::write(filename, self.to_json()?)
+            .map_err(|e| format!("Cannot write {}: {}", filename, e))
+    }
+}
@@ -0,0 +1,362 @@
+use crate::maze::{Compass, InitOptions, Location, Maze, Position, StartWall, Wall, Zone};
+use serde::{Deserialize, Serialize};
+
+/*
+    Messages from the robot to a host mirroring its map. Serializable with
+    serde, and with a compact byte encoding (encode / decode) for UART or
+    BLE. Coordinates are single bytes, so mazes up to 255x255.
+
+    Byte layout, first byte is the tag:
+      1 WallUpdate      x, y, compass << 2 | wall                 4 bytes
+      2 PositionUpdate  x, y, heading                             4 bytes
+      3 GoalReached     x, y, steps (u32 little endian)           7 bytes
+      4 Snapshot        width, height, goal x, goal y,
+                        start x, start y, start heading,
+                        Maze::packed_walls (w*h/2 rounded up),
+                        start wall option, zone count,
+                        per zone: min x, min y, max x, max y,
+                                  weight (u16 little endian),
+                        blocked flag, if 1 Maze::packed_blocked
+                                  (w*h/8 rounded up)  11 + w*h/2 + 6*zones
+    Compass: 0 N, 1 E, 2 S, 3 W. Wall: 0 absent, 1 present, 2 unexplored.
+    Start wall (InitOptions): 0 right, 1 left, 2 none.
+    A message does not know its own length on the wire; frame it.
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WallUpdate {
+    pub x: u8,
+    pub y: u8,
+    pub compass: Compass,
+    pub wall: Wall,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PositionUpdate {
+    pub x: u8,
+    pub y: u8,
+    pub dir: Compass,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GoalReached {
+    pub x: u8,
+    pub y: u8,
+    pub steps: u32,
+}
+
+// The whole map, e.g. when a host connects in the middle of a run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub maze: Maze,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Message {
+    WallUpdate(WallUpdate),
+    PositionUpdate(PositionUpdate),
+    GoalReached(GoalReached),
+    Snapshot(Snapshot),
+}
+
+const TAG_WALL: u8 = 1;
+const TAG_POSITION: u8 = 2;
+const TAG_GOAL: u8 = 3;
+const TAG_SNAPSHOT: u8 = 4;
+
+fn compass_code(compass: Compass) -> u8 {
+    match compass {
+        Compass::North => 0,
+        Compass::East => 1,
+        Compass::South => 2,
+        Compass::West => 3,
+    }
+}
+
+fn compass_from_code(code: u8) -> Result<Compass, String> {
+    Compass::iter()
+        .nth(code as usize)
+        .ok_or(format!("Invalid compass code {}", code))
+}
+
+fn wall_code(wall: Wall) -> u8 {
+    match wall {
+        Wall::Absent => 0,
+        Wall::Present => 1,
+        Wall::Unexplored => 2,
+    }
+}
+
+fn wall_from_code(code: u8) -> Result<Wall, String> {
+    match code {
+        0 => Ok(Wall::Absent),
+        1 => Ok(Wall::Present),
+        2 => Ok(Wall::Unexplored),
+        _ => Err(format!("Invalid wall code {}", code)),
+    }
+}
+
+fn start_wall_code(start_wall: StartWall) -> u8 {
+    match start_wall {
+        StartWall::Right => 0,
+        StartWall::Left => 1,
+        StartWall::None => 2,
+    }
+}
+
+fn start_wall_from_code(code: u8) -> Result<StartWall, String> {
+    match code {
+        0 => Ok(StartWall::Right),
+        1 => Ok(StartWall::Left),
+        2 => Ok(StartWall::None),
+        _ => Err(format!("Invalid start wall code {}", code)),
+    }
+}
+
+fn coordinate(value: usize) -> Result<u8, String> {
+    u8::try_from(value).map_err(|_| format!("Coordinate {} does not fit in a byte", value))
+}
+
+impl Message {
+    // Robot side helpers
+    pub fn wall(pos: Position, compass: Compass, wall: Wall) -> Result<Message, String> {
+        Ok(Message::WallUpdate(WallUpdate {
+            x: coordinate(pos.x)?,
+            y: coordinate(pos.y)?,
+            compass,
+            wall,
+        }))
+    }
+
+    pub fn position(location: Location) -> Result<Message, String> {
+        Ok(Message::PositionUpdate(PositionUpdate {
+            x: coordinate(location.pos.x)?,
+            y: coordinate(location.pos.y)?,
+            dir: location.dir,
+        }))
+    }
+
+    pub fn goal_reached(pos: Position, steps: u32) -> Result<Message, String> {
+        Ok(Message::GoalReached(GoalReached {
+            x: coordinate(pos.x)?,
+            y: coordinate(pos.y)?,
+            steps,
+        }))
+    }
+
+    // Wall updates for the known walls of one cell
+    pub fn cell_walls(maze: &Maze, pos: Position) -> Result<Vec<Message>, String> {
+        Compass::iter()
+            .map(|compass| (compass, maze.get(pos.y, pos.x, compass)))
+            .filter(|(_, wall)| *wall != Wall::Unexplored)
+            .map(|(compass, wall)| Message::wall(pos, compass, wall))
+            .collect()
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        Ok(match self {
+            Message::WallUpdate(u) => vec![
+                TAG_WALL,
+                u.x,
+                u.y,
+                compass_code(u.compass) << 2 | wall_code(u.wall),
+            ],
+            Message::PositionUpdate(u) => vec![TAG_POSITION, u.x, u.y, compass_code(u.dir)],
+            Message::GoalReached(g) => {
+                let mut bytes = vec![TAG_GOAL, g.x, g.y];
+                bytes.extend_from_slice(&g.steps.to_le_bytes());
+                bytes
+            }
+            Message::Snapshot(s) => {
+                let maze = &s.maze;
+                let (goal, start) = (maze.get_goal(), maze.get_start());
+                let mut bytes = vec![
+                    TAG_SNAPSHOT,
+                    coordinate(maze.get_width())?,
+                    coordinate(maze.get_height())?,
+                    coordinate(goal.x)?,
+                    coordinate(goal.y)?,
+                    coordinate(start.pos.x)?,
+                    coordinate(start.pos.y)?,
+                    compass_code(start.dir),
+                ];
+                bytes.extend(maze.packed_walls());
+                bytes.push(start_wall_code(maze.get_init_options().start_wall));
+                let zones = maze.zones();
+                bytes.push(
+                    u8::try_from(zones.len())
+                        .map_err(|_| format!("{} zones do not fit in a snapshot", zones.len()))?,
+                );
+                for zone in zones {
+                    bytes.extend([
+                        coordinate(zone.min.x)?,
+                        coordinate(zone.min.y)?,
+                        coordinate(zone.max.x)?,
+                        coordinate(zone.max.y)?,
+                    ]);
+                    bytes.extend(zone.weight.to_le_bytes());
+                }
+                let blocked = maze.packed_blocked();
+                bytes.push(u8::from(!blocked.is_empty()));
+                bytes.extend(blocked);
+                bytes
+            }
+        })
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Message, String> {
+        let expect = |len: usize| {
+            if bytes.len() == len {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Message {} must be {} bytes, got {}",
+                    bytes[0],
+                    len,
+                    bytes.len()
+                ))
+            }
+        };
+        match bytes.first() {
+            Some(&TAG_WALL) => {
+                expect(4)?;
+                Ok(Message::WallUpdate(WallUpdate {
+                    x: bytes[1],
+                    y: bytes[2],
+                    compass: compass_from_code(bytes[3] >> 2)?,
+                    wall: wall_from_code(bytes[3] & 3)?,
+                }))
+            }
+            Some(&TAG_POSITION) => {
+                expect(4)?;
+                Ok(Message::PositionUpdate(PositionUpdate {
+                    x: bytes[1],
+                    y: bytes[2],
+                    dir: compass_from_code(bytes[3])?,
+                }))
+            }
+            Some(&TAG_GOAL) => {
+                expect(7)?;
+                Ok(Message::GoalReached(GoalReached {
+                    x: bytes[1],
+                    y: bytes[2],
+                    steps: u32::from_le_bytes([bytes[3], bytes[4], bytes[5], bytes[6]]),
+                }))
+            }
+            Some(&TAG_SNAPSHOT) => {
+                if bytes.len() < 8 {
+                    return Err("Snapshot header is 8 bytes".to_string());
+                }
+                let (width, height) = (bytes[1] as usize, bytes[2] as usize);
+                if width == 0 || height == 0 {
+                    return Err(format!("Invalid maze size {}x{}", width, height));
+                }
+                let goal = Position::new(bytes[3] as usize, bytes[4] as usize);
+                let start = Position::new(bytes[5] as usize, bytes[6] as usize);
+                for (name, pos) in [("Goal", goal), ("Start", start)] {
+                    if pos.x >= width || pos.y >= height {
+                        return Err(format!(
+                            "{} ({}, {}) is outside the {}x{} maze",
+                            name, pos.x, pos.y, width, height
+                        ));
+                    }
+                }
+                let walls_end = 8 + (width * height).div_ceil(2);
+                if bytes.len() < walls_end + 3 {
+                    return Err(format!(
+                        "Snapshot of a {}x{} maze is at least {} bytes, got {}",
+                        width,
+                        height,
+                        walls_end + 3,
+                        bytes.len()
+                    ));
+                }
+                let mut maze = Maze::new(width, height);
+                maze.set_packed_walls(&bytes[8..walls_end])?;
+                maze.set_goal(goal);
+                maze.set_start_location(Location::new(start, compass_from_code(bytes[7])?));
+                maze.set_init_options(InitOptions {
+                    start_wall: start_wall_from_code(bytes[walls_end])?,
+                });
+                let zones_end = walls_end + 2 + bytes[walls_end + 1] as usize * 6;
+                if bytes.len() <= zones_end {
+                    return Err(format!(
+                        "Snapshot ends inside its {} zones",
+                        bytes[walls_end + 1]
+                    ));
+                }
+                let blocked = &bytes[zones_end + 1..];
+                match bytes[zones_end] {
+                    0 if blocked.is_empty() => (),
+                    1 if !blocked.is_empty() => maze.set_packed_blocked(blocked)?,
+                    flag => {
+                        return Err(format!(
+                            "Blocked flag {} with {} bytes of blocked cells",
+                            flag,
+                            blocked.len()
+                        ))
+                    }
+                }
+                for zone in bytes[walls_end + 2..zones_end].chunks(6) {
+                    maze.add_zone(Zone {
+                        min: Position::new(zone[0] as usize, zone[1] as usize),
+                        max: Position::new(zone[2] as usize, zone[3] as usize),
+                        weight: u16::from_le_bytes([zone[4], zone[5]]),
+                    })?;
+                }
+                Ok(Message::Snapshot(Snapshot { maze }))
+            }
+            Some(tag) => Err(format!("Unknown message tag {}", tag)),
+            None => Err("Empty message".to_string()),
+        }
+    }
+}
+
+/*
+    Host side: the robot's map and pose rebuilt from its messages. The
+    maze must have the robot's size until the first snapshot arrives.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mirror {
+    pub maze: Maze,
+    pub location: Location,
+    // Goal cell and step count of the last GoalReached
+    pub goal_reached: Option<(Position, u32)>,
+}
+
+impl Mirror {
+    pub fn new(maze: Maze) -> Self {
+        Mirror {
+            location: maze.get_start(),
+            maze,
+            goal_reached: None,
+        }
+    }
+
+    pub fn apply(&mut self, message: &Message) -> Result<(), String> {
+        let inside = |x: u8, y: u8| {
+            if (x as usize) < self.maze.get_width() && (y as usize) < self.maze.get_height() {
+                Ok(Position::new(x as usize, y as usize))
+            } else {
+                Err(format!("Cell ({}, {}) is outside the maze", x, y))
+            }
+        };
+        match message {
+            Message::WallUpdate(u) => {
+                let pos = inside(u.x, u.y)?;
+                self.maze.set(pos.y, pos.x, u.compass, u.wall);
+            }
+            Message::PositionUpdate(u) => {
+                self.location = Location::new(inside(u.x, u.y)?, u.dir);
+            }
+            Message::GoalReached(g) => {
+                self.goal_reached = Some((inside(g.x, g.y)?, g.steps));
+            }
+            Message::Snapshot(s) => self.maze = s.maze.clone(),
+        }
+        Ok(())
+    }
+
+    pub fn apply_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.apply(&Message::decode(bytes)?)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::maze::{Compass, Maze, Position};
+use serde::{Deserialize, Serialize};
+
+// When a wall was first observed: run number (counted by reset) and
+// the navigate step within that run
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Discovery {
+    pub run: u32,
+    pub step: u32,
+}
+
+/*
+    Where every known wall of the solver's map came from. Only built with
+    the provenance feature, since it costs four entries per cell.
+
+    Both sides of a wall carry the same entry; walls() reports each wall
+    once (north and east sides, plus the outer south and west walls).
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    run: u32,
+    step: u32,
+    width: usize,
+    height: usize,
+    walls: Vec<Option<Discovery>>,
+}
+
+fn compass_index(compass: Compass) -> usize {
+    match compass {
+        Compass::North => 0,
+        Compass::East => 1,
+        Compass::South => 2,
+        Compass::West => 3,
+    }
+}
+
+impl Provenance {
+    pub fn new(width: usize, height: usize) -> Self {
+        Provenance {
+            run: 0,
+            step: 0,
+            width,
+            height,
+            walls: vec![None; width * height * 4],
+        }
+    }
+
+    pub fn run(&self) -> u32 {
+        self.run
+    }
+
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    pub(crate) fn next_step(&mut self) {
+        self.step += 1;
+    }
+
+    pub(crate) fn next_run(&mut self) {
+        self.run += 1;
+        self.step = 0;
+    }
+
+    // Forget every wall, counters are kept
+    pub(crate) fn clear(&mut self) {
+        self.walls.iter_mut().for_each(|w| *w = None);
+    }
+
+    fn index(&self, pos: Position, compass: Compass) -> Option<usize> {
+        (pos.x < self.width && pos.y < self.height)
+            .then(|| (pos.y * self.width + pos.x) * 4 + compass_index(compass))
+    }
+
+    pub fn get(&self, pos: Position, compass: Compass) -> Option<Discovery> {
+        self.index(pos, compass).and_then(|i| self.walls[i])
+    }
+
+    // Note the wall as discovered now, unless it is already known
+    pub(crate) fn record(&mut self, maze: &Maze, pos: Position, compass: Compass) {
+        if self.width != maze.get_width() || self.height != maze.get_height() {
+            // Restored from data saved without the provenance feature
+            *self = Provenance {
+                run: self.run,
+                step: self.step,
+                ..Provenance::new(maze.get_width(), maze.get_height())
+            };
+        }
+        if self.get(pos, compass).is_some() {
+            return;
+        }
+        let discovery = Some(Discovery {
+            run: self.run,
+            step: self.step,
+        });
+        if let Some(i) = self.index(pos, compass) {
+            self.walls[i] = discovery;
+        }
+        if let Some((y, x)) = maze.get_neighbor_cell(pos.y, pos.x, compass) {
+            if let Some(i) = self.index(Position::new(x, y), compass.opposite()) {
+                self.walls[i] = discovery;
+            }
+        }
+    }
+
+    // Every discovered wall, once each
+    pub fn walls(&self) -> Vec<(Position, Compass, Discovery)> {
+        let mut walls = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Position::new(x, y);
+                for compass in Compass::iter() {
+                    let owned = match compass {
+                        Compass::North | Compass::East => true,
+                        Compass::South => y == 0,
+                        Compass::West => x == 0,
+                    };
+                    if let (true, Some(discovery)) = (owned, self.get(pos, compass)) {
+                        walls.push((pos, compass, discovery));
+                    }
+                }
+            }
+        }
+        walls
+    }
+
+    // Walls the given run added to the map
+    pub fn added_in(&self, run: u32) -> Vec<(Position, Compass)> {
+        self.walls()
+            .into_iter()
+            .filter(|(_, _, discovery)| discovery.run == run)
+            .map(|(pos, compass, _)| (pos, compass))
+            .collect()
+    }
+}
@@ -0,0 +1,243 @@
+use crate::adachi::Adachi;
+use crate::maze::{Compass, Location, Maze, Position, Wall};
+use crate::path_finder::PathFinder;
+
+/*
+    Renderer for live debugging. Layout is the same as display_step_map:
+
+    +---+---+
+    |  2   1| 1
+    +   +---+
+    |  3  0 | 0
+    +---+---+
+       0   1
+
+    With color enabled, step values are drawn as a heat map (green near
+    the goal, red far away), the robot cell and the goal cell get a
+    background color and unexplored walls ("???", "?") are drawn in dark gray.
+
+    Markers put a one character prefix in front of the step value:
+    robot "^ > v <" (heading), goal "G", visited cells ".".
+    +---+---+
+    |  2 >1 | 1
+    +   +---+
+    |  3 G0 | 0
+    +---+---+
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderOptions {
+    pub color: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Markers<'a> {
+    pub robot: bool,
+    pub goal: bool,
+    pub visited: &'a [Position],
+}
+
+impl Default for Markers<'_> {
+    fn default() -> Self {
+        Markers {
+            robot: true,
+            goal: true,
+            visited: &[],
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { color: true }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const UNEXPLORED: &str = "\x1b[90m";
+const ROBOT_BG: &str = "\x1b[44m";
+const GOAL_BG: &str = "\x1b[45m";
+
+fn paint(text: &str, style: &str, options: &RenderOptions) -> String {
+    if options.color && !style.is_empty() {
+        format!("{}{}{}", style, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+fn heat(step: u16, max: u16) -> &'static str {
+    let ratio = step as f32 / max.max(1) as f32;
+    if ratio < 0.25 {
+        "\x1b[32m"
+    } else if ratio < 0.5 {
+        "\x1b[36m"
+    } else if ratio < 0.75 {
+        "\x1b[33m"
+    } else {
+        "\x1b[31m"
+    }
+}
+
+// Draw the maze with a 3-character content and a style for each cell
+pub fn draw(
+    maze: &Maze,
+    options: &RenderOptions,
+    cell: impl Fn(Position) -> (String, String),
+) -> String {
+    let (width, height) = (maze.get_width(), maze.get_height());
+    let horizontal = |wall: Wall| match wall {
+        Wall::Absent => "   ".to_string(),
+        Wall::Present => "---".to_string(),
+        Wall::Unexplored => paint("???", UNEXPLORED, options),
+    };
+    let vertical = |wall: Wall| match wall {
+        Wall::Absent => " ".to_string(),
+        Wall::Present => "|".to_string(),
+        Wall::Unexplored => paint("?", UNEXPLORED, options),
+    };
+
+    let mut lines = vec![];
+    for y in (0..height).rev() {
+        let mut line = String::new();
+        for x in 0..width {
+            line += "+";
+            line += &horizontal(maze.get(y, x, Compass::North));
+        }
+        line += "+";
+        lines.push(line);
+
+        let mut line = String::new();
+        for x in 0..width {
+            line += &vertical(maze.get(y, x, Compass::West));
+            let (content, style) = if maze.cell_exists(y, x) {
+                cell(Position::new(x, y))
+            } else {
+                ("###".to_string(), String::new())
+            };
+            line += &paint(&content, &style, options);
+        }
+        line += &vertical(maze.get(y, width - 1, Compass::East));
+        line += &format!(" {}", y);
+        lines.push(line);
+    }
+    let mut line = String::new();
+    for x in 0..width {
+        line += "+";
+        line += &horizontal(maze.get(0, x, Compass::South));
+    }
+    line += "+";
+    lines.push(line);
+    lines.push((0..width).map(|x| format!(" {:3}", x)).collect());
+    lines.join("\n")
+}
+
+// Maze only: goal highlighted, unexplored walls grayed out
+pub fn render_maze(maze: &Maze, options: &RenderOptions) -> String {
+    draw(maze, options, |pos| {
+        if pos == maze.get_goal() {
+            (" G ".to_string(), GOAL_BG.to_string())
+        } else {
+            ("   ".to_string(), String::new())
+        }
+    })
+}
+
+// Maze with a route drawn through it. Each visited cell shows the heading
+// of the last visit, the final location is highlighted like the robot.
+pub fn render_trace(maze: &Maze, trace: &[Location], options: &RenderOptions) -> String {
+    let last = trace.last().map(|location| location.pos);
+    draw(maze, options, |pos| {
+        let visit = trace.iter().rev().find(|location| location.pos == pos);
+        let content = match visit {
+            Some(location) => format!(" {} ", location.dir.to_arrow()),
+            None if pos == maze.get_goal() => " G ".to_string(),
+            None => "   ".to_string(),
+        };
+        let style = if Some(pos) == last {
+            ROBOT_BG
+        } else if pos == maze.get_goal() {
+            GOAL_BG
+        } else {
+            ""
+        };
+        (content, style.to_string())
+    })
+}
+
+// Step value with a one character marker in front
+fn mark(marker: char, step: u16) -> String {
+    if step < 100 {
+        format!("{}{:2}", marker, step)
+    } else {
+        format!(" {} ", marker)
+    }
+}
+
+// Step map of the solver (calc_step_map or navigate must have been called)
+pub fn render_step_map(solver: &Adachi, options: &RenderOptions, markers: &Markers) -> String {
+    let maze = solver.get_maze();
+    let mut max = 0;
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            let step = solver.get_step(x, y);
+            if step != Adachi::NONE {
+                max = max.max(step);
+            }
+        }
+    }
+    let robot = solver.get_location();
+    draw(maze, options, |pos| {
+        let step = solver.get_step(pos.x, pos.y);
+        let content = if markers.robot && pos == robot.pos {
+            mark(robot.dir.to_arrow(), step)
+        } else if markers.goal && pos == maze.get_goal() {
+            mark('G', step)
+        } else if markers.visited.contains(&pos) {
+            mark('.', step)
+        } else if step == Adachi::NONE {
+            "   ".to_string()
+        } else {
+            format!("{:3}", step)
+        };
+        let style = if pos == robot.pos {
+            format!("{}{}", ROBOT_BG, heat(step, max))
+        } else if pos == maze.get_goal() {
+            format!("{}{}", GOAL_BG, heat(step, max))
+        } else if step == Adachi::NONE {
+            String::new()
+        } else {
+            heat(step, max).to_string()
+        };
+        (content, style)
+    })
+}
+
+// Adachi::frontier_distance_map: distance to the nearest unexplored wall,
+// heat colored like the step map ("  ." on frontier cells)
+pub fn render_frontier_distance(solver: &Adachi, options: &RenderOptions) -> String {
+    let map = solver.frontier_distance_map();
+    let max = map
+        .iter()
+        .flatten()
+        .filter(|d| **d != Adachi::NONE)
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let robot = solver.get_location();
+    draw(solver.get_maze(), options, |pos| {
+        let distance = map[pos.y][pos.x];
+        let content = match distance {
+            0 => "  .".to_string(),
+            Adachi::NONE => "   ".to_string(),
+            d => format!("{:3}", d),
+        };
+        let style = if pos == robot.pos {
+            format!("{}{}", ROBOT_BG, heat(distance, max))
+        } else if distance == Adachi::NONE {
+            String::new()
+        } else {
+            heat(distance, max).to_string()
+        };
+        (content, style)
+    })
+}
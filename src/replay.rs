@@ -0,0 +1,276 @@
+use crate::adachi::Adachi;
+use crate::maze::{Direction, Location, Maze, Position, Wall};
+use crate::path_finder::{
+    MoveFailure, NavigationStep, Observation, PathFinder, Result as NavigationResult,
+};
+use crate::simulator::{FaultConfig, Simulator};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+pub const REPLAY_VERSION: u32 = 1;
+const HEADER: &str = "mm_maze replay";
+
+// Everything the simulator told the finder and what the finder answered
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ReplayEvent {
+    Decision {
+        location: Location,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        // None at the goal or on an error
+        direction: Option<Direction>,
+        error: Option<String>,
+    },
+    // The finder was put on `location` after a move
+    Moved(Location),
+    // A move failed, the finder ended up on `location`
+    MoveFailed(Location),
+    // Simulated restart (FaultConfig::restart)
+    Reset,
+}
+
+/*
+    A simulated search that can be reproduced exactly: the real maze, the
+    solver state before the run (configuration included, callbacks are not
+    saved), the fault injection settings with their RNG seed and every
+    event of the run. File format:
+
+      mm_maze replay 1
+      {"maze":{...},"goal":{...},"faults":null,"max_steps":1000,...}
+
+    The first line is the format version; files of another version are
+    rejected.
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub maze: Maze,
+    pub goal: Position,
+    pub faults: Option<FaultConfig>,
+    pub max_steps: usize,
+    pub solver: serde_json::Value,
+    pub events: Vec<ReplayEvent>,
+    // Simulator::run result: steps taken or the error
+    pub result: Result<usize, String>,
+}
+
+// Why a replay could not be loaded or played back
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayError {
+    Format(String),
+    Version(u32),
+    // First event that differs; None when one run is shorter
+    Diverged {
+        index: usize,
+        recorded: Option<ReplayEvent>,
+        replayed: Option<ReplayEvent>,
+    },
+    // Same events, different outcome
+    Result {
+        recorded: Result<usize, String>,
+        replayed: Result<usize, String>,
+    },
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReplayError::Format(e) => write!(f, "Invalid replay: {}", e),
+            ReplayError::Version(version) => write!(
+                f,
+                "Replay version {} is not supported (expected {})",
+                version, REPLAY_VERSION
+            ),
+            ReplayError::Diverged {
+                index,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "Diverged at event {}: recorded {:?}, replayed {:?}",
+                index, recorded, replayed
+            ),
+            ReplayError::Result { recorded, replayed } => write!(
+                f,
+                "Different result: recorded {:?}, replayed {:?}",
+                recorded, replayed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// PathFinder wrapper logging the events of a run
+struct Recorder<'a> {
+    solver: &'a mut Adachi,
+    events: Vec<ReplayEvent>,
+}
+
+impl PathFinder for Recorder<'_> {
+    fn navigate_step(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        goal: Position,
+    ) -> NavigationResult<NavigationStep> {
+        self.navigate_step_observed(&Observation::new(front, left, right), goal)
+    }
+
+    fn navigate_step_observed(
+        &mut self,
+        observation: &Observation,
+        goal: Position,
+    ) -> NavigationResult<NavigationStep> {
+        let location = self.solver.get_location();
+        let result = self.solver.navigate_step_observed(observation, goal);
+        self.events.push(ReplayEvent::Decision {
+            location,
+            front: observation.front,
+            left: observation.left,
+            right: observation.right,
+            direction: result.as_ref().ok().and_then(|step| step.direction),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+        result
+    }
+
+    fn get_location(&self) -> Location {
+        self.solver.get_location()
+    }
+
+    fn set_location(&mut self, location: Location) {
+        self.events.push(ReplayEvent::Moved(location));
+        self.solver.set_location(location);
+    }
+
+    fn get_maze(&self) -> &Maze {
+        self.solver.get_maze()
+    }
+
+    fn reset(&mut self, keep_map: bool) {
+        self.events.push(ReplayEvent::Reset);
+        self.solver.reset(keep_map);
+    }
+
+    fn report_move_failure(&mut self, failure: MoveFailure) {
+        self.solver.report_move_failure(failure);
+        self.events
+            .push(ReplayEvent::MoveFailed(self.solver.get_location()));
+    }
+}
+
+fn run(
+    maze: &Maze,
+    solver: &mut Adachi,
+    goal: Position,
+    faults: Option<FaultConfig>,
+    max_steps: usize,
+) -> (Vec<ReplayEvent>, Result<usize, String>) {
+    let mut sim = Simulator::new(maze.clone());
+    sim.set_faults(faults);
+    let mut recorder = Recorder {
+        solver,
+        events: vec![],
+    };
+    let result = sim.run(&mut recorder, goal, max_steps);
+    (recorder.events, result)
+}
+
+impl Replay {
+    // Run `solver` through `maze` like Simulator::run and keep everything
+    // needed to run it again. Returns the solver after the run.
+    pub fn record(
+        maze: &Maze,
+        mut solver: Adachi,
+        goal: Position,
+        faults: Option<FaultConfig>,
+        max_steps: usize,
+    ) -> Result<(Replay, Adachi), String> {
+        let state = serde_json::to_value(&solver).map_err(|e| e.to_string())?;
+        let (events, result) = run(maze, &mut solver, goal, faults, max_steps);
+        let replay = Replay {
+            maze: maze.clone(),
+            goal,
+            faults,
+            max_steps,
+            solver: state,
+            events,
+            result,
+        };
+        Ok((replay, solver))
+    }
+
+    // Solver as it was before the recorded run
+    pub fn initial_solver(&self) -> Result<Adachi, ReplayError> {
+        serde_json::from_value(self.solver.clone()).map_err(|e| ReplayError::Format(e.to_string()))
+    }
+
+    // Run the recorded search again and check that every event matches.
+    // Returns the solver after the run.
+    pub fn play(&self) -> Result<Adachi, ReplayError> {
+        let mut solver = self.initial_solver()?;
+        let (events, result) = run(
+            &self.maze,
+            &mut solver,
+            self.goal,
+            self.faults,
+            self.max_steps,
+        );
+        let length = events.len().max(self.events.len());
+        if let Some(index) = (0..length).find(|i| events.get(*i) != self.events.get(*i)) {
+            return Err(ReplayError::Diverged {
+                index,
+                recorded: self.events.get(index).cloned(),
+                replayed: events.get(index).cloned(),
+            });
+        }
+        if result != self.result {
+            return Err(ReplayError::Result {
+                recorded: self.result.clone(),
+                replayed: result,
+            });
+        }
+        Ok(solver)
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        writeln!(writer, "{} {}", HEADER, REPLAY_VERSION)
+            .and_then(|_| writeln!(writer, "{}", json))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn read(mut reader: impl BufRead) -> Result<Replay, ReplayError> {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|e| ReplayError::Format(e.to_string()))?;
+        let version = header
+            .trim()
+            .strip_prefix(HEADER)
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or_else(|| ReplayError::Format(format!("Bad header \"{}\"", header.trim())))?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::Version(version));
+        }
+        serde_json::from_reader(reader).map_err(|e| ReplayError::Format(e.to_string()))
+    }
+
+    pub fn write_file(&self, filename: &str) -> Result<(), String> {
+        let file = std::fs::File::create(filename)
+            .map_err(|e| format!("Cannot create {}: {}", filename, e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.write(&mut writer)?;
+        writer
+            .flush()
+            .map_err(|e| format!("Cannot write {}: {}", filename, e))
+    }
+
+    pub fn from_file(filename: &str) -> Result<Replay, ReplayError> {
+        let file = std::fs::File::open(filename)
+            .map_err(|e| ReplayError::Format(format!("Cannot open {}: {}", filename, e)))?;
+        Replay::read(std::io::BufReader::new(file))
+    }
+}
@@ -0,0 +1,213 @@
+use crate::adachi::Adachi;
+use crate::fast_run::{fastest_run, TimeModel};
+use crate::maze::{Compass, Location, Maze, Position};
+use crate::path_finder::PathFinder;
+use crate::profiles::Profile;
+use crate::simulator::Simulator;
+
+/*
+    Contest rules for scoring a mouse. The score of a run is
+
+        run time + search_time_factor * time since the first start
+                 + touch_penalty * operator touches
+
+    and the contest score is the best score of the finished runs that
+    started within the time limit and the run limit. Lower is better.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoringRules {
+    pub search_time_factor: f32,
+    // Seconds per touch of the mouse by the operator
+    pub touch_penalty: f32,
+    pub max_runs: usize,
+    // Seconds from the first start
+    pub time_limit: f32,
+}
+
+impl ScoringRules {
+    // Classic 16x16: 1/30 of the elapsed time, 3 s per touch,
+    // 5 runs in 10 minutes
+    pub fn classic() -> Self {
+        ScoringRules {
+            search_time_factor: 1.0 / 30.0,
+            touch_penalty: 3.0,
+            max_runs: 5,
+            time_limit: 600.0,
+        }
+    }
+
+    // Only the best run time counts
+    pub fn run_time_only() -> Self {
+        ScoringRules {
+            search_time_factor: 0.0,
+            touch_penalty: 0.0,
+            max_runs: usize::MAX,
+            time_limit: f32::INFINITY,
+        }
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        ScoringRules::classic()
+    }
+}
+
+// One run from the start cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunRecord {
+    // Seconds since the first start when this run started
+    pub start_time: f32,
+    // Start to goal, None if the goal was not reached
+    pub run_time: Option<f32>,
+    pub touches: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    // Score of every run, None for runs that do not count
+    pub run_scores: Vec<Option<f32>>,
+    // Index of the best run
+    pub best_run: Option<usize>,
+    pub score: Option<f32>,
+}
+
+pub fn score(runs: &[RunRecord], rules: &ScoringRules) -> Score {
+    let run_scores: Vec<Option<f32>> = runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| {
+            if i >= rules.max_runs || run.start_time > rules.time_limit {
+                return None;
+            }
+            let penalty = rules.search_time_factor * run.start_time
+                + rules.touch_penalty * run.touches as f32;
+            run.run_time.map(|time| time + penalty)
+        })
+        .collect();
+    let best_run = run_scores
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| s.map(|s| (i, s)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i);
+    Score {
+        best_run,
+        score: best_run.and_then(|i| run_scores[i]),
+        run_scores,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContestResult {
+    pub runs: Vec<RunRecord>,
+    pub score: Score,
+}
+
+// Time of one simulated step at search speed
+fn step_time(before: Location, after: Location, model: &TimeModel) -> f32 {
+    let cell = model.straight_time(1.0, false);
+    let turn = if after.dir == before.dir {
+        0.0
+    } else if after.dir == before.dir.opposite() {
+        2.0 * model.turn90
+    } else {
+        model.turn90
+    };
+    if after.pos == before.pos {
+        // Failed move or turn in place
+        turn.max(model.turn90)
+    } else {
+        cell + turn
+    }
+}
+
+// Simulate from the solver location to `goal`, returning the seconds taken
+fn timed_search(
+    sim: &mut Simulator,
+    solver: &mut Adachi,
+    goal: Position,
+    model: &TimeModel,
+    max_steps: usize,
+) -> Result<f32, String> {
+    sim.set_location(solver.get_location());
+    let mut time = 0.0;
+    let mut steps = 0;
+    while sim.get_location().pos != goal {
+        if steps >= max_steps {
+            return Err(format!("Step limit {} reached", max_steps));
+        }
+        steps += 1;
+        let before = solver.get_location();
+        sim.tick(solver, goal)?;
+        time += step_time(before, solver.get_location(), model);
+    }
+    Ok(time)
+}
+
+/*
+    Contest of one strategy in `maze`, no touches:
+    1. search run from the start to the goal (a scored run), then the
+       search continues back to the start
+    2. `fast_runs` fast runs over the learned map (fast_run::fastest_run
+       with the `fast` profile), each followed by a return at the same speed;
+       a run that finds no route takes no time
+    Runs that would start after the time limit are not made. Search moves
+    are timed with the `search` profile, one cell plus its turn per step.
+*/
+pub fn simulate_contest(
+    maze: &Maze,
+    mut solver: Adachi,
+    rules: &ScoringRules,
+    search: &Profile,
+    fast: &Profile,
+    fast_runs: usize,
+) -> Result<ContestResult, String> {
+    let (search_model, fast_model) = (search.time_model(), fast.time_model());
+    let max_steps = 16 * maze.get_width() * maze.get_height();
+    let start = maze.get_start();
+    let goal = maze.get_goal();
+    let mut sim = Simulator::new(maze.clone());
+
+    let to_goal = timed_search(&mut sim, &mut solver, goal, &search_model, max_steps)?;
+    let back = timed_search(&mut sim, &mut solver, start.pos, &search_model, max_steps)?;
+    let mut runs = vec![RunRecord {
+        start_time: 0.0,
+        run_time: Some(to_goal),
+        touches: 0,
+    }];
+    let mut elapsed = to_goal + back;
+
+    let learned = solver.get_maze();
+    let run_time = fastest_run(learned, start, goal, &fast_model).map(|run| run.time);
+    // The mouse can leave the goal in any heading and the run search
+    // never makes U-turns, so take the best of all four
+    let return_time = Compass::iter()
+        .filter_map(|heading| {
+            fastest_run(
+                learned,
+                Location::new(goal, heading),
+                start.pos,
+                &fast_model,
+            )
+        })
+        .map(|run| run.time)
+        .min_by(f32::total_cmp)
+        .unwrap_or(back);
+    for _ in 0..fast_runs.min(rules.max_runs.saturating_sub(1)) {
+        if elapsed > rules.time_limit {
+            break;
+        }
+        runs.push(RunRecord {
+            start_time: elapsed,
+            run_time,
+            touches: 0,
+        });
+        // Without a route the mouse stays at the start
+        elapsed += run_time.map_or(0.0, |time| time + return_time);
+    }
+    Ok(ContestResult {
+        score: score(&runs, rules),
+        runs,
+    })
+}
@@ -0,0 +1,91 @@
+use crate::maze::{CellWalls, Compass, Location, Maze, Position, Wall, WallChange};
+use std::sync::{mpsc, Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/*
+    A Maze behind Arc<RwLock>, for a telemetry thread writing walls while
+    other threads read them. Clones share the same maze. Each method takes
+    the lock for its own duration only; use read() / write() to keep it
+    over several calls.
+
+    A panic while the lock is held does not leave a half-written maze (every
+    write is a single wall), so a poisoned lock is used as is.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct SharedMaze {
+    inner: Arc<RwLock<Maze>>,
+}
+
+impl SharedMaze {
+    pub fn new(maze: Maze) -> Self {
+        SharedMaze {
+            inner: Arc::new(RwLock::new(maze)),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Maze> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, Maze> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Copy of the current state
+    pub fn snapshot(&self) -> Maze {
+        self.read().clone()
+    }
+
+    pub fn get(&self, y: usize, x: usize, compass: Compass) -> Wall {
+        self.read().get(y, x, compass)
+    }
+
+    pub fn set(&self, y: usize, x: usize, compass: Compass, wall: Wall) {
+        self.write().set(y, x, compass, wall);
+    }
+
+    pub fn cell_walls(&self, y: usize, x: usize) -> CellWalls {
+        self.read().cell_walls(y, x)
+    }
+
+    pub fn set_cell_walls(&self, y: usize, x: usize, walls: CellWalls) {
+        self.write().set_cell_walls(y, x, walls);
+    }
+
+    pub fn merge(&self, other: &Maze) -> Result<usize, String> {
+        self.write().merge(other)
+    }
+
+    pub fn get_goal(&self) -> Position {
+        self.read().get_goal()
+    }
+
+    pub fn set_goal(&self, pos: Position) {
+        self.write().set_goal(pos);
+    }
+
+    pub fn get_start(&self) -> Location {
+        self.read().get_start()
+    }
+
+    pub fn set_start(&self, start: Location) -> Result<(), String> {
+        self.write().set_start(start)
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.read().get_width()
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.read().get_height()
+    }
+
+    pub fn subscribe(&self) -> mpsc::Receiver<WallChange> {
+        self.write().subscribe()
+    }
+}
+
+impl From<Maze> for SharedMaze {
+    fn from(maze: Maze) -> Self {
+        SharedMaze::new(maze)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::graph::MazeGraph;
+use crate::maze::StepMapMode;
+use crate::maze::{Maze, Position};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Number of distinct shortest cell paths from start to goal (saturating)
+pub fn count_shortest_paths(
+    maze: &Maze,
+    start: Position,
+    goal: Position,
+    mode: StepMapMode,
+) -> u64 {
+    let graph = maze.to_graph(mode);
+    let dist = graph.distances_from(graph.node(start));
+    if dist[graph.node(goal)].is_none() {
+        return 0;
+    }
+
+    // Count in order of increasing distance from the start
+    let mut order: Vec<usize> = (0..graph.node_count())
+        .filter(|n| dist[*n].is_some())
+        .collect();
+    order.sort_by_key(|n| dist[*n]);
+    let mut count = vec![0u64; graph.node_count()];
+    count[graph.node(start)] = 1;
+    for node in order {
+        for (next, cost) in graph.neighbors(node) {
+            if dist[node].map(|d| d + *cost as u32) == dist[*next] {
+                count[*next] = count[*next].saturating_add(count[node]);
+            }
+        }
+    }
+    count[graph.node(goal)]
+}
+
+// Cells lying on at least one shortest start-goal path (both ends
+// included): d_start + d_goal == d_total. Empty when the goal cannot be
+// reached. Row by row from (0, 0).
+pub fn cells_on_shortest_paths(
+    maze: &Maze,
+    start: Position,
+    goal: Position,
+    mode: StepMapMode,
+) -> Vec<Position> {
+    let graph = maze.to_graph(mode);
+    let from_start = graph.distances_from(graph.node(start));
+    let from_goal = graph.distances_from(graph.node(goal));
+    let Some(total) = from_start[graph.node(goal)] else {
+        return vec![];
+    };
+    (0..graph.node_count())
+        .filter(|n| match (from_start[*n], from_goal[*n]) {
+            (Some(a), Some(b)) => a + b == total,
+            _ => false,
+        })
+        .map(|n| graph.position(n))
+        .collect()
+}
+
+// Up to `limit` shortest cell paths from start to goal (both included)
+pub fn enumerate_shortest_paths(
+    maze: &Maze,
+    start: Position,
+    goal: Position,
+    mode: StepMapMode,
+    limit: usize,
+) -> Vec<Vec<Position>> {
+    let graph = maze.to_graph(mode);
+    let to_goal = graph.distances_from(graph.node(goal));
+    let mut paths = vec![];
+    if to_goal[graph.node(start)].is_none() || limit == 0 {
+        return paths;
+    }
+    let mut path = vec![graph.node(start)];
+    descend(
+        &graph,
+        &to_goal,
+        graph.node(goal),
+        &mut path,
+        &mut paths,
+        limit,
+    );
+    paths
+}
+
+// Depth-first walk that only follows edges on a shortest path to the goal
+fn descend(
+    graph: &MazeGraph,
+    to_goal: &[Option<u32>],
+    goal: usize,
+    path: &mut Vec<usize>,
+    paths: &mut Vec<Vec<Position>>,
+    limit: usize,
+) {
+    let node = *path.last().unwrap();
+    if node == goal {
+        paths.push(path.iter().map(|n| graph.position(*n)).collect());
+        return;
+    }
+    for (next, cost) in graph.neighbors(node) {
+        if paths.len() >= limit {
+            return;
+        }
+        if to_goal[*next].map(|d| d + *cost as u32) == to_goal[node] {
+            path.push(*next);
+            descend(graph, to_goal, goal, path, paths, limit);
+            path.pop();
+        }
+    }
+}
+
+// Yen's algorithm: the k cheapest loopless paths from start to goal,
+// cheapest first, as (cost, cells). Costs follow StepMapMode::cost, so
+// UnexploredWithPenalty gives weighted results.
+pub fn k_shortest_paths(
+    maze: &Maze,
+    start: Position,
+    goal: Position,
+    mode: StepMapMode,
+    k: usize,
+) -> Vec<(u32, Vec<Position>)> {
+    let graph = maze.to_graph(mode);
+    let (src, dst) = (graph.node(start), graph.node(goal));
+    let no_nodes = vec![false; graph.node_count()];
+    let mut found: Vec<(u32, Vec<usize>)> = vec![];
+    let mut candidates: Vec<(u32, Vec<usize>)> = vec![];
+
+    match dijkstra_path(&graph, src, dst, &no_nodes, &[]) {
+        Some(first) if k > 0 => found.push(first),
+        _ => return vec![],
+    }
+
+    while found.len() < k {
+        let previous = found.last().unwrap().1.clone();
+        for i in 0..previous.len() - 1 {
+            let root = &previous[..=i];
+            let spur = previous[i];
+
+            // Do not repeat an edge already used after the same root
+            let removed_edges: Vec<(usize, usize)> = found
+                .iter()
+                .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                .map(|(_, p)| (p[i], p[i + 1]))
+                .collect();
+            // Keep the path loopless
+            let mut removed_nodes = no_nodes.clone();
+            for node in &root[..i] {
+                removed_nodes[*node] = true;
+            }
+
+            if let Some((spur_cost, spur_path)) =
+                dijkstra_path(&graph, spur, dst, &removed_nodes, &removed_edges)
+            {
+                let mut path = root[..i].to_vec();
+                path.extend(spur_path);
+                let cost = path_cost(&graph, &root[..=i]) + spur_cost;
+                let known = found
+                    .iter()
+                    .chain(candidates.iter())
+                    .any(|(_, p)| *p == path);
+                if !known {
+                    candidates.push((cost, path));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            break;
+        }
+        let best = (0..candidates.len())
+            .min_by_key(|i| (candidates[*i].0, candidates[*i].1.len()))
+            .unwrap();
+        found.push(candidates.swap_remove(best));
+    }
+
+    found
+        .into_iter()
+        .map(|(cost, path)| (cost, path.iter().map(|n| graph.position(*n)).collect()))
+        .collect()
+}
+
+fn path_cost(graph: &MazeGraph, path: &[usize]) -> u32 {
+    path.windows(2)
+        .map(|w| {
+            graph
+                .neighbors(w[0])
+                .iter()
+                .find(|(n, _)| *n == w[1])
+                .map_or(0, |(_, c)| *c as u32)
+        })
+        .sum()
+}
+
+fn dijkstra_path(
+    graph: &MazeGraph,
+    src: usize,
+    dst: usize,
+    removed_nodes: &[bool],
+    removed_edges: &[(usize, usize)],
+) -> Option<(u32, Vec<usize>)> {
+    let mut dist: Vec<Option<u32>> = vec![None; graph.node_count()];
+    let mut prev: Vec<Option<usize>> = vec![None; graph.node_count()];
+    let mut queue = BinaryHeap::new();
+    dist[src] = Some(0);
+    queue.push(Reverse((0u32, src)));
+    while let Some(Reverse((d, node))) = queue.pop() {
+        if node == dst {
+            break;
+        }
+        if dist[node].is_some_and(|best| best < d) {
+            continue;
+        }
+        for (next, cost) in graph.neighbors(node) {
+            if removed_nodes[*next] || removed_edges.contains(&(node, *next)) {
+                continue;
+            }
+            let nd = d + *cost as u32;
+            if dist[*next].is_none_or(|best| nd < best) {
+                dist[*next] = Some(nd);
+                prev[*next] = Some(node);
+                queue.push(Reverse((nd, *next)));
+            }
+        }
+    }
+
+    let cost = dist[dst]?;
+    let mut path = vec![dst];
+    while let Some(p) = prev[*path.last().unwrap()] {
+        path.push(p);
+    }
+    path.reverse();
+    Some((cost, path))
+}
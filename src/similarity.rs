@@ -0,0 +1,108 @@
+use crate::adachi::{Adachi, StepMapMode};
+use crate::maze::{Compass, Maze, Position, Wall};
+use crate::wall_list::{Orientation, WallRecord};
+
+// Comparison between two mazes of the same size, e.g. a robot's map
+// against the official maze.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Similarity {
+    // Fraction of interior walls with the same state (0.0 - 1.0)
+    pub wall_agreement: f64,
+    // Shortest start-goal length of `a` minus that of `b`.
+    // None when either maze has no known route.
+    pub path_length_difference: Option<i32>,
+    pub dead_end_difference: i32,
+    pub wall_count_difference: i32,
+}
+
+pub fn compare(a: &Maze, b: &Maze) -> Result<Similarity, String> {
+    if a.get_width() != b.get_width() || a.get_height() != b.get_height() {
+        return Err(format!(
+            "Maze sizes differ: {}x{} and {}x{}",
+            a.get_width(),
+            a.get_height(),
+            b.get_width(),
+            b.get_height()
+        ));
+    }
+    let path_length_difference = match (shortest_path_length(a), shortest_path_length(b)) {
+        (Some(la), Some(lb)) => Some(la as i32 - lb as i32),
+        _ => None,
+    };
+    Ok(Similarity {
+        wall_agreement: wall_agreement(a, b)?,
+        path_length_difference,
+        dead_end_difference: dead_end_count(a) as i32 - dead_end_count(b) as i32,
+        wall_count_difference: wall_count(a) as i32 - wall_count(b) as i32,
+    })
+}
+
+fn interior_walls(maze: &Maze) -> Vec<WallRecord> {
+    let (width, height) = (maze.get_width(), maze.get_height());
+    maze.to_wall_list()
+        .into_iter()
+        .filter(|r| match r.orientation {
+            Orientation::Horizontal => r.y != 0 && r.y != height,
+            Orientation::Vertical => r.x != 0 && r.x != width,
+            Orientation::Blocked => false,
+        })
+        .collect()
+}
+
+// Fraction of interior walls whose state matches in both mazes
+pub fn wall_agreement(a: &Maze, b: &Maze) -> Result<f64, String> {
+    if a.get_width() != b.get_width() || a.get_height() != b.get_height() {
+        return Err("Maze sizes differ".to_string());
+    }
+    let walls_a = interior_walls(a);
+    let walls_b = interior_walls(b);
+    if walls_a.is_empty() {
+        return Ok(1.0);
+    }
+    let matching = walls_a
+        .iter()
+        .zip(walls_b.iter())
+        .filter(|(wa, wb)| wa.state == wb.state)
+        .count();
+    Ok(matching as f64 / walls_a.len() as f64)
+}
+
+// Number of steps from the start to the goal through known walls only
+pub fn shortest_path_length(maze: &Maze) -> Option<u16> {
+    let mut solver = Adachi::new(maze.clone());
+    solver.set_mode(StepMapMode::UnexploredAsPresent);
+    solver.calc_step_map(maze.get_goal());
+    let start = maze.get_start().pos;
+    let step = solver.get_step(start.x, start.y);
+    if step == Adachi::NONE {
+        None
+    } else {
+        Some(step)
+    }
+}
+
+// Cells surrounded by walls on three sides, excluding the start
+pub fn dead_end_count(maze: &Maze) -> usize {
+    let mut count = 0;
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            if Position::new(x, y) == maze.get_start().pos {
+                continue;
+            }
+            let walls = Compass::iter()
+                .filter(|c| maze.get(y, x, *c) == Wall::Present)
+                .count();
+            if walls == 3 {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+pub fn wall_count(maze: &Maze) -> usize {
+    interior_walls(maze)
+        .iter()
+        .filter(|r| r.state == Wall::Present)
+        .count()
+}
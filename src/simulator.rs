@@ -0,0 +1,368 @@
+use crate::maze::{Direction, Location, Maze, Position, Wall};
+use crate::path_finder::{MoveFailure, Observation, PathFinder, RangeReading};
+use crate::rng::XorShift64;
+use serde::{Deserialize, Serialize};
+
+/*
+    Drives a PathFinder through a known maze: the simulator senses the walls
+    around the simulated mouse, asks the finder for a direction and moves.
+
+    The long-range sensor (range > 0) looks up to `range` walls ahead,
+    e.g. with range 3 on an open corridor the reading is
+    RangeReading { open_cells: 3, wall: false }.
+
+    Fault injection (set_faults) makes run() misbehave like a real mouse.
+    Every probability is per step:
+    - missed move: the mouse stays put, the finder gets MoveFailure::Aborted
+    - spurious turn: the mouse turns in place instead of moving, the finder
+      gets MoveFailure::Displaced with the real location
+    - delayed walls: the wall update does not arrive in time, the finder
+      sees only unexplored walls this step (it gets them on a later visit)
+    - restart: the mouse is put back on the start, finder.reset(true)
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct FaultConfig {
+    pub missed_move: f64,
+    pub spurious_turn: f64,
+    pub delayed_walls: f64,
+    pub restart: f64,
+    pub seed: u64,
+}
+
+// How many faults were injected
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct FaultLog {
+    pub missed_moves: usize,
+    pub spurious_turns: usize,
+    pub delayed_walls: usize,
+    pub restarts: usize,
+}
+
+// Serializable, fault RNG state included, to checkpoint a long run
+// (see checkpoint::Simulation)
+#[derive(Serialize, Deserialize)]
+pub struct Simulator {
+    maze: Maze,
+    location: Location,
+    range: usize,
+    faults: Option<(FaultConfig, XorShift64)>,
+    fault_log: FaultLog,
+}
+
+impl Simulator {
+    pub fn new(maze: Maze) -> Self {
+        Simulator {
+            location: maze.get_start(),
+            maze,
+            range: 0,
+            faults: None,
+            fault_log: FaultLog::default(),
+        }
+    }
+
+    // None disables fault injection. Same seed, same faults.
+    pub fn set_faults(&mut self, faults: Option<FaultConfig>) {
+        self.faults = faults.map(|config| (config, XorShift64::new(config.seed)));
+        self.fault_log = FaultLog::default();
+    }
+
+    pub fn fault_log(&self) -> FaultLog {
+        self.fault_log
+    }
+
+    fn inject(&mut self, pick: impl Fn(&FaultConfig) -> f64) -> bool {
+        match self.faults.as_mut() {
+            Some((config, rng)) => {
+                let probability = pick(config);
+                rng.chance(probability)
+            }
+            None => false,
+        }
+    }
+
+    // Enable the long-range front sensor, 0 disables it
+    pub fn set_range(&mut self, range: usize) {
+        self.range = range;
+    }
+
+    pub fn get_maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn get_location(&self) -> Location {
+        self.location
+    }
+
+    pub fn set_location(&mut self, location: Location) {
+        self.location = location;
+    }
+
+    fn wall(&self, direction: Direction) -> Wall {
+        let compass = self.location.dir.turn(direction);
+        self.maze
+            .get(self.location.pos.y, self.location.pos.x, compass)
+    }
+
+    // Walls in front of the mouse up to `max_cells` ahead
+    pub fn cast_ray(&self, max_cells: usize) -> RangeReading {
+        let compass = self.location.dir;
+        let mut pos = self.location.pos;
+        let mut open_cells = 0;
+        while open_cells < max_cells {
+            if self.maze.get(pos.y, pos.x, compass) == Wall::Present {
+                return RangeReading {
+                    open_cells,
+                    wall: true,
+                };
+            }
+            match self.maze.get_neighbor_cell(pos.y, pos.x, compass) {
+                Some((y, x)) => pos = Position::new(x, y),
+                None => break,
+            }
+            open_cells += 1;
+        }
+        RangeReading {
+            open_cells,
+            wall: false,
+        }
+    }
+
+    pub fn sense(&self) -> Observation {
+        Observation {
+            front: self.wall(Direction::Forward),
+            left: self.wall(Direction::Left),
+            right: self.wall(Direction::Right),
+            ahead: (self.range > 0).then(|| self.cast_ray(self.range)),
+        }
+    }
+
+    // Turn and move one cell. Fails (and stays put) if a wall is in the way.
+    pub fn step(&mut self, direction: Direction) -> Result<Location, String> {
+        if self.wall(direction) == Wall::Present {
+            return Err(format!(
+                "Wall in the way at {} going {}",
+                self.location,
+                direction.to_log()
+            ));
+        }
+        let mut next = self.location;
+        next.turn(direction);
+        next.forward();
+        self.location = next;
+        Ok(next)
+    }
+
+    // Run the finder from its current location until it reaches `goal`.
+    // Returns the number of steps, failed moves included.
+    pub fn run(
+        &mut self,
+        finder: &mut dyn PathFinder,
+        goal: Position,
+        max_steps: usize,
+    ) -> Result<usize, String> {
+        self.location = finder.get_location();
+        let mut steps = 0;
+        while self.location.pos != goal {
+            if steps >= max_steps {
+                return Err(format!("Step limit {} reached", max_steps));
+            }
+            steps += 1;
+            self.tick(finder, goal)?;
+        }
+        Ok(steps)
+    }
+
+    // One step of run: sense, ask the finder for a direction and move,
+    // faults included. The finder must be on the simulated location.
+    pub fn tick(&mut self, finder: &mut dyn PathFinder, goal: Position) -> Result<(), String> {
+        if self.inject(|f| f.restart) {
+            self.fault_log.restarts += 1;
+            finder.reset(true);
+            self.location = finder.get_location();
+            return Ok(());
+        }
+        let observation = if self.inject(|f| f.delayed_walls) {
+            self.fault_log.delayed_walls += 1;
+            Observation::new(Wall::Unexplored, Wall::Unexplored, Wall::Unexplored)
+        } else {
+            self.sense()
+        };
+        let direction = finder
+            .navigate_observed(&observation, goal)
+            .map_err(|e| e.to_string())?;
+
+        if self.inject(|f| f.missed_move) {
+            self.fault_log.missed_moves += 1;
+            finder.report_move_failure(MoveFailure::Aborted);
+            return Ok(());
+        }
+        if self.inject(|f| f.spurious_turn) {
+            self.fault_log.spurious_turns += 1;
+            let turn = match self.faults.as_mut() {
+                Some((_, rng)) => [Direction::Left, Direction::Right][rng.below(2)],
+                None => Direction::Left,
+            };
+            self.location.turn(turn);
+            finder.report_move_failure(MoveFailure::Displaced(self.location));
+            return Ok(());
+        }
+        let location = self.step(direction)?;
+        finder.set_location(location);
+        Ok(())
+    }
+}
+
+// Who moves first in each round of a MultiSimulator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interleave {
+    // Always in the order the mice were added
+    Fixed,
+    // The first mover rotates every round
+    Rotating,
+}
+
+// What happens when a mouse wants to enter a cell occupied by another one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collision {
+    // Stay put for this round; the finder gets MoveFailure::Aborted
+    Wait,
+    // Mice pass through each other
+    Ignore,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct MouseResult {
+    pub steps: usize,
+    // Rounds spent waiting for another mouse
+    pub waits: usize,
+    pub reached: bool,
+}
+
+struct Mouse<'a> {
+    finder: Box<dyn PathFinder + 'a>,
+    goal: Position,
+    location: Location,
+    result: MouseResult,
+}
+
+/*
+    Several independent finders in the same maze. Each round every mouse
+    that has not reached its goal senses and makes one move, in the order
+    given by Interleave. A mouse that reaches its goal is taken out of the
+    maze, so it no longer blocks anyone.
+*/
+pub struct MultiSimulator<'a> {
+    sim: Simulator,
+    mice: Vec<Mouse<'a>>,
+    interleave: Interleave,
+    collision: Collision,
+}
+
+impl<'a> MultiSimulator<'a> {
+    pub fn new(maze: Maze, interleave: Interleave, collision: Collision) -> Self {
+        MultiSimulator {
+            sim: Simulator::new(maze),
+            mice: vec![],
+            interleave,
+            collision,
+        }
+    }
+
+    // Add a mouse at the finder's location; returns its index
+    pub fn add(&mut self, finder: Box<dyn PathFinder + 'a>, goal: Position) -> usize {
+        self.mice.push(Mouse {
+            location: finder.get_location(),
+            finder,
+            goal,
+            result: MouseResult::default(),
+        });
+        self.mice.len() - 1
+    }
+
+    pub fn finder(&self, index: usize) -> &dyn PathFinder {
+        self.mice[index].finder.as_ref()
+    }
+
+    pub fn location(&self, index: usize) -> Location {
+        self.mice[index].location
+    }
+
+    fn occupied(&self, pos: Position, except: usize) -> bool {
+        self.mice
+            .iter()
+            .enumerate()
+            .any(|(i, mouse)| i != except && !mouse.result.reached && mouse.location.pos == pos)
+    }
+
+    // One move of one mouse
+    fn move_mouse(&mut self, index: usize) -> Result<(), String> {
+        let mouse = &mut self.mice[index];
+        if mouse.location.pos == mouse.goal {
+            mouse.result.reached = true;
+            return Ok(());
+        }
+        self.sim.set_location(mouse.location);
+        let observation = self.sim.sense();
+        let direction = mouse
+            .finder
+            .navigate_observed(&observation, mouse.goal)
+            .map_err(|e| format!("Mouse {}: {}", index, e))?;
+
+        let mut next = mouse.location;
+        next.turn(direction);
+        next.forward();
+        if self.collision == Collision::Wait && self.occupied(next.pos, index) {
+            let mouse = &mut self.mice[index];
+            mouse.result.waits += 1;
+            mouse.finder.report_move_failure(MoveFailure::Aborted);
+            return Ok(());
+        }
+        let location = self
+            .sim
+            .step(direction)
+            .map_err(|e| format!("Mouse {}: {}", index, e))?;
+        let mouse = &mut self.mice[index];
+        mouse.location = location;
+        mouse.finder.set_location(location);
+        mouse.result.steps += 1;
+        if location.pos == mouse.goal {
+            mouse.result.reached = true;
+        }
+        Ok(())
+    }
+
+    // Run until every mouse has reached its goal or `max_rounds` rounds
+    // have passed. A navigation error of any mouse stops the run, so does
+    // a round in which every mouse had to wait (deadlock).
+    pub fn run(&mut self, max_rounds: usize) -> Result<Vec<MouseResult>, String> {
+        let count = self.mice.len();
+        for round in 0..max_rounds {
+            if self.mice.iter().all(|mouse| mouse.result.reached) {
+                break;
+            }
+            let first = match self.interleave {
+                Interleave::Fixed => 0,
+                Interleave::Rotating => round % count,
+            };
+            let waits: usize = self.mice.iter().map(|mouse| mouse.result.waits).sum();
+            let mut moved = false;
+            for i in (0..count).map(|i| (first + i) % count) {
+                if !self.mice[i].result.reached {
+                    let steps = self.mice[i].result.steps;
+                    self.move_mouse(i)?;
+                    moved |= self.mice[i].result.steps != steps;
+                }
+            }
+            let waited = self
+                .mice
+                .iter()
+                .map(|mouse| mouse.result.waits)
+                .sum::<usize>()
+                != waits;
+            if waited && !moved {
+                return Err(format!("Deadlock in round {}", round));
+            }
+        }
+        Ok(self.mice.iter().map(|mouse| mouse.result).collect())
+    }
+}
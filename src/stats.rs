@@ -0,0 +1,113 @@
+use crate::adachi::{Adachi, TieBreak};
+use crate::maze::{Compass, Maze, Position, Wall};
+use crate::similarity;
+
+// Structural statistics of a fully known maze (unexplored walls count as present)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MazeStats {
+    pub cells: usize,
+    // Steps of the shortest start-goal route, None without a route
+    pub shortest_path_length: Option<u16>,
+    // Turns along that route (straight moves preferred on ties)
+    pub turn_count: Option<u16>,
+    pub dead_end_count: usize,
+    // Longest corridor leading into a dead end, in cells
+    pub false_corridor_depth: usize,
+    pub wall_count: usize,
+}
+
+impl MazeStats {
+    /*
+        Difficulty score, higher is harder:
+
+            (path + 2 * turns + 4 * false_corridor + dead_ends) / sqrt(cells)
+
+        Turns cost more than straight steps (slower runs, more decisions),
+        deep false corridors are the main trap for a search. The square root
+        keeps 16x16 and 32x32 mazes on a comparable scale.
+        None when the goal cannot be reached.
+    */
+    pub fn difficulty(&self) -> Option<f64> {
+        let path = self.shortest_path_length? as f64;
+        let turns = self.turn_count? as f64;
+        let raw = path
+            + 2.0 * turns
+            + 4.0 * self.false_corridor_depth as f64
+            + self.dead_end_count as f64;
+        Some(raw / (self.cells as f64).sqrt())
+    }
+}
+
+fn open_sides(maze: &Maze, pos: Position) -> Vec<Compass> {
+    Compass::iter()
+        .filter(|c| maze.get(pos.y, pos.x, *c) == Wall::Absent)
+        .collect()
+}
+
+// Walk from a dead end until a cell with more than two openings
+fn corridor_depth(maze: &Maze, dead_end: Position) -> usize {
+    let mut depth = 1;
+    let mut pos = dead_end;
+    let mut came_from: Option<Compass> = None;
+    loop {
+        let exits: Vec<Compass> = open_sides(maze, pos)
+            .into_iter()
+            .filter(|c| Some(*c) != came_from)
+            .collect();
+        if exits.len() != 1 {
+            return depth;
+        }
+        let Some((y, x)) = maze.get_neighbor_cell(pos.y, pos.x, exits[0]) else {
+            return depth;
+        };
+        let next = Position::new(x, y);
+        if open_sides(maze, next).len() != 2 || next == maze.get_goal() {
+            return depth;
+        }
+        came_from = Some(exits[0].opposite());
+        pos = next;
+        depth += 1;
+    }
+}
+
+pub fn stats(maze: &Maze) -> MazeStats {
+    let mut solver = Adachi::with_known_maze(maze.clone(), maze.get_start());
+    solver.set_tie_break(TieBreak::PreferStraight);
+    let route = solver
+        .plan_fast_run(maze.get_start().pos, maze.get_goal())
+        .ok();
+    let turn_count = route.map(|route| {
+        let mut heading = maze.get_start().dir;
+        let mut turns = 0;
+        for pair in route.windows(2) {
+            let compass = crate::path::compass_between(pair[0], pair[1]).unwrap_or(heading);
+            if compass != heading {
+                turns += 1;
+                heading = compass;
+            }
+        }
+        turns
+    });
+
+    let mut false_corridor_depth = 0;
+    for y in 0..maze.get_height() {
+        for x in 0..maze.get_width() {
+            let pos = Position::new(x, y);
+            if pos != maze.get_start().pos
+                && pos != maze.get_goal()
+                && open_sides(maze, pos).len() == 1
+            {
+                false_corridor_depth = false_corridor_depth.max(corridor_depth(maze, pos));
+            }
+        }
+    }
+
+    MazeStats {
+        cells: maze.get_width() * maze.get_height(),
+        shortest_path_length: similarity::shortest_path_length(maze),
+        turn_count,
+        dead_end_count: similarity::dead_end_count(maze),
+        false_corridor_depth,
+        wall_count: similarity::wall_count(maze),
+    }
+}
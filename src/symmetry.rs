@@ -0,0 +1,151 @@
+use crate::maze::{Compass, Location, Maze, Position, StartWall};
+
+/*
+    The 8 symmetries of a rectangle (dihedral group D4): an optional mirror
+    (x -> width - 1 - x, East <-> West) followed by clockwise quarter turns.
+    An odd number of turns swaps width and height.
+
+    Fingerprints only cover the walls, not the start or the goal, so the
+    same maze entered in a different orientation has the same canonical
+    fingerprint.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Symmetry {
+    pub mirrored: bool,
+    // Clockwise, 0 - 3
+    pub quarter_turns: u8,
+}
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry {
+        mirrored: false,
+        quarter_turns: 0,
+    };
+
+    pub fn all() -> impl Iterator<Item = Symmetry> {
+        [false, true].into_iter().flat_map(|mirrored| {
+            (0..4).map(move |quarter_turns| Symmetry {
+                mirrored,
+                quarter_turns,
+            })
+        })
+    }
+
+    // Size of the maze after the transform
+    pub fn size(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.quarter_turns % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    pub fn compass(&self, compass: Compass) -> Compass {
+        let compass = match (self.mirrored, compass) {
+            (true, Compass::East) => Compass::West,
+            (true, Compass::West) => Compass::East,
+            (_, c) => c,
+        };
+        compass.rotate(self.quarter_turns as i32)
+    }
+
+    // Where the cell `pos` of a width x height maze ends up
+    pub fn position(&self, pos: Position, width: usize, height: usize) -> Position {
+        let (mut x, mut y) = (pos.x, pos.y);
+        let (mut w, mut h) = (width, height);
+        if self.mirrored {
+            x = w - 1 - x;
+        }
+        for _ in 0..self.quarter_turns % 4 {
+            // Clockwise with y pointing north: (x, y) -> (y, w - 1 - x)
+            (x, y) = (y, w - 1 - x);
+            (w, h) = (h, w);
+        }
+        Position::new(x, y)
+    }
+
+    pub fn location(&self, location: Location, width: usize, height: usize) -> Location {
+        Location::new(
+            self.position(location.pos, width, height),
+            self.compass(location.dir),
+        )
+    }
+}
+
+// Copy of `maze` with walls, start and goal moved by `symmetry`
+pub fn transform(maze: &Maze, symmetry: Symmetry) -> Maze {
+    let (width, height) = (maze.get_width(), maze.get_height());
+    let (new_width, new_height) = symmetry.size(width, height);
+    let mut options = maze.get_init_options();
+    if symmetry.mirrored {
+        // Seen from the start heading, the mandatory wall changes side
+        options.start_wall = match options.start_wall {
+            StartWall::Right => StartWall::Left,
+            StartWall::Left => StartWall::Right,
+            StartWall::None => StartWall::None,
+        };
+    }
+    let mut result = Maze::with_options(new_width, new_height, options);
+    for y in 0..height {
+        for x in 0..width {
+            let pos = symmetry.position(Position::new(x, y), width, height);
+            for compass in Compass::iter() {
+                result.set(
+                    pos.y,
+                    pos.x,
+                    symmetry.compass(compass),
+                    maze.get(y, x, compass),
+                );
+            }
+        }
+    }
+    for blocked in maze.blocked_cells() {
+        let pos = symmetry.position(blocked, width, height);
+        result.set_cell_exists(pos.y, pos.x, false);
+    }
+    result.set_start_location(symmetry.location(maze.get_start(), width, height));
+    result.set_goal(symmetry.position(maze.get_goal(), width, height));
+    result
+}
+
+// Size (2 x u16 little endian) followed by Maze::packed_walls and, if any
+// cell is blocked, Maze::packed_blocked. Comparable with the usual
+// ordering of byte vectors.
+pub fn fingerprint(maze: &Maze) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(maze.get_width() as u16).to_le_bytes());
+    bytes.extend_from_slice(&(maze.get_height() as u16).to_le_bytes());
+    bytes.extend(maze.packed_walls());
+    bytes.extend(maze.packed_blocked());
+    bytes
+}
+
+// Symmetries that leave the walls unchanged, the identity included
+pub fn symmetries(maze: &Maze) -> Vec<Symmetry> {
+    let own = fingerprint(maze);
+    Symmetry::all()
+        .filter(|s| fingerprint(&transform(maze, *s)) == own)
+        .collect()
+}
+
+// True if some symmetry other than the identity maps the walls onto themselves
+pub fn is_symmetric(maze: &Maze) -> bool {
+    symmetries(maze).len() > 1
+}
+
+// The orientation with the smallest fingerprint and the symmetry that
+// produces it from `maze`. Ties go to the first symmetry in Symmetry::all.
+pub fn canonical_form(maze: &Maze) -> (Maze, Symmetry) {
+    Symmetry::all()
+        .map(|s| (transform(maze, s), s))
+        .min_by(|(a, _), (b, _)| fingerprint(a).cmp(&fingerprint(b)))
+        .unwrap()
+}
+
+// Equal for every orientation of the same maze, use as a dedup key
+pub fn canonical_fingerprint(maze: &Maze) -> Vec<u8> {
+    Symmetry::all()
+        .map(|s| fingerprint(&transform(maze, s)))
+        .min()
+        .unwrap()
+}
@@ -0,0 +1,190 @@
+use crate::maze::{Compass, Location, Maze, Wall, WallChange};
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{NoCallback, ServerHandshake};
+use tungstenite::handshake::{HandshakeError, MidHandshake};
+use tungstenite::{Message, WebSocket};
+
+// Handshakes not done by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+type PendingHandshake = MidHandshake<ServerHandshake<TcpStream, NoCallback>>;
+
+/*
+    JSON messages of the telemetry WebSocket. A new client first gets a
+    snapshot, then only deltas:
+      {"type":"snapshot","maze":{...},"step_map":[[...]],"pose":{...}}
+      {"type":"wall","x":1,"y":2,"compass":"North","wall":"Present"}
+      {"type":"pose","pose":{"pos":{"x":0,"y":1},"dir":"North"}}
+      {"type":"step_map","step_map":[[...]]}
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    Snapshot {
+        maze: Box<Maze>,
+        step_map: Option<Vec<Vec<u16>>>,
+        pose: Location,
+    },
+    Wall {
+        x: usize,
+        y: usize,
+        compass: Compass,
+        wall: Wall,
+    },
+    Pose {
+        pose: Location,
+    },
+    StepMap {
+        step_map: Vec<Vec<u16>>,
+    },
+}
+
+impl From<WallChange> for TelemetryEvent {
+    fn from(change: WallChange) -> Self {
+        TelemetryEvent::Wall {
+            x: change.x,
+            y: change.y,
+            compass: change.compass,
+            wall: change.new,
+        }
+    }
+}
+
+/*
+    WebSocket server for a browser dashboard. Wall deltas come from the
+    maze's change notifications (Maze::subscribe / Adachi::subscribe),
+    pose and step map are compared with what was sent last. Nothing runs
+    in the background: call publish() from the control loop, e.g. once
+    per step. Clients that fail to receive are dropped. Handshakes never
+    block publish: they advance a little on every call and are abandoned
+    after HANDSHAKE_TIMEOUT.
+*/
+pub struct TelemetryServer {
+    listener: TcpListener,
+    clients: Vec<WebSocket<TcpStream>>,
+    handshakes: Vec<(Instant, PendingHandshake)>,
+    changes: mpsc::Receiver<WallChange>,
+    pose: Option<Location>,
+    step_map: Option<Vec<Vec<u16>>>,
+}
+
+impl TelemetryServer {
+    // `changes` is the receiver of the maze given to publish
+    pub fn bind(addr: &str, changes: mpsc::Receiver<WallChange>) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+        Ok(TelemetryServer {
+            listener,
+            clients: Vec::new(),
+            handshakes: Vec::new(),
+            changes,
+            pose: None,
+            step_map: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    // Advance the handshakes without blocking; returns the new clients
+    fn accept_clients(&mut self) -> Vec<WebSocket<TcpStream>> {
+        let now = Instant::now();
+        let mut attempts = vec![];
+        while let Ok((stream, peer)) = self.listener.accept() {
+            match stream.set_nonblocking(true) {
+                Ok(_) => attempts.push((now, tungstenite::accept(stream))),
+                Err(e) => log::warn!("Telemetry: cannot accept {}: {}", peer, e),
+            }
+        }
+        for (since, pending) in self.handshakes.drain(..) {
+            if now.duration_since(since) > HANDSHAKE_TIMEOUT {
+                log::warn!("Telemetry: handshake timed out");
+            } else {
+                attempts.push((since, pending.handshake()));
+            }
+        }
+        let mut ready = vec![];
+        for (since, attempt) in attempts {
+            match attempt {
+                Ok(client) => {
+                    // Blocking again, but a stalled client cannot hold up a send for long
+                    let stream = client.get_ref();
+                    match stream
+                        .set_nonblocking(false)
+                        .and_then(|_| stream.set_write_timeout(Some(Duration::from_millis(500))))
+                    {
+                        Ok(_) => ready.push(client),
+                        Err(e) => log::warn!("Telemetry: cannot set up client: {}", e),
+                    }
+                }
+                Err(HandshakeError::Interrupted(pending)) => self.handshakes.push((since, pending)),
+                Err(HandshakeError::Failure(e)) => log::warn!("Telemetry: handshake failed: {}", e),
+            }
+        }
+        ready
+    }
+
+    fn broadcast(&mut self, event: &TelemetryEvent) {
+        self.clients.retain_mut(|client| send(client, event));
+    }
+
+    // Accept new clients and send what changed since the last call
+    pub fn publish(&mut self, maze: &Maze, pose: Location, step_map: Option<&[Vec<u16>]>) {
+        let walls: Vec<WallChange> = self.changes.try_iter().collect();
+        // Clients accepted now already see the changes in the snapshot,
+        // which is only built when there are such clients
+        let mut fresh = self.accept_clients();
+        if !fresh.is_empty() {
+            let snapshot = TelemetryEvent::Snapshot {
+                maze: Box::new(maze.clone()),
+                step_map: step_map.map(|s| s.to_vec()),
+                pose,
+            };
+            fresh.retain_mut(|client| send(client, &snapshot));
+        }
+        let mut events: Vec<TelemetryEvent> = walls
+            .into_iter()
+            .filter(|change| change.old != change.new)
+            .map(TelemetryEvent::from)
+            .collect();
+        if self.pose != Some(pose) {
+            self.pose = Some(pose);
+            events.push(TelemetryEvent::Pose { pose });
+        }
+        if let Some(step_map) = step_map {
+            if self.step_map.as_deref() != Some(step_map) {
+                self.step_map = Some(step_map.to_vec());
+                events.push(TelemetryEvent::StepMap {
+                    step_map: step_map.to_vec(),
+                });
+            }
+        }
+        for event in &events {
+            self.broadcast(event);
+        }
+        self.clients.append(&mut fresh);
+    }
+}
+
+// False if the client is gone
+fn send(client: &mut WebSocket<TcpStream>, event: &TelemetryEvent) -> bool {
+    let text = match serde_json::to_string(event) {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Telemetry: cannot serialize {:?}: {}", event, e);
+            return true;
+        }
+    };
+    client.send(Message::text(text)).is_ok()
+}
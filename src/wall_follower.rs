@@ -0,0 +1,94 @@
+use crate::maze::{Direction, Location, Maze, Position, Wall};
+use crate::path_finder::{NavigationError, NavigationStep, PathFinder};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+// Classic wall follower: keep one hand on the wall. Needs no map, but the
+// observed walls are recorded so get_maze still shows what was seen.
+// Does not reach goals in the middle of a loop (e.g. a center goal
+// surrounded by an island); mainly a last-resort fallback.
+pub struct WallFollower {
+    hand: Hand,
+    maze: Maze,
+    location: Location,
+}
+
+impl WallFollower {
+    pub fn new(maze: Maze, hand: Hand) -> Self {
+        WallFollower {
+            hand,
+            location: maze.get_start(),
+            maze,
+        }
+    }
+}
+
+impl PathFinder for WallFollower {
+    fn navigate_step(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+        goal: Position,
+    ) -> Result<NavigationStep, NavigationError> {
+        if goal == self.location.pos {
+            return Ok(NavigationStep::goal_reached());
+        }
+        let (pos, dir) = (self.location.pos, self.location.dir);
+        for (direction, wall) in [
+            (Direction::Forward, front),
+            (Direction::Left, left),
+            (Direction::Right, right),
+        ] {
+            if wall != Wall::Unexplored {
+                self.maze.set(pos.y, pos.x, dir.turn(direction), wall);
+            }
+        }
+
+        let order = match self.hand {
+            Hand::Left => [Direction::Left, Direction::Forward, Direction::Right],
+            Hand::Right => [Direction::Right, Direction::Forward, Direction::Left],
+        };
+        let open = |direction: Direction| match direction {
+            Direction::Forward => front == Wall::Absent,
+            Direction::Left => left == Wall::Absent,
+            Direction::Right => right == Wall::Absent,
+            Direction::Backward => false,
+        };
+        let direction = order
+            .into_iter()
+            .find(|d| open(*d))
+            .unwrap_or(Direction::Backward);
+        if direction == Direction::Backward
+            && self.maze.get(pos.y, pos.x, dir.turn(Direction::Backward)) == Wall::Present
+        {
+            return Err(NavigationError::NoPath);
+        }
+        Ok(NavigationStep::go(direction, None))
+    }
+
+    fn get_location(&self) -> Location {
+        self.location
+    }
+
+    fn set_location(&mut self, location: Location) {
+        self.location = location;
+    }
+
+    fn get_maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    fn reset(&mut self, keep_map: bool) {
+        if !keep_map {
+            let goal = self.maze.get_goal();
+            self.maze.init();
+            self.maze.set_goal(goal);
+        }
+        self.location = self.maze.get_start();
+    }
+}
@@ -0,0 +1,288 @@
+use crate::maze::{Compass, Maze, Wall};
+use serde::{Deserialize, Serialize};
+
+/*
+    Wall list representation: one record per wall segment.
+
+    Horizontal wall (x, y) lies on the south side of cell (x, y),
+    y = height is the outer wall on the north side of the top row.
+    Vertical wall (x, y) lies on the west side of cell (x, y),
+    x = width is the outer wall on the east side of the right column.
+
+    Cells that do not exist (Maze::set_cell_exists) follow the walls as
+    Blocked records of the cell (x, y); their state is always Present.
+
+    CSV example:
+    x,y,orientation,state
+    0,0,Horizontal,Present
+    1,0,Vertical,Absent
+    3,3,Blocked,Present
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    Blocked,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WallRecord {
+    pub x: usize,
+    pub y: usize,
+    pub orientation: Orientation,
+    pub state: Wall,
+}
+
+// A wall known in both mazes with different states
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WallDiff {
+    pub x: usize,
+    pub y: usize,
+    pub orientation: Orientation,
+    pub mine: Wall,
+    pub reference: Wall,
+}
+
+// Why two mazes are not consistent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Inconsistency {
+    // (width, height) of each maze
+    SizeMismatch {
+        mine: (usize, usize),
+        reference: (usize, usize),
+    },
+    // Known walls that disagree, never empty
+    Walls(Vec<WallDiff>),
+}
+
+impl WallRecord {
+    // Convert to the cell + compass addressing used by Maze::get / Maze::set
+    fn to_cell(self, width: usize, height: usize) -> Option<(usize, usize, Compass)> {
+        match self.orientation {
+            Orientation::Horizontal if self.x < width && self.y < height => {
+                Some((self.y, self.x, Compass::South))
+            }
+            Orientation::Horizontal if self.x < width && self.y == height => {
+                Some((self.y - 1, self.x, Compass::North))
+            }
+            Orientation::Vertical if self.y < height && self.x < width => {
+                Some((self.y, self.x, Compass::West))
+            }
+            Orientation::Vertical if self.y < height && self.x == width => {
+                Some((self.y, self.x - 1, Compass::East))
+            }
+            Orientation::Horizontal | Orientation::Vertical | Orientation::Blocked => None,
+        }
+    }
+}
+
+impl Maze {
+    pub fn to_wall_list(&self) -> Vec<WallRecord> {
+        let (width, height) = (self.get_width(), self.get_height());
+        let mut list = Vec::new();
+        for y in 0..height + 1 {
+            for x in 0..width {
+                let state = if y < height {
+                    self.get(y, x, Compass::South)
+                } else {
+                    self.get(y - 1, x, Compass::North)
+                };
+                list.push(WallRecord {
+                    x,
+                    y,
+                    orientation: Orientation::Horizontal,
+                    state,
+                });
+            }
+        }
+        for y in 0..height {
+            for x in 0..width + 1 {
+                let state = if x < width {
+                    self.get(y, x, Compass::West)
+                } else {
+                    self.get(y, x - 1, Compass::East)
+                };
+                list.push(WallRecord {
+                    x,
+                    y,
+                    orientation: Orientation::Vertical,
+                    state,
+                });
+            }
+        }
+        list
+    }
+
+    // Blocked records of the cells that do not exist
+    pub fn to_blocked_list(&self) -> Vec<WallRecord> {
+        self.blocked_cells()
+            .into_iter()
+            .map(|pos| WallRecord {
+                x: pos.x,
+                y: pos.y,
+                orientation: Orientation::Blocked,
+                state: Wall::Present,
+            })
+            .collect()
+    }
+
+    // Ok if every wall known in both mazes agrees; unexplored walls on
+    // either side are ignored. Mazes of different sizes are never
+    // consistent.
+    pub fn is_consistent_with(&self, reference: &Maze) -> Result<(), Inconsistency> {
+        let mine = (self.get_width(), self.get_height());
+        let theirs = (reference.get_width(), reference.get_height());
+        if mine != theirs {
+            return Err(Inconsistency::SizeMismatch {
+                mine,
+                reference: theirs,
+            });
+        }
+        let diffs: Vec<WallDiff> = self
+            .to_wall_list()
+            .into_iter()
+            .zip(reference.to_wall_list())
+            .filter(|(a, b)| {
+                a.state != Wall::Unexplored && b.state != Wall::Unexplored && a.state != b.state
+            })
+            .map(|(a, b)| WallDiff {
+                x: a.x,
+                y: a.y,
+                orientation: a.orientation,
+                mine: a.state,
+                reference: b.state,
+            })
+            .collect();
+        if diffs.is_empty() {
+            Ok(())
+        } else {
+            Err(Inconsistency::Walls(diffs))
+        }
+    }
+
+    // Walls missing from the list keep their initial state (see Maze::init)
+    pub fn from_wall_list(
+        width: usize,
+        height: usize,
+        list: &[WallRecord],
+    ) -> Result<Maze, String> {
+        let mut maze = Maze::new(width, height);
+        let mut blocked = vec![];
+        for record in list {
+            if record.orientation == Orientation::Blocked {
+                if record.x >= width || record.y >= height || record.state != Wall::Present {
+                    return Err(format!(
+                        "Invalid blocked cell ({}, {}) {:?} in a {}x{} maze",
+                        record.x, record.y, record.state, width, height
+                    ));
+                }
+                blocked.push((record.y, record.x));
+                continue;
+            }
+            match record.to_cell(width, height) {
+                Some((y, x, compass)) => maze.set(y, x, compass, record.state),
+                None => {
+                    return Err(format!(
+                        "Wall {:?} ({}, {}) is out of a {}x{} maze",
+                        record.orientation, record.x, record.y, width, height
+                    ))
+                }
+            }
+        }
+        for (y, x) in blocked {
+            maze.set_cell_exists(y, x, false);
+        }
+        Ok(maze)
+    }
+
+    // Known-walls exchange for tools without a notion of "unknown": only
+    // present walls are listed, absent and unexplored ones are left out
+    pub fn to_present_wall_list(&self) -> Vec<WallRecord> {
+        self.to_wall_list()
+            .into_iter()
+            .filter(|r| r.state == Wall::Present)
+            .collect()
+    }
+
+    // Counterpart of to_present_wall_list: listed walls get their state,
+    // inner walls missing from the list are unexplored (the start side
+    // wall of Maze::init stays present)
+    pub fn from_present_wall_list(
+        width: usize,
+        height: usize,
+        list: &[WallRecord],
+    ) -> Result<Maze, String> {
+        Maze::from_wall_list(width, height, list)
+    }
+
+    // Same maze with every absent inner wall turned into an unexplored one,
+    // for maze text or other formats where "no wall" may just mean "not seen"
+    pub fn absent_as_unexplored(&self) -> Maze {
+        let mut maze = self.clone();
+        for y in 0..maze.get_height() {
+            for x in 0..maze.get_width() {
+                for compass in [Compass::North, Compass::East] {
+                    if maze.get_neighbor_cell(y, x, compass).is_some()
+                        && maze.get(y, x, compass) == Wall::Absent
+                    {
+                        maze.set(y, x, compass, Wall::Unexplored);
+                    }
+                }
+            }
+        }
+        maze
+    }
+
+    pub fn write_present_wall_list_csv(&self, writer: impl std::io::Write) -> Result<(), String> {
+        let mut list = self.to_present_wall_list();
+        list.extend(self.to_blocked_list());
+        write_csv(writer, list)
+    }
+
+    pub fn read_present_wall_list_csv(
+        reader: impl std::io::Read,
+        width: usize,
+        height: usize,
+    ) -> Result<Maze, String> {
+        Maze::from_present_wall_list(width, height, &read_csv(reader)?)
+    }
+
+    pub fn write_wall_list_csv(&self, writer: impl std::io::Write) -> Result<(), String> {
+        let mut list = self.to_wall_list();
+        list.extend(self.to_blocked_list());
+        write_csv(writer, list)
+    }
+
+    pub fn read_wall_list_csv(
+        reader: impl std::io::Read,
+        width: usize,
+        height: usize,
+    ) -> Result<Maze, String> {
+        Maze::from_wall_list(width, height, &read_csv(reader)?)
+    }
+}
+
+fn write_csv(writer: impl std::io::Write, list: Vec<WallRecord>) -> Result<(), String> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for record in list {
+        if let Err(e) = csv_writer.serialize(record) {
+            return Err(e.to_string());
+        }
+    }
+    match csv_writer.flush() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn read_csv(reader: impl std::io::Read) -> Result<Vec<WallRecord>, String> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let mut list = Vec::new();
+    for record in csv_reader.deserialize() {
+        match record {
+            Ok(r) => list.push(r),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(list)
+}
@@ -0,0 +1,87 @@
+use crate::maze::{Direction, Position, Wall};
+use crate::path_finder::PathFinder;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WaypointEvent {
+    Reached { index: usize, position: Position },
+    // All waypoints have been visited
+    Completed,
+}
+
+// Visit an ordered list of cells (e.g. goal -> start -> goal) with any PathFinder
+pub struct Waypoints<P: PathFinder> {
+    finder: P,
+    targets: Vec<Position>,
+    current: usize,
+    events: Vec<WaypointEvent>,
+}
+
+impl<P: PathFinder> Waypoints<P> {
+    pub fn new(finder: P, targets: Vec<Position>) -> Self {
+        Waypoints {
+            finder,
+            targets,
+            current: 0,
+            events: vec![],
+        }
+    }
+
+    pub fn current_target(&self) -> Option<Position> {
+        self.targets.get(self.current).copied()
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.targets.len()
+    }
+
+    // Returns the next direction, or None once every waypoint has been reached.
+    // Waypoints reached at the current location are skipped and reported as events.
+    pub fn navigate(
+        &mut self,
+        front: Wall,
+        left: Wall,
+        right: Wall,
+    ) -> anyhow::Result<Option<Direction>> {
+        let pos = self.finder.get_location().pos;
+        while let Some(target) = self.current_target() {
+            if target != pos {
+                break;
+            }
+            log::info!("Waypoint {} reached: {:?}", self.current, target);
+            self.events.push(WaypointEvent::Reached {
+                index: self.current,
+                position: target,
+            });
+            self.current += 1;
+            if self.is_finished() {
+                self.events.push(WaypointEvent::Completed);
+            }
+        }
+
+        match self.current_target() {
+            Some(target) => Ok(Some(self.finder.navigate(front, left, right, target)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<WaypointEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn finder(&self) -> &P {
+        &self.finder
+    }
+
+    pub fn finder_mut(&mut self) -> &mut P {
+        &mut self.finder
+    }
+
+    pub fn into_finder(self) -> P {
+        self.finder
+    }
+}